@@ -8,7 +8,7 @@
 //!
 //! The CLI follows a nested command pattern:
 //! - Global options (environment, verbosity, etc.)
-//! - Top-level commands (package, config)
+//! - Top-level commands (package, set, apply, config)
 //! - Subcommands (install, check, list, etc.)
 //!
 //! # Examples
@@ -34,7 +34,8 @@ pub struct ClapCli {
     /// Override the target environment from configuration file
     ///
     /// Specifies which environment configuration to use for package operations.
-    /// This overrides the environment setting in the config file.
+    /// This overrides the environment setting in the config file, including an
+    /// environment detected with `environment: auto`.
     ///
     /// Example: --environment=macos, --environment=linux
     #[clap(long, short = 'e', global = true)]
@@ -81,6 +82,41 @@ pub(crate) enum ClapCommands {
     /// This is the primary interface for package operations.
     Package(PackageCommands),
 
+    /// Package set operations
+    ///
+    /// Commands for installing, checking, and listing the named sets of packages
+    /// defined under `sets` in the configuration file.
+    Set(SetCommands),
+
+    /// Converge the machine to the packages it should have
+    ///
+    /// Checks every package in the manifest, leaves the ones that are already
+    /// installed alone, and installs the rest in dependency order, then prints a
+    /// converge report of unchanged, installed, and failed packages. Running it
+    /// again once it has succeeded changes nothing.
+    ///
+    /// The manifest is every package that supports the current environment, or
+    /// the packages in the sets given with `--set`.
+    ///
+    /// Exits with 0 when the machine matches the manifest, 1 when a package
+    /// couldn't be installed, and 2 when the manifest can't be worked out (e.g. an
+    /// unknown set), so it can be run from cron or CI.
+    ///
+    /// Example: `selfie apply` or `selfie apply --set dev --set ci`
+    Apply {
+        /// Only apply the packages in this set; can be given more than once
+        #[arg(short, long = "set", value_name = "SET")]
+        sets: Vec<String>,
+
+        /// Show what would be installed without installing anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// Undo a failed install by running the package's rollback commands
+        #[arg(long, conflicts_with = "dry_run")]
+        rollback_on_failure: bool,
+    },
+
     /// Configuration management operations
     ///
     /// Commands for validating and managing the selfie configuration file.
@@ -105,18 +141,85 @@ pub(crate) struct PackageCommands {
 /// including installation, checking, validation, and information retrieval.
 #[derive(Subcommand, Debug, Clone)]
 pub(crate) enum PackageSubcommands {
-    /// Install a package using its configured installation method
+    /// Install packages using their configured installation methods
     ///
-    /// Executes the package's installation command for the current environment.
-    /// If the package is already installed (based on its check command), the
-    /// installation may be skipped unless forced.
+    /// Executes each package's installation command for the current environment.
+    /// If a package is already installed (based on its check command), its
+    /// installation is skipped unless `--force` is given. When several packages
+    /// are given, or `--all` is used, dependencies they share are installed once.
     ///
-    /// Example: `selfie package install node`
+    /// Example: `selfie package install node ripgrep` or `selfie package install --all`
     Install {
-        /// Name of the package to install
+        /// Names of the packages to install
+        ///
+        /// Each must correspond to a package definition file in the package
+        /// directory. The package name should match the filename (without extension).
+        #[arg(required_unless_present = "all")]
+        package_names: Vec<String>,
+
+        /// Install every package that supports the current environment
+        #[arg(short, long, conflicts_with = "package_names")]
+        all: bool,
+
+        /// Run the install command even if the package is already installed
+        ///
+        /// Skips the package's check command, so the installation always runs.
+        #[arg(short, long)]
+        force: bool,
+
+        /// Show what would be installed without installing anything
+        ///
+        /// Resolves dependencies and runs only the read-only check commands, then
+        /// prints the install plan.
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// Undo a failed install by running the package's rollback commands
+        ///
+        /// When a package's install command or one of its hooks fails, its
+        /// `rollback` commands run in order to clean up whatever the install left
+        /// behind.
+        #[arg(long, conflicts_with = "dry_run")]
+        rollback_on_failure: bool,
+    },
+
+    /// Upgrade installed packages using their configured update commands
+    ///
+    /// Runs the package's update command if it's installed. Packages without an
+    /// update command are reported as not upgradable rather than reinstalled.
+    ///
+    /// With `--all`, every installed package that supports the current
+    /// environment is upgraded and a summary is shown.
+    ///
+    /// Example: `selfie package upgrade ripgrep` or `selfie package upgrade --all`
+    Upgrade {
+        /// Name of the package to upgrade
+        ///
+        /// Must correspond to a package definition file in the package directory.
+        #[arg(required_unless_present = "all")]
+        package_name: Option<String>,
+
+        /// Upgrade every package that supports the current environment
+        ///
+        /// Honors `stop_on_error`: when set, stops at the first package that fails
+        /// to upgrade; otherwise upgrades them all and reports the failures.
+        #[arg(short, long, conflicts_with = "package_name")]
+        all: bool,
+    },
+
+    /// Uninstall a package using its configured uninstall command
+    ///
+    /// Runs the package's check command first and does nothing if the package
+    /// isn't installed. Warns when other installed packages depend on it. After
+    /// uninstalling, the check command is run again to confirm the package is gone.
+    ///
+    /// Unlike `remove`, this leaves the package definition file in place.
+    ///
+    /// Example: `selfie package uninstall ripgrep`
+    Uninstall {
+        /// Name of the package to uninstall
         ///
         /// Must correspond to a package definition file in the package directory.
-        /// The package name should match the filename (without extension).
         package_name: String,
     },
 
@@ -126,12 +229,23 @@ pub(crate) enum PackageSubcommands {
     /// already installed in the current environment. This is useful for
     /// verification and before attempting installation.
     ///
-    /// Example: `selfie package check node`
+    /// With `--all`, every package that supports the current environment is
+    /// checked and a summary of installed, missing, and failed packages is shown.
+    ///
+    /// Example: `selfie package check node` or `selfie package check --all`
     Check {
         /// Name of the package to check for installation
         ///
         /// Must correspond to a package definition file in the package directory.
-        package_name: String,
+        #[arg(required_unless_present = "all")]
+        package_name: Option<String>,
+
+        /// Check every package that supports the current environment
+        ///
+        /// Honors `stop_on_error`: when set, stops at the first package that can't
+        /// be checked; otherwise checks them all and reports the failures.
+        #[arg(short, long, conflicts_with = "package_name")]
+        all: bool,
     },
 
     /// List all available packages in the package directory
@@ -197,7 +311,8 @@ pub(crate) enum PackageSubcommands {
     ///
     /// Permanently removes a package definition file from the package directory.
     /// This operation requires confirmation and will warn if the package is a
-    /// dependency of other packages.
+    /// dependency of other packages. It doesn't remove the software itself; use
+    /// `uninstall` for that.
     ///
     /// Example: `selfie package remove my-tool`
     Remove {
@@ -223,6 +338,71 @@ pub(crate) enum PackageSubcommands {
     },
 }
 
+/// Set command group container
+///
+/// This structure holds the subcommands that work on named package sets.
+#[derive(Args, Debug, Clone)]
+pub(crate) struct SetCommands {
+    /// The specific set operation to perform
+    #[clap(subcommand)]
+    pub(crate) command: SetSubcommands,
+}
+
+/// Operations on named package sets
+///
+/// Sets are defined in the configuration file, e.g.
+/// `sets: { dev: [git, ripgrep, neovim], ci: [git, curl] }`. When several sets are
+/// given, a package that belongs to more than one of them is only processed once,
+/// and the run ends with a summary for each set.
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum SetSubcommands {
+    /// Install every package in one or more sets
+    ///
+    /// Works like `package install` with all the sets' packages, so dependencies
+    /// they share are installed once.
+    ///
+    /// Example: `selfie set install dev` or `selfie set install dev ci`
+    Install {
+        /// Names of the sets to install
+        #[arg(required = true)]
+        set_names: Vec<String>,
+
+        /// Run the install commands even if the packages are already installed
+        #[arg(short, long)]
+        force: bool,
+
+        /// Show what would be installed without installing anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// Undo a failed install by running the package's rollback commands
+        #[arg(long, conflicts_with = "dry_run")]
+        rollback_on_failure: bool,
+    },
+
+    /// Check whether every package in one or more sets is installed
+    ///
+    /// Honors `stop_on_error`: when set, stops at the first package that can't
+    /// be checked.
+    ///
+    /// Example: `selfie set check dev`
+    Check {
+        /// Names of the sets to check
+        #[arg(required = true)]
+        set_names: Vec<String>,
+    },
+
+    /// List the packages in each set
+    ///
+    /// Lists every set when no names are given.
+    ///
+    /// Example: `selfie set list` or `selfie set list dev`
+    List {
+        /// Names of the sets to list
+        set_names: Vec<String>,
+    },
+}
+
 /// Configuration command group container
 ///
 /// This structure holds the configuration-related subcommands. It serves as
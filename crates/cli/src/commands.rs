@@ -7,7 +7,7 @@
 //! # Architecture
 //!
 //! The dispatcher follows a hierarchical routing pattern:
-//! 1. Top-level command dispatch (package vs config)
+//! 1. Top-level command dispatch (package, set, apply, or config)
 //! 2. Subcommand dispatch within each category
 //! 3. Individual command handler execution
 //!
@@ -19,15 +19,17 @@
 //! - 2: Validation/usage error
 //! - Other codes: Command-specific errors
 
+pub(crate) mod apply;
 pub(crate) mod config;
 pub(crate) mod package;
+pub(crate) mod set;
 
 use package::list::ListCommand;
-use selfie::config::AppConfig;
+use selfie::{config::AppConfig, package::service::InstallOptions};
 use tracing::debug;
 
 use crate::{
-    cli::{ClapCommands, ConfigSubcommands, PackageSubcommands},
+    cli::{ClapCommands, ConfigSubcommands, PackageSubcommands, SetSubcommands},
     terminal_progress_reporter::TerminalProgressReporter,
 };
 
@@ -51,6 +53,8 @@ use crate::{
 /// # Command Categories
 ///
 /// - **Package commands**: Install, check, list, info, create, validate packages
+/// - **Set commands**: Install, check, and list named sets of packages
+/// - **Apply**: Converge the machine to every package, or to a few sets
 /// - **Config commands**: Validate configuration files and settings
 pub(crate) async fn dispatch_command(
     command: &ClapCommands,
@@ -64,6 +68,21 @@ pub(crate) async fn dispatch_command(
         ClapCommands::Package(package_cmd) => {
             dispatch_package_command(&package_cmd.command, config, reporter).await
         }
+        ClapCommands::Set(set_cmd) => {
+            dispatch_set_command(&set_cmd.command, config, reporter).await
+        }
+        ClapCommands::Apply {
+            sets,
+            dry_run,
+            rollback_on_failure,
+        } => {
+            let options = InstallOptions {
+                force: false,
+                dry_run: *dry_run,
+                rollback_on_failure: *rollback_on_failure,
+            };
+            apply::handle_apply(sets, options, config, reporter).await
+        }
         ClapCommands::Config(config_cmd) => {
             dispatch_config_command(&config_cmd.command, original_config, reporter)
         }
@@ -89,6 +108,8 @@ pub(crate) async fn dispatch_command(
 /// # Supported Operations
 ///
 /// - `install`: Install packages using configured installation methods
+/// - `upgrade`: Upgrade installed packages using their update commands
+/// - `uninstall`: Remove installed packages using their uninstall commands
 /// - `check`: Verify if packages are already installed
 /// - `list`: Display all available packages
 /// - `info`: Show detailed package information
@@ -102,12 +123,42 @@ async fn dispatch_package_command(
     debug!("Handling package command: {:?}", command);
 
     match command {
-        PackageSubcommands::Install { package_name } => {
-            package::install::handle_install(package_name, config, reporter)
+        PackageSubcommands::Install {
+            package_names,
+            all,
+            force,
+            dry_run,
+            rollback_on_failure,
+        } => {
+            let options = InstallOptions {
+                force: *force,
+                dry_run: *dry_run,
+                rollback_on_failure: *rollback_on_failure,
+            };
+            let selection = if *all {
+                package::install::InstallSelection::All
+            } else {
+                package::install::InstallSelection::Named(package_names.clone())
+            };
+            package::install::handle_install(selection, options, config, reporter).await
         }
-        PackageSubcommands::Check { package_name } => {
-            package::check::handle_check(package_name, config, reporter).await
+        // clap guarantees exactly one of a package name or `--all`
+        PackageSubcommands::Upgrade { package_name, .. } => match package_name {
+            Some(package_name) => {
+                package::upgrade::handle_upgrade(package_name, config, reporter).await
+            }
+            None => package::upgrade::handle_upgrade_all(config, reporter).await,
+        },
+        PackageSubcommands::Uninstall { package_name } => {
+            package::uninstall::handle_uninstall(package_name, config, reporter).await
         }
+        // clap guarantees exactly one of a package name or `--all`
+        PackageSubcommands::Check { package_name, .. } => match package_name {
+            Some(package_name) => {
+                package::check::handle_check(package_name, config, reporter).await
+            }
+            None => package::check::handle_check_all(config, reporter).await,
+        },
         PackageSubcommands::List => ListCommand::new(config, reporter).handle_command().await,
         PackageSubcommands::Info { package_name } => {
             package::info::handle_info(package_name, config, reporter).await
@@ -128,6 +179,54 @@ async fn dispatch_package_command(
     }
 }
 
+/// Handle package set commands
+///
+/// Routes set-related subcommands to their handlers. Like package commands, set
+/// commands use the configuration with CLI overrides applied.
+///
+/// # Arguments
+///
+/// * `command` - The specific set subcommand to execute
+/// * `config` - Application configuration with CLI overrides applied
+/// * `reporter` - Terminal progress reporter for user feedback
+///
+/// # Returns
+///
+/// Exit code indicating command success (0) or failure (non-zero)
+///
+/// # Supported Operations
+///
+/// - `install`: Install every package in the named sets
+/// - `check`: Check every package in the named sets
+/// - `list`: Display the packages in each set
+async fn dispatch_set_command(
+    command: &SetSubcommands,
+    config: &AppConfig,
+    reporter: TerminalProgressReporter,
+) -> i32 {
+    debug!("Handling set command: {:?}", command);
+
+    match command {
+        SetSubcommands::Install {
+            set_names,
+            force,
+            dry_run,
+            rollback_on_failure,
+        } => {
+            let options = InstallOptions {
+                force: *force,
+                dry_run: *dry_run,
+                rollback_on_failure: *rollback_on_failure,
+            };
+            set::handle_set_install(set_names, options, config, reporter).await
+        }
+        SetSubcommands::Check { set_names } => {
+            set::handle_set_check(set_names, config, reporter).await
+        }
+        SetSubcommands::List { set_names } => set::handle_set_list(set_names, config, reporter),
+    }
+}
+
 /// Handle configuration management commands
 ///
 /// Routes configuration-related subcommands to their specific handlers.
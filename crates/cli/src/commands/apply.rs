@@ -0,0 +1,271 @@
+use selfie::{
+    config::AppConfig,
+    package::{
+        event::{InstallPlanAction, InstallPlanData, InstallSummaryData, PackageEvent},
+        service::{InstallOptions, PackageService},
+        set::PackageSet,
+    },
+};
+
+use crate::{
+    event_processor::EventProcessor, formatters::format_key,
+    terminal_progress_reporter::TerminalProgressReporter,
+};
+
+use super::package::{common, install};
+
+/// Exit code when the machine matches the manifest, whether or not anything had
+/// to be installed
+pub(crate) const EXIT_CONVERGED: i32 = 0;
+/// Exit code when at least one package couldn't be installed
+pub(crate) const EXIT_FAILED: i32 = 1;
+/// Exit code when the manifest itself couldn't be worked out, e.g. an unknown set
+pub(crate) const EXIT_INVALID_MANIFEST: i32 = 2;
+
+/// What `apply` did, or would do, to each package in the manifest
+#[derive(Debug, Default, PartialEq)]
+struct ConvergeReport {
+    /// Packages that were already installed
+    unchanged: Vec<String>,
+    /// Packages that were installed, or would be on a dry run
+    installed: Vec<String>,
+    /// Packages that failed to install or were never started because of a failure
+    failed: Vec<String>,
+    /// Packages whose `when:` conditions aren't met on this machine
+    not_applicable: Vec<String>,
+    dry_run: bool,
+}
+
+impl ConvergeReport {
+    fn from_summary(install_summary: &InstallSummaryData) -> Self {
+        Self {
+            unchanged: install_summary.already_installed.clone(),
+            installed: install_summary.installed.clone(),
+            failed: install_summary
+                .failed
+                .iter()
+                .chain(&install_summary.skipped)
+                .cloned()
+                .collect(),
+            not_applicable: install_summary.not_applicable.clone(),
+            dry_run: false,
+        }
+    }
+
+    fn from_plan(install_plan: &InstallPlanData) -> Self {
+        let planned = |action| {
+            install_plan
+                .steps
+                .iter()
+                .filter(|step| step.action == action)
+                .map(|step| step.package_name.clone())
+                .collect()
+        };
+
+        Self {
+            unchanged: planned(InstallPlanAction::AlreadyInstalled),
+            installed: planned(InstallPlanAction::Install),
+            failed: install_plan.failed.clone(),
+            not_applicable: planned(InstallPlanAction::Skipped),
+            dry_run: true,
+        }
+    }
+
+    fn exit_code(&self) -> i32 {
+        if self.failed.is_empty() {
+            EXIT_CONVERGED
+        } else {
+            EXIT_FAILED
+        }
+    }
+}
+
+/// Converge the machine to the manifest: every package for the environment, or
+/// the packages in `set_names`
+///
+/// Packages that are already installed are left alone and the rest are installed
+/// in dependency order, so running this again once it succeeds changes nothing.
+pub(crate) async fn handle_apply(
+    set_names: &[String],
+    options: InstallOptions,
+    config: &AppConfig,
+    reporter: TerminalProgressReporter,
+) -> i32 {
+    let service = common::create_package_service(config);
+
+    let event_stream = if set_names.is_empty() {
+        tracing::debug!("Applying all packages ({:?})", options);
+        service.install_all(options).await
+    } else {
+        let sets = match PackageSet::resolve(config, set_names) {
+            Ok(sets) => sets,
+            Err(err) => {
+                reporter.report_error(err);
+                return EXIT_INVALID_MANIFEST;
+            }
+        };
+        let members = PackageSet::unique_members(&sets);
+        tracing::debug!(
+            "Applying sets {:?}: {:?} ({:?})",
+            set_names,
+            members,
+            options
+        );
+        service.install_packages(&members, options).await
+    };
+
+    let mut report: Option<ConvergeReport> = None;
+    let processor = EventProcessor::new(reporter);
+    let exit_code = processor
+        .process_events_with_handler(event_stream, |event, reporter| match event {
+            PackageEvent::InstallPlanCompleted { install_plan, .. } => {
+                install::display_install_plan_table(install_plan, config);
+                report = Some(ConvergeReport::from_plan(install_plan));
+                Some(true)
+            }
+            PackageEvent::RollbackCompleted { rollback, .. } => {
+                install::report_rollback(rollback, *reporter);
+                Some(true)
+            }
+            PackageEvent::InstallSummaryCompleted {
+                install_summary, ..
+            } => {
+                report = Some(ConvergeReport::from_summary(install_summary));
+                Some(true)
+            }
+            _ => None,
+        })
+        .await;
+
+    match report {
+        Some(report) => {
+            display_converge_report(&report, config);
+            exit_code.max(report.exit_code())
+        }
+        None => exit_code,
+    }
+}
+
+fn display_converge_report(report: &ConvergeReport, config: &AppConfig) {
+    let mut table = common::create_formatted_table();
+    table.set_header(vec!["Status", "Count", "Packages"]);
+
+    let installed_label = if report.dry_run {
+        "📦 To install"
+    } else {
+        "📦 Installed"
+    };
+    let mut rows = vec![
+        ("✅ Unchanged", &report.unchanged),
+        (installed_label, &report.installed),
+        ("❌ Failed", &report.failed),
+    ];
+    if !report.not_applicable.is_empty() {
+        rows.push(("⏭️ Not applicable", &report.not_applicable));
+    }
+
+    for (status, packages) in rows {
+        table.add_row(vec![
+            format_key(status, config.use_colors()),
+            packages.len().to_string(),
+            packages.join(", "),
+        ]);
+    }
+
+    println!();
+    println!(
+        "📋 Converge Report ({}):",
+        format_key(config.environment(), config.use_colors())
+    );
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use selfie::package::event::InstallPlanStep;
+    use test_common::{TEST_ENV, test_config};
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_converge_report_from_summary() {
+        let install_summary = InstallSummaryData {
+            environment: TEST_ENV.to_string(),
+            installed: names(&["git"]),
+            already_installed: names(&["curl"]),
+            failed: names(&["neovim"]),
+            skipped: names(&["lazyvim"]),
+            not_applicable: names(&["mas"]),
+        };
+
+        let report = ConvergeReport::from_summary(&install_summary);
+
+        assert_eq!(report.unchanged, ["curl"]);
+        assert_eq!(report.installed, ["git"]);
+        assert_eq!(report.failed, ["neovim", "lazyvim"]);
+        assert_eq!(report.not_applicable, ["mas"]);
+        assert_eq!(report.exit_code(), EXIT_FAILED);
+    }
+
+    #[test]
+    fn test_converge_report_from_plan() {
+        let step = |name: &str, action| InstallPlanStep {
+            package_name: name.to_string(),
+            install_command: format!("brew install {name}"),
+            action,
+            required_by: None,
+        };
+        let install_plan = InstallPlanData {
+            requested: names(&["git", "curl"]),
+            environment: TEST_ENV.to_string(),
+            steps: vec![
+                step("git", InstallPlanAction::Install),
+                step("curl", InstallPlanAction::AlreadyInstalled),
+            ],
+            failed: Vec::new(),
+        };
+
+        let report = ConvergeReport::from_plan(&install_plan);
+
+        assert_eq!(report.unchanged, ["curl"]);
+        assert_eq!(report.installed, ["git"]);
+        assert!(report.dry_run);
+        assert_eq!(report.exit_code(), EXIT_CONVERGED);
+
+        let broken = InstallPlanData {
+            failed: names(&["broken"]),
+            ..install_plan
+        };
+        let report = ConvergeReport::from_plan(&broken);
+        assert_eq!(report.failed, ["broken"]);
+        assert_eq!(report.exit_code(), EXIT_FAILED);
+    }
+
+    #[test]
+    fn test_display_converge_report() {
+        let report = ConvergeReport {
+            unchanged: names(&["curl"]),
+            installed: names(&["git"]),
+            ..ConvergeReport::default()
+        };
+
+        // Just test that it doesn't panic
+        display_converge_report(&report, &test_config());
+    }
+
+    #[tokio::test]
+    async fn test_handle_apply_unknown_set() {
+        let result = handle_apply(
+            &names(&["work"]),
+            InstallOptions::default(),
+            &test_config(),
+            TerminalProgressReporter::new(false),
+        )
+        .await;
+
+        assert_eq!(result, EXIT_INVALID_MANIFEST);
+    }
+}
@@ -33,7 +33,31 @@ pub(crate) fn handle_validate(
         0
     } else {
         reporter.report_success("Configuration is valid.");
-        report_with_style("environment:", original_config.environment());
+        if original_config.environment_detected() {
+            report_with_style(
+                "environment:",
+                format!("{} (detected)", original_config.environment()),
+            );
+        } else {
+            report_with_style("environment:", original_config.environment());
+        }
+        if let Some(facts) = original_config.os_facts() {
+            report_with_style(
+                "detected from:",
+                format!(
+                    "kernel {}, arch {}, os_id {}",
+                    facts.kernel(),
+                    facts.arch(),
+                    facts.os_id().unwrap_or("unknown")
+                ),
+            );
+        }
+        if !original_config.environment_fallbacks().is_empty() {
+            report_with_style(
+                "environment_fallbacks:",
+                original_config.environment_fallbacks().join(", "),
+            );
+        }
         report_with_style(
             "package_directory:",
             original_config.package_directory().display(),
@@ -47,6 +71,22 @@ pub(crate) fn handle_validate(
             original_config.max_parallel_installations().get(),
         );
         report_with_style("stop_on_error:", original_config.stop_on_error());
+        report_with_style("retries:", original_config.retries());
+        report_with_style(
+            "retry_delay:",
+            format!("{} seconds", original_config.retry_delay().as_secs()),
+        );
+        if !original_config.sets().is_empty() {
+            report_with_style(
+                "sets:",
+                original_config
+                    .sets()
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+        }
         report_with_style("verbose:", original_config.verbose());
         report_with_style("use_colors:", original_config.use_colors());
 
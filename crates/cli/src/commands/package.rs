@@ -6,6 +6,8 @@ pub(crate) mod info;
 pub(crate) mod install;
 pub(crate) mod list;
 pub(crate) mod remove;
+pub(crate) mod uninstall;
+pub(crate) mod upgrade;
 pub(crate) mod validate;
 
 use std::path::Path;
@@ -8,10 +8,13 @@ use console::style;
 use selfie::{
     commands::ShellCommandRunner,
     config::AppConfig,
-    fs::real::RealFileSystem,
+    fs::{FileSystem, real::RealFileSystem},
     package::{
-        GetPackage, port::PackageRepository, repository::yaml::YamlPackageRepository,
+        GetPackage,
+        port::PackageRepository,
+        repository::yaml::YamlPackageRepository,
         service::PackageServiceImpl,
+        state::{InstallReason, InstallRecord, JsonStateStore},
     },
 };
 use std::{path::Path, process::Command};
@@ -127,17 +130,52 @@ pub(super) fn create_new_package(package_name: &str, config: &AppConfig) -> GetP
     GetPackage::new(package_name, config.package_directory())
 }
 
-/// Create a package service with repository and command runner
-pub(super) fn create_package_service(
+/// Create the store that records what selfie has installed
+///
+/// Uses the configured `state_directory`, or else selfie's directory under the
+/// user data directory. If neither is available, state is kept in a `.selfie`
+/// directory inside the package directory.
+pub(super) fn create_state_store(config: &AppConfig) -> JsonStateStore<RealFileSystem> {
+    let state_directory = if let Some(dir) = config.state_directory() {
+        dir.clone()
+    } else {
+        RealFileSystem.data_dir().unwrap_or_else(|err| {
+            let fallback = config.package_directory().join(".selfie");
+            tracing::warn!(
+                "Unable to find the user data directory ({err}); keeping install state in {}",
+                fallback.display()
+            );
+            fallback
+        })
+    };
+
+    JsonStateStore::new(RealFileSystem, &state_directory)
+}
+
+/// Create a package service with repository, command runner, state store, and
+/// filesystem
+pub(crate) fn create_package_service(
     config: &AppConfig,
-) -> PackageServiceImpl<YamlPackageRepository<RealFileSystem>, ShellCommandRunner> {
+) -> PackageServiceImpl<
+    YamlPackageRepository<RealFileSystem>,
+    ShellCommandRunner,
+    JsonStateStore<RealFileSystem>,
+    RealFileSystem,
+> {
     let repo = create_package_repository(config);
     let command_runner = ShellCommandRunner::new("/bin/sh", config.command_timeout());
-    PackageServiceImpl::new(repo, command_runner, config.clone())
+    let state_store = create_state_store(config);
+    PackageServiceImpl::new(
+        repo,
+        command_runner,
+        state_store,
+        RealFileSystem,
+        config.clone(),
+    )
 }
 
 /// Create a formatted table with consistent styling
-pub(super) fn create_formatted_table() -> Table {
+pub(crate) fn create_formatted_table() -> Table {
     let mut table = Table::new();
     table
         .load_preset(presets::UTF8_FULL_CONDENSED)
@@ -146,16 +184,16 @@ pub(super) fn create_formatted_table() -> Table {
     table
 }
 
-/// Format environment names with current environment highlighting
+/// Format environment names, highlighting the one selected for the current environment
 pub(super) fn format_environment_names(
     environments: &[String],
-    current_environment: &str,
+    selected_environment: Option<&str>,
     config: &AppConfig,
 ) -> String {
     environments
         .iter()
         .map(|env_name| {
-            if env_name == current_environment {
+            if selected_environment == Some(env_name.as_str()) {
                 let env = format!("*{env_name}");
                 if config.use_colors() {
                     style(env).bold().green().to_string()
@@ -190,6 +228,20 @@ pub(super) fn format_field_value(value: &str, use_colors: bool) -> String {
     }
 }
 
+/// Format when selfie installed a package, in local time, and why
+pub(super) fn format_install_record(record: &InstallRecord) -> String {
+    let installed_at = record
+        .installed_at
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M");
+    let reason = match record.reason {
+        InstallReason::Direct => "directly",
+        InstallReason::Dependency => "as a dependency",
+    };
+
+    format!("{installed_at} {reason} (v{})", record.version)
+}
+
 /// Process events with a custom handler using consistent pattern
 pub(super) async fn process_events_with_custom_handler<F>(
     event_stream: selfie::package::event::EventStream,
@@ -320,7 +372,7 @@ mod tests {
         let config = test_config_with_dir(TempDir::new().unwrap().path());
         let environments = vec!["test".to_string(), "production".to_string()];
 
-        let result = format_environment_names(&environments, "test", &config);
+        let result = format_environment_names(&environments, Some("test"), &config);
 
         // Just test that it doesn't panic and returns something
         assert!(!result.is_empty());
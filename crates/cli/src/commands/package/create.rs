@@ -438,7 +438,7 @@ mod tests {
 
         // Check that the default environment has the expected structure
         let default_env = get_package.package.environments().get("default").unwrap();
-        assert!(default_env.install().contains("template-test"));
+        assert!(default_env.install().to_string().contains("template-test"));
         assert!(default_env.check().unwrap().contains("template-test"));
         assert!(default_env.dependencies().is_empty());
     }
@@ -452,7 +452,7 @@ mod tests {
             vec!["dependency1".to_string(), "dependency2".to_string()],
         );
 
-        assert_eq!(env_config.install(), "brew install test");
+        assert_eq!(env_config.install().to_string(), "brew install test");
         assert_eq!(env_config.check(), Some("command -v test"));
         assert_eq!(env_config.dependencies(), &["dependency1", "dependency2"]);
     }
@@ -517,7 +517,7 @@ mod tests {
         assert!(environments.contains_key("default"));
 
         let default_env = environments.get("default").unwrap();
-        assert!(default_env.install().starts_with("# TODO:"));
+        assert!(default_env.install().to_string().starts_with("# TODO:"));
         assert!(default_env.check().is_some());
         assert!(default_env.dependencies().is_empty());
     }
@@ -545,7 +545,7 @@ mod tests {
         assert!(!environments.contains_key("macos"));
 
         let staging_env = environments.get("staging").unwrap();
-        assert!(staging_env.install().contains("test-staging"));
+        assert!(staging_env.install().to_string().contains("test-staging"));
         assert!(staging_env.check().unwrap().contains("test-staging"));
         assert!(staging_env.dependencies().is_empty());
     }
@@ -580,8 +580,8 @@ mod tests {
     #[test]
     fn test_max_name_retries_constant() {
         // Ensure the retry limit is reasonable
-        assert!(MAX_NAME_RETRIES > 0);
-        assert!(MAX_NAME_RETRIES <= 5); // Don't allow too many retries
+        const { assert!(MAX_NAME_RETRIES > 0) };
+        const { assert!(MAX_NAME_RETRIES <= 5) }; // Don't allow too many retries
         assert_eq!(MAX_NAME_RETRIES, 3); // Verify the exact value we set
     }
 
@@ -62,8 +62,11 @@ pub(crate) async fn handle_edit(
         common::create_new_package(package_name, config)
     };
 
-    // Write the package to the file system first
-    if let Err(exit_code) = common::save_package(&repo, &package_blob, &reporter) {
+    // Write a new package to the file system first. An existing package is opened
+    // as written, since the loaded package has its environments' `use:` resolved.
+    if package_blob.is_new
+        && let Err(exit_code) = common::save_package(&repo, &package_blob, &reporter)
+    {
         return exit_code;
     }
 
@@ -94,7 +94,7 @@ fn create_package_info_table(package_info: &PackageInfoData, config: &AppConfig)
     // Format the environment names as a comma-separated list
     let env_names = common::format_environment_names(
         &package_info.environments,
-        &package_info.current_environment,
+        package_info.selected_environment.as_deref(),
         config,
     );
     table.add_row(vec![
@@ -102,6 +102,23 @@ fn create_package_info_table(package_info: &PackageInfoData, config: &AppConfig)
         format_value(&env_names),
     ]);
 
+    // Show which block is used here, since it may be a fallback
+    let selected = match package_info.selected_environment.as_deref() {
+        Some(selected) if selected == package_info.current_environment => selected.to_string(),
+        Some(selected) => format!(
+            "{selected} (fallback for {})",
+            package_info.current_environment
+        ),
+        None => format!(
+            "none (not supported in {})",
+            package_info.current_environment
+        ),
+    };
+    table.add_row(vec![
+        format_key_fn("Selected Environment"),
+        format_value(&selected),
+    ]);
+
     table
 }
 
@@ -136,23 +153,65 @@ fn create_environment_table(env_status: &EnvironmentStatusData, config: &AppConf
         |value: &str| -> String { common::format_field_value(value, config.use_colors()) };
 
     // Add installation status if this is the current environment and we have status
-    if env_status.is_current {
-        if let Some(status) = &env_status.status {
-            let status_text = format_status(status, config.use_colors());
-            env_table.add_row(vec![format_env_key("Status"), status_text]);
+    if env_status.is_current
+        && let Some(status) = &env_status.status
+    {
+        let mut status_text = format_status(status, config.use_colors());
+        if let Some(version) = &env_status.installed_version {
+            status_text.push_str(&format!(" (version {version})"));
         }
+        env_table.add_row(vec![format_env_key("Status"), status_text]);
+    }
+
+    if let Some(required_version) = &env_status.required_version {
+        env_table.add_row(vec![
+            format_env_key("Required version"),
+            format_env_value(required_version),
+        ]);
     }
 
     // Add environment detail rows
+    if !env_status.pre_install_commands.is_empty() {
+        env_table.add_row(vec![
+            format_env_key("Pre-install"),
+            format_env_value(&env_status.pre_install_commands.join("\n")),
+        ]);
+    }
+
     env_table.add_row(vec![
         format_env_key("Install"),
         format_env_value(&env_status.install_command),
     ]);
 
+    if !env_status.post_install_commands.is_empty() {
+        env_table.add_row(vec![
+            format_env_key("Post-install"),
+            format_env_value(&env_status.post_install_commands.join("\n")),
+        ]);
+    }
+
     if let Some(check) = &env_status.check_command {
         env_table.add_row(vec![format_env_key("Check"), format_env_value(check)]);
     }
 
+    if let Some(update) = &env_status.update_command {
+        env_table.add_row(vec![format_env_key("Update"), format_env_value(update)]);
+    }
+
+    if let Some(uninstall) = &env_status.uninstall_command {
+        env_table.add_row(vec![
+            format_env_key("Uninstall"),
+            format_env_value(uninstall),
+        ]);
+    }
+
+    if !env_status.rollback_commands.is_empty() {
+        env_table.add_row(vec![
+            format_env_key("Rollback"),
+            format_env_value(&env_status.rollback_commands.join("\n")),
+        ]);
+    }
+
     if !env_status.dependencies.is_empty() {
         env_table.add_row(vec![
             format_env_key("Dependencies"),
@@ -160,6 +219,17 @@ fn create_environment_table(env_status: &EnvironmentStatusData, config: &AppConf
         ]);
     }
 
+    if let Some(record) = &env_status.install_record {
+        env_table.add_row(vec![
+            format_env_key("Installed by selfie"),
+            format_env_value(&common::format_install_record(record)),
+        ]);
+        env_table.add_row(vec![
+            format_env_key("Installed with"),
+            format_env_value(&record.install_command),
+        ]);
+    }
+
     env_table
 }
 
@@ -193,7 +263,10 @@ fn format_status(status: &EnvironmentStatus, use_colors: bool) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use selfie::package::event::{EnvironmentStatus, EnvironmentStatusData, PackageInfoData};
+    use selfie::package::{
+        event::{EnvironmentStatus, EnvironmentStatusData, PackageInfoData},
+        state::{InstallReason, InstallRecord},
+    };
     use test_common::{ALT_TEST_ENV, TEST_ENV, TEST_VERSION, test_config, test_config_with_colors};
 
     fn create_test_package_info() -> PackageInfoData {
@@ -204,6 +277,7 @@ mod tests {
             homepage: Some("https://example.com".to_string()),
             environments: vec![TEST_ENV.to_string(), ALT_TEST_ENV.to_string()],
             current_environment: TEST_ENV.to_string(),
+            selected_environment: Some(TEST_ENV.to_string()),
         }
     }
 
@@ -213,12 +287,27 @@ mod tests {
             is_current,
             install_command: "apt install test-package".to_string(),
             check_command: Some("which test-package".to_string()),
+            pre_install_commands: vec!["apt-add-repository test-ppa".to_string()],
+            post_install_commands: Vec::new(),
+            uninstall_command: Some("apt remove test-package".to_string()),
+            update_command: Some("apt upgrade test-package".to_string()),
+            rollback_commands: vec!["apt-add-repository --remove test-ppa".to_string()],
             dependencies: vec!["dependency1".to_string(), "dependency2".to_string()],
             status: if is_current {
                 Some(EnvironmentStatus::Installed)
             } else {
                 None
             },
+            installed_version: is_current.then(|| "14.1.0".to_string()),
+            required_version: Some(">=14.0".to_string()),
+            install_record: is_current.then(|| InstallRecord {
+                package_name: "test-package".to_string(),
+                environment: TEST_ENV.to_string(),
+                installed_at: chrono::Utc::now(),
+                version: TEST_VERSION.to_string(),
+                install_command: "apt install test-package".to_string(),
+                reason: InstallReason::Direct,
+            }),
         }
     }
 
@@ -252,6 +341,30 @@ mod tests {
         let _table_str = table.to_string();
     }
 
+    #[test]
+    fn test_create_environment_table_shows_install_record() {
+        let config = test_config();
+
+        let installed = create_environment_table(&create_test_environment_status(true), &config);
+        let installed_str = installed.to_string();
+        assert!(installed_str.contains("Installed by selfie"));
+        assert!(installed_str.contains("directly"));
+
+        let not_installed =
+            create_environment_table(&create_test_environment_status(false), &config);
+        assert!(!not_installed.to_string().contains("Installed by selfie"));
+    }
+
+    #[test]
+    fn test_create_environment_table_shows_installed_version() {
+        let config = test_config();
+
+        let table = create_environment_table(&create_test_environment_status(true), &config);
+        let table_str = table.to_string();
+        assert!(table_str.contains("Installed ✓ (version 14.1.0)"));
+        assert!(table_str.contains(">=14.0"));
+    }
+
     #[test]
     fn test_format_status_functions() {
         let status = EnvironmentStatus::Installed;
@@ -267,7 +380,7 @@ mod tests {
     fn test_format_environment_names() {
         let config = test_config();
         let environments = vec![TEST_ENV.to_string(), ALT_TEST_ENV.to_string()];
-        let result = common::format_environment_names(&environments, TEST_ENV, &config);
+        let result = common::format_environment_names(&environments, Some(TEST_ENV), &config);
         // Just test that it doesn't panic
         assert!(!result.is_empty());
     }
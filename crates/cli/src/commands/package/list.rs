@@ -79,7 +79,7 @@ fn display_packages_table(
     }
 
     let mut table = common::create_formatted_table();
-    table.set_header(vec!["Name", "Version", "Environments"]);
+    table.set_header(vec!["Name", "Version", "Environments", "Installed"]);
 
     for package in packages {
         let package_name = if config.use_colors() {
@@ -94,10 +94,19 @@ fn display_packages_table(
             format!("v{}", package.version)
         };
 
-        let environments =
-            common::format_environment_names(&package.environments, config.environment(), config);
+        let environments = common::format_environment_names(
+            &package.environments,
+            package.selected_environment.as_deref(),
+            config,
+        );
 
-        table.add_row(vec![package_name, version, environments]);
+        // Only packages selfie installed in the current environment have a record
+        let installed = package
+            .install_record
+            .as_ref()
+            .map_or_else(|| "-".to_string(), common::format_install_record);
+
+        table.add_row(vec![package_name, version, environments, installed]);
     }
 
     println!("{table}");
@@ -106,7 +115,10 @@ fn display_packages_table(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use selfie::package::event::PackageListItem;
+    use selfie::package::{
+        event::PackageListItem,
+        state::{InstallReason, InstallRecord},
+    };
     use test_common::{ALT_TEST_ENV, TEST_ENV, TEST_VERSION, test_config, test_config_with_colors};
 
     fn create_mock_reporter() -> TerminalProgressReporter {
@@ -139,6 +151,30 @@ mod tests {
             name: "test-package".to_string(),
             version: TEST_VERSION.to_string(),
             environments: vec![TEST_ENV.to_string()],
+            selected_environment: Some(TEST_ENV.to_string()),
+            install_record: None,
+        }];
+
+        // Should not panic
+        display_packages_table(&packages, &config);
+    }
+
+    #[test]
+    fn test_display_packages_table_with_install_record() {
+        let config = test_config();
+        let packages = vec![PackageListItem {
+            name: "test-package".to_string(),
+            version: TEST_VERSION.to_string(),
+            environments: vec![TEST_ENV.to_string()],
+            selected_environment: Some(TEST_ENV.to_string()),
+            install_record: Some(InstallRecord {
+                package_name: "test-package".to_string(),
+                environment: TEST_ENV.to_string(),
+                installed_at: chrono::Utc::now(),
+                version: TEST_VERSION.to_string(),
+                install_command: "echo install".to_string(),
+                reason: InstallReason::Dependency,
+            }),
         }];
 
         // Should not panic
@@ -152,6 +188,8 @@ mod tests {
             name: "test-package".to_string(),
             version: TEST_VERSION.to_string(),
             environments: vec![TEST_ENV.to_string()],
+            selected_environment: Some(TEST_ENV.to_string()),
+            install_record: None,
         }];
 
         // Should not panic with colors enabled
@@ -170,7 +208,7 @@ mod tests {
         let config = test_config();
         let environments = vec![TEST_ENV.to_string(), ALT_TEST_ENV.to_string()];
 
-        let result = common::format_environment_names(&environments, TEST_ENV, &config);
+        let result = common::format_environment_names(&environments, Some(TEST_ENV), &config);
 
         // Just test that it doesn't panic and returns something
         assert!(!result.is_empty());
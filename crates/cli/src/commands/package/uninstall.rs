@@ -0,0 +1,79 @@
+use selfie::{config::AppConfig, package::service::PackageService};
+
+use crate::{
+    event_processor::EventProcessor, terminal_progress_reporter::TerminalProgressReporter,
+};
+
+use super::common;
+
+pub(crate) async fn handle_uninstall(
+    package_name: &str,
+    config: &AppConfig,
+    reporter: TerminalProgressReporter,
+) -> i32 {
+    tracing::debug!("Uninstalling package: {}", package_name);
+
+    let service = common::create_package_service(config);
+    let event_stream = service.uninstall(package_name).await;
+
+    // Default event handling covers progress, warnings about dependents, command
+    // output, and the final result
+    let processor = EventProcessor::new(reporter);
+    processor
+        .process_events_with_handler(event_stream, |_event, _reporter| None)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use test_common::{TEST_ENV, TEST_VERSION, create_custom_package_file, test_config_with_dir};
+
+    fn create_mock_reporter() -> TerminalProgressReporter {
+        TerminalProgressReporter::new(false)
+    }
+
+    #[tokio::test]
+    async fn test_handle_uninstall_not_installed() {
+        let temp_dir = TempDir::new().unwrap();
+        let _ = create_custom_package_file(
+            &temp_dir,
+            "test-package",
+            TEST_VERSION,
+            TEST_ENV,
+            "echo install",
+            Some("exit 1"),
+        );
+        let config = test_config_with_dir(temp_dir.path());
+
+        let result = handle_uninstall("test-package", &config, create_mock_reporter()).await;
+        assert_eq!(result, 0);
+    }
+
+    #[tokio::test]
+    async fn test_handle_uninstall_without_uninstall_command() {
+        let temp_dir = TempDir::new().unwrap();
+        let _ = create_custom_package_file(
+            &temp_dir,
+            "test-package",
+            TEST_VERSION,
+            TEST_ENV,
+            "echo install",
+            Some("true"),
+        );
+        let config = test_config_with_dir(temp_dir.path());
+
+        let result = handle_uninstall("test-package", &config, create_mock_reporter()).await;
+        assert_eq!(result, 1);
+    }
+
+    #[tokio::test]
+    async fn test_handle_uninstall_nonexistent_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = test_config_with_dir(temp_dir.path());
+
+        let result = handle_uninstall("nonexistent", &config, create_mock_reporter()).await;
+        assert_eq!(result, 1);
+    }
+}
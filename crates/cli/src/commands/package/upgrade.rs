@@ -0,0 +1,177 @@
+use selfie::{
+    config::AppConfig,
+    package::{
+        event::{PackageEvent, UpgradeResult, UpgradeResultData, UpgradeSummaryData},
+        service::PackageService,
+    },
+};
+
+use crate::{
+    event_processor::EventProcessor, formatters::format_key,
+    terminal_progress_reporter::TerminalProgressReporter,
+};
+
+use super::common;
+
+pub(crate) async fn handle_upgrade(
+    package_name: &str,
+    config: &AppConfig,
+    reporter: TerminalProgressReporter,
+) -> i32 {
+    tracing::debug!("Upgrading package: {}", package_name);
+
+    let service = common::create_package_service(config);
+    let event_stream = service.upgrade(package_name).await;
+
+    // The final result already says how the upgrade went, so the structured
+    // result needs no extra output
+    let processor = EventProcessor::new(reporter);
+    processor
+        .process_events_with_handler(event_stream, |event, _reporter| match event {
+            PackageEvent::UpgradeResultCompleted { .. } => Some(true),
+            _ => None,
+        })
+        .await
+}
+
+pub(crate) async fn handle_upgrade_all(
+    config: &AppConfig,
+    reporter: TerminalProgressReporter,
+) -> i32 {
+    tracing::debug!("Upgrading all packages");
+
+    let service = common::create_package_service(config);
+    let event_stream = service.upgrade_all().await;
+
+    let processor = EventProcessor::new(reporter);
+    processor
+        .process_events_with_handler(event_stream, |event, reporter| match event {
+            PackageEvent::UpgradeResultCompleted { upgrade_result, .. } => {
+                report_upgrade_result_line(upgrade_result, *reporter);
+                Some(true)
+            }
+            PackageEvent::UpgradeSummaryCompleted {
+                upgrade_summary, ..
+            } => {
+                display_upgrade_summary_table(upgrade_summary, config);
+                Some(true)
+            }
+            _ => None,
+        })
+        .await
+}
+
+/// Report a single package's outcome while upgrading all packages
+fn report_upgrade_result_line(
+    upgrade_result: &UpgradeResultData,
+    reporter: TerminalProgressReporter,
+) {
+    let name = &upgrade_result.package_name;
+
+    match &upgrade_result.result {
+        UpgradeResult::Upgraded => reporter.report_success(format!("{name}: upgraded")),
+        UpgradeResult::NotUpgradable => reporter.report_info(format!("{name}: not upgradable")),
+        UpgradeResult::NotInstalled => reporter.report_info(format!("{name}: not installed")),
+        UpgradeResult::Failed(error) => reporter.report_error(format!("{name}: {error}")),
+        UpgradeResult::Skipped(reason) => {
+            reporter.report_info(format!("{name}: skipped ({reason})"));
+        }
+    }
+}
+
+fn display_upgrade_summary_table(upgrade_summary: &UpgradeSummaryData, config: &AppConfig) {
+    let mut table = common::create_formatted_table();
+    table.set_header(vec!["Status", "Count", "Packages"]);
+
+    let mut rows = vec![
+        ("⬆️ Upgraded", &upgrade_summary.upgraded),
+        ("➖ Not upgradable", &upgrade_summary.not_upgradable),
+        ("❌ Not installed", &upgrade_summary.not_installed),
+        ("⚠️ Failed", &upgrade_summary.failed),
+    ];
+    // Skipped packages only exist when the upgrades stopped early
+    if !upgrade_summary.skipped.is_empty() {
+        rows.push(("⏭️ Skipped", &upgrade_summary.skipped));
+    }
+    if !upgrade_summary.not_applicable.is_empty() {
+        rows.push(("⏭️ Not applicable", &upgrade_summary.not_applicable));
+    }
+
+    for (status, packages) in rows {
+        table.add_row(vec![
+            format_key(status, config.use_colors()),
+            packages.len().to_string(),
+            packages.join(", "),
+        ]);
+    }
+
+    println!();
+    println!(
+        "📋 Upgrade Summary ({}):",
+        format_key(&upgrade_summary.environment, config.use_colors())
+    );
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use test_common::{TEST_ENV, TEST_VERSION, create_custom_package_file, test_config_with_dir};
+
+    fn create_mock_reporter() -> TerminalProgressReporter {
+        TerminalProgressReporter::new(false)
+    }
+
+    #[tokio::test]
+    async fn test_handle_upgrade_not_upgradable() {
+        let temp_dir = TempDir::new().unwrap();
+        let _ = create_custom_package_file(
+            &temp_dir,
+            "test-package",
+            TEST_VERSION,
+            TEST_ENV,
+            "echo install",
+            Some("true"),
+        );
+        let config = test_config_with_dir(temp_dir.path());
+
+        let result = handle_upgrade("test-package", &config, create_mock_reporter()).await;
+        assert_eq!(result, 1);
+    }
+
+    #[tokio::test]
+    async fn test_handle_upgrade_all_without_update_commands() {
+        let temp_dir = TempDir::new().unwrap();
+        let _ = create_custom_package_file(
+            &temp_dir,
+            "test-package",
+            TEST_VERSION,
+            TEST_ENV,
+            "echo install",
+            Some("true"),
+        );
+        let config = test_config_with_dir(temp_dir.path());
+
+        // Packages that can't be upgraded aren't failures when upgrading everything
+        let result = handle_upgrade_all(&config, create_mock_reporter()).await;
+        assert_eq!(result, 0);
+    }
+
+    #[test]
+    fn test_display_upgrade_summary_table() {
+        let config = test_config_with_dir(TempDir::new().unwrap().path());
+        let upgrade_summary = UpgradeSummaryData {
+            environment: TEST_ENV.to_string(),
+            upgraded: vec!["ripgrep".to_string()],
+            not_upgradable: vec!["homebrew".to_string()],
+            not_installed: vec![],
+            failed: vec!["node".to_string()],
+            skipped: vec!["zsh".to_string()],
+            not_applicable: vec!["mas".to_string()],
+        };
+
+        // Just test that the function doesn't panic
+        display_upgrade_summary_table(&upgrade_summary, &config);
+    }
+}
@@ -16,6 +16,8 @@ use crate::{
     terminal_progress_reporter::TerminalProgressReporter,
 };
 
+use super::common;
+
 pub(crate) async fn handle_validate(
     package_name: &str,
     config: &AppConfig,
@@ -28,7 +30,14 @@ pub(crate) async fn handle_validate(
     let command_runner = ShellCommandRunner::new("/bin/sh", config.command_timeout());
 
     // Create the package service implementation
-    let service = PackageServiceImpl::new(repo, command_runner, config.clone());
+    let state_store = common::create_state_store(config);
+    let service = PackageServiceImpl::new(
+        repo,
+        command_runner,
+        state_store,
+        RealFileSystem,
+        config.clone(),
+    );
 
     // Call the service's validate method to get an event stream
     match service.validate(package_name, None).await {
@@ -0,0 +1,323 @@
+use selfie::{
+    config::AppConfig,
+    package::{
+        event::{InstallPlanAction, PackageEvent},
+        service::{InstallOptions, PackageService},
+        set::{PackageSet, SetError},
+    },
+};
+
+use crate::{
+    event_processor::EventProcessor, formatters::format_key,
+    terminal_progress_reporter::TerminalProgressReporter,
+};
+
+use super::package::{check, common, install};
+
+/// The packages that ended up in each status, e.g. `("✅ Installed", [...])`
+type StatusGroups = Vec<(&'static str, Vec<String>)>;
+
+pub(crate) async fn handle_set_install(
+    set_names: &[String],
+    options: InstallOptions,
+    config: &AppConfig,
+    reporter: TerminalProgressReporter,
+) -> i32 {
+    let Some(sets) = resolve_sets(set_names, config, reporter) else {
+        return 1;
+    };
+    let members = PackageSet::unique_members(&sets);
+    if members.is_empty() {
+        reporter.report_warning("The given sets have no packages; nothing to install");
+        return 0;
+    }
+
+    tracing::debug!(
+        "Installing sets {:?}: {:?} ({:?})",
+        set_names,
+        members,
+        options
+    );
+    let service = common::create_package_service(config);
+    let event_stream = service.install_packages(&members, options).await;
+
+    let mut status_groups: Option<StatusGroups> = None;
+    let processor = EventProcessor::new(reporter);
+    let exit_code = processor
+        .process_events_with_handler(event_stream, |event, reporter| match event {
+            PackageEvent::InstallPlanCompleted { install_plan, .. } => {
+                install::display_install_plan_table(install_plan, config);
+                let planned = |action| {
+                    install_plan
+                        .steps
+                        .iter()
+                        .filter(|step| step.action == action)
+                        .map(|step| step.package_name.clone())
+                        .collect()
+                };
+                status_groups = Some(vec![
+                    ("📦 Would install", planned(InstallPlanAction::Install)),
+                    (
+                        "✅ Already installed",
+                        planned(InstallPlanAction::AlreadyInstalled),
+                    ),
+                    ("⏭️ Not applicable", planned(InstallPlanAction::Skipped)),
+                ]);
+                Some(true)
+            }
+            PackageEvent::RollbackCompleted { rollback, .. } => {
+                install::report_rollback(rollback, *reporter);
+                Some(true)
+            }
+            PackageEvent::InstallSummaryCompleted {
+                install_summary, ..
+            } => {
+                status_groups = Some(vec![
+                    ("📦 Installed", install_summary.installed.clone()),
+                    (
+                        "✅ Already installed",
+                        install_summary.already_installed.clone(),
+                    ),
+                    ("❌ Failed", install_summary.failed.clone()),
+                    ("⏭️ Skipped", install_summary.skipped.clone()),
+                    ("⏭️ Not applicable", install_summary.not_applicable.clone()),
+                ]);
+                Some(true)
+            }
+            _ => None,
+        })
+        .await;
+
+    if let Some(status_groups) = status_groups {
+        display_set_summary("Install", &sets, &status_groups, config);
+    }
+    exit_code
+}
+
+pub(crate) async fn handle_set_check(
+    set_names: &[String],
+    config: &AppConfig,
+    reporter: TerminalProgressReporter,
+) -> i32 {
+    let Some(sets) = resolve_sets(set_names, config, reporter) else {
+        return 1;
+    };
+    let members = PackageSet::unique_members(&sets);
+    if members.is_empty() {
+        reporter.report_warning("The given sets have no packages; nothing to check");
+        return 0;
+    }
+
+    tracing::debug!("Checking sets {:?}: {:?}", set_names, members);
+    let service = common::create_package_service(config);
+    let event_stream = service.check_packages(&members).await;
+
+    let mut status_groups: Option<StatusGroups> = None;
+    let processor = EventProcessor::new(reporter);
+    let exit_code = processor
+        .process_events_with_handler(event_stream, |event, reporter| match event {
+            PackageEvent::CheckResultCompleted { check_result, .. } => {
+                check::report_check_result_line(check_result, *reporter);
+                Some(true)
+            }
+            PackageEvent::CheckSummaryCompleted { check_summary, .. } => {
+                status_groups = Some(vec![
+                    ("✅ Installed", check_summary.installed.clone()),
+                    ("⬆️ Outdated", check_summary.outdated.clone()),
+                    ("❌ Missing", check_summary.missing.clone()),
+                    ("⚠️ Failed", check_summary.failed.clone()),
+                    ("⏭️ Skipped", check_summary.skipped.clone()),
+                    ("⏭️ Not applicable", check_summary.not_applicable.clone()),
+                ]);
+                Some(true)
+            }
+            _ => None,
+        })
+        .await;
+
+    if let Some(status_groups) = status_groups {
+        display_set_summary("Check", &sets, &status_groups, config);
+    }
+    exit_code
+}
+
+pub(crate) fn handle_set_list(
+    set_names: &[String],
+    config: &AppConfig,
+    reporter: TerminalProgressReporter,
+) -> i32 {
+    let sets = if set_names.is_empty() {
+        let sets = PackageSet::all(config);
+        if sets.is_empty() {
+            reporter.report_info(SetError::NoSets);
+            return 0;
+        }
+        sets
+    } else {
+        let Some(sets) = resolve_sets(set_names, config, reporter) else {
+            return 1;
+        };
+        sets
+    };
+
+    let mut table = common::create_formatted_table();
+    table.set_header(vec!["Set", "Count", "Packages"]);
+
+    for set in &sets {
+        table.add_row(vec![
+            format_key(&set.name, config.use_colors()),
+            set.members.len().to_string(),
+            set.members.join(", "),
+        ]);
+    }
+
+    println!("{table}");
+    0
+}
+
+/// Look up the named sets, reporting the error if one isn't defined
+fn resolve_sets(
+    set_names: &[String],
+    config: &AppConfig,
+    reporter: TerminalProgressReporter,
+) -> Option<Vec<PackageSet>> {
+    match PackageSet::resolve(config, set_names) {
+        Ok(sets) => Some(sets),
+        Err(err) => {
+            reporter.report_error(err);
+            None
+        }
+    }
+}
+
+/// Show, for each set, which of its packages ended up in each status
+///
+/// Statuses that no package of any set ended up in are left out.
+fn display_set_summary(
+    operation: &str,
+    sets: &[PackageSet],
+    status_groups: &StatusGroups,
+    config: &AppConfig,
+) {
+    let columns: Vec<_> = status_groups
+        .iter()
+        .filter(|(_, packages)| sets.iter().any(|set| !set.members_in(packages).is_empty()))
+        .collect();
+
+    let mut table = common::create_formatted_table();
+    let mut header = vec!["Set".to_string()];
+    header.extend(columns.iter().map(|(status, _)| (*status).to_string()));
+    table.set_header(header);
+
+    for set in sets {
+        let mut row = vec![format_key(&set.name, config.use_colors())];
+        row.extend(
+            columns
+                .iter()
+                .map(|(_, packages)| set.members_in(packages).join(", ")),
+        );
+        table.add_row(row);
+    }
+
+    println!();
+    println!(
+        "📋 {operation} Summary by Set ({}):",
+        format_key(config.environment(), config.use_colors())
+    );
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use selfie::config::AppConfigBuilder;
+    use tempfile::TempDir;
+    use test_common::{TEST_ENV, TEST_VERSION, create_custom_package_file, test_config};
+
+    fn config_with_sets(temp_dir: &TempDir) -> AppConfig {
+        AppConfigBuilder::default()
+            .environment(TEST_ENV)
+            .package_directory(temp_dir.path())
+            .state_directory(temp_dir.path().join(test_common::config::STATE_DIR_NAME))
+            .use_colors(false)
+            .set("dev", vec!["git", "ripgrep"])
+            .set("ci", vec!["git"])
+            .build()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[tokio::test]
+    async fn test_handle_set_install_unknown_set() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = config_with_sets(&temp_dir);
+
+        let result = handle_set_install(
+            &names(&["work"]),
+            InstallOptions::default(),
+            &config,
+            TerminalProgressReporter::new(false),
+        )
+        .await;
+
+        assert_eq!(result, 1);
+    }
+
+    #[tokio::test]
+    async fn test_handle_set_install_shared_members_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("install.log");
+        for name in ["git", "ripgrep"] {
+            let _ = create_custom_package_file(
+                &temp_dir,
+                name,
+                TEST_VERSION,
+                TEST_ENV,
+                &format!("echo {name} >> {}", log.display()),
+                None,
+            );
+        }
+        let config = config_with_sets(&temp_dir);
+
+        let result = handle_set_install(
+            &names(&["dev", "ci"]),
+            InstallOptions::default(),
+            &config,
+            TerminalProgressReporter::new(false),
+        )
+        .await;
+
+        assert_eq!(result, 0);
+        let installed = std::fs::read_to_string(log).unwrap();
+        assert_eq!(installed.lines().collect::<Vec<_>>().len(), 2);
+    }
+
+    #[test]
+    fn test_handle_set_list() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = config_with_sets(&temp_dir);
+        let reporter = TerminalProgressReporter::new(false);
+
+        assert_eq!(handle_set_list(&[], &config, reporter), 0);
+        assert_eq!(handle_set_list(&names(&["dev"]), &config, reporter), 0);
+        assert_eq!(handle_set_list(&names(&["work"]), &config, reporter), 1);
+        assert_eq!(handle_set_list(&[], &test_config(), reporter), 0);
+    }
+
+    #[test]
+    fn test_display_set_summary() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = config_with_sets(&temp_dir);
+        let sets = PackageSet::resolve(&config, &names(&["dev", "ci"])).unwrap();
+        let status_groups = vec![
+            ("✅ Installed", names(&["git"])),
+            ("❌ Missing", names(&["ripgrep"])),
+            ("⚠️ Failed", Vec::new()),
+        ];
+
+        // Just test that it doesn't panic
+        display_set_summary("Check", &sets, &status_groups, &config);
+    }
+}
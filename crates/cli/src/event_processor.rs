@@ -159,6 +159,11 @@ impl EventProcessor {
                     self.reporter.report_error(err);
                     *exit_code = 1;
                 }
+                OperationResult::AlreadyInstalled(msg)
+                | OperationResult::NotInstalled(msg)
+                | OperationResult::Skipped(msg) => {
+                    self.reporter.report_info(msg);
+                }
             },
 
             PackageEvent::Canceled { reason, .. } => {
@@ -188,6 +193,36 @@ impl EventProcessor {
                 // If no custom handler processed them, just continue
             }
 
+            PackageEvent::CheckSummaryCompleted { .. } => {
+                // These structured events are handled by command-specific handlers
+                // If no custom handler processed them, just continue
+            }
+
+            PackageEvent::InstallPlanCompleted { .. } => {
+                // These structured events are handled by command-specific handlers
+                // If no custom handler processed them, just continue
+            }
+
+            PackageEvent::InstallSummaryCompleted { .. } => {
+                // These structured events are handled by command-specific handlers
+                // If no custom handler processed them, just continue
+            }
+
+            PackageEvent::RollbackCompleted { .. } => {
+                // These structured events are handled by command-specific handlers
+                // If no custom handler processed them, just continue
+            }
+
+            PackageEvent::UpgradeResultCompleted { .. } => {
+                // These structured events are handled by command-specific handlers
+                // If no custom handler processed them, just continue
+            }
+
+            PackageEvent::UpgradeSummaryCompleted { .. } => {
+                // These structured events are handled by command-specific handlers
+                // If no custom handler processed them, just continue
+            }
+
             PackageEvent::ValidationResultCompleted { .. } => {
                 // These structured events are handled by command-specific handlers
                 // If no custom handler processed them, just continue
@@ -294,6 +329,7 @@ mod tests {
             package::{
                 repository::YamlPackageRepository,
                 service::{PackageService, PackageServiceImpl},
+                state::JsonStateStore,
             },
         };
 
@@ -307,7 +343,10 @@ mod tests {
 
         let repo = YamlPackageRepository::new(RealFileSystem, config.package_directory().clone());
         let command_runner = ShellCommandRunner::new("/bin/sh", config.command_timeout());
-        let service = PackageServiceImpl::new(repo, command_runner, config);
+        let state_store =
+            JsonStateStore::new(RealFileSystem, &config.package_directory().join(".selfie"));
+        let service =
+            PackageServiceImpl::new(repo, command_runner, state_store, RealFileSystem, config);
 
         let reporter = TerminalProgressReporter::new(false);
         let processor = EventProcessor::new(reporter);
@@ -330,6 +369,7 @@ mod tests {
             package::{
                 repository::YamlPackageRepository,
                 service::{PackageService, PackageServiceImpl},
+                state::JsonStateStore,
             },
         };
 
@@ -343,7 +383,10 @@ mod tests {
 
         let repo = YamlPackageRepository::new(RealFileSystem, config.package_directory().clone());
         let command_runner = ShellCommandRunner::new("/bin/sh", config.command_timeout());
-        let service = PackageServiceImpl::new(repo, command_runner, config);
+        let state_store =
+            JsonStateStore::new(RealFileSystem, &config.package_directory().join(".selfie"));
+        let service =
+            PackageServiceImpl::new(repo, command_runner, state_store, RealFileSystem, config);
 
         let reporter = TerminalProgressReporter::new(false);
         let processor = EventProcessor::new(reporter);
@@ -388,6 +431,7 @@ mod tests {
             package::{
                 repository::YamlPackageRepository,
                 service::{PackageService, PackageServiceImpl},
+                state::JsonStateStore,
             },
         };
 
@@ -401,7 +445,10 @@ mod tests {
 
         let repo = YamlPackageRepository::new(RealFileSystem, config.package_directory().clone());
         let command_runner = ShellCommandRunner::new("/bin/sh", config.command_timeout());
-        let service = PackageServiceImpl::new(repo, command_runner, config);
+        let state_store =
+            JsonStateStore::new(RealFileSystem, &config.package_directory().join(".selfie"));
+        let service =
+            PackageServiceImpl::new(repo, command_runner, state_store, RealFileSystem, config);
 
         let reporter = TerminalProgressReporter::new(false);
         let processor = EventProcessor::new(reporter);
@@ -16,8 +16,11 @@
 //!
 //! The CLI supports various package management operations:
 //! ```bash
-//! selfie install <package>     # Install a package
+//! selfie install <package>...  # Install one or more packages (or --all)
+//! selfie upgrade <package>     # Upgrade a package (or --all)
+//! selfie uninstall <package>   # Uninstall a package
 //! selfie check <package>       # Check if a package is installed
+//! selfie check --all           # Check every package for the environment
 //! selfie list                  # List available packages
 //! selfie info <package>        # Get package information
 //! selfie validate <package>    # Validate package definition
@@ -35,6 +38,7 @@ use std::process;
 
 use clap::Parser;
 use selfie::{
+    commands::ShellCommandRunner,
     config::{
         YamlLoader,
         loader::{ApplyToConfg, ConfigLoader},
@@ -55,7 +59,6 @@ use crate::{cli::ClapCli, commands::dispatch_command};
 /// # Arguments
 ///
 /// * `verbose` - Whether to enable verbose (DEBUG) logging
-
 fn init_tracing(verbose: bool) {
     let level = if verbose {
         tracing::Level::DEBUG
@@ -100,8 +103,10 @@ async fn main() -> anyhow::Result<()> {
     // - `config`: Used for most operations (includes CLI argument overrides)
     // - `original_config`: Used for config commands that need the raw file content
     let (config, original_config) = {
-        // 1. Load config.yaml
-        let config = YamlLoader::new(&fs).load_config()?;
+        // 1. Load config.yaml, and detect the architecture and any `auto` environment
+        let mut config = YamlLoader::new(&fs).load_config()?;
+        let command_runner = ShellCommandRunner::new("/bin/sh", config.command_timeout());
+        config.detect_environment(&fs, &command_runner).await;
 
         // 2. Apply CLI args to config (overriding)
         (args.apply_to_config(config.clone()), config)
@@ -112,6 +117,12 @@ async fn main() -> anyhow::Result<()> {
     // TODO: Maybe don't need to build this until it's needed?
     let reporter = TerminalProgressReporter::new(config.use_colors());
 
+    if let Some(reason) = config.undetected_environment() {
+        reporter.report_warning(format!(
+            "{reason}. Add a `detect` rule that matches this machine, name the environment in the config file, or pass --environment."
+        ));
+    }
+
     // 3. Dispatch and execute the requested command
     let exit_code = dispatch_command(&args.command, &config, original_config, reporter).await;
 
@@ -0,0 +1,117 @@
+pub mod common;
+
+use std::io::Write;
+
+use common::{SELFIE_ENV, add_package, get_command_with_test_config, setup_default_test_config};
+use predicates::prelude::*;
+use selfie::package::PackageBuilder;
+use tempfile::TempDir;
+use test_common::read_log;
+
+/// Add a package whose install command logs its name to `install.log` and
+/// creates a marker file that its check command looks for
+fn add_marker_package(temp_dir: &TempDir, name: &str, install: &str) {
+    let marker = temp_dir.path().join(format!("{name}.installed"));
+    let log = temp_dir.path().join("install.log");
+    let package = PackageBuilder::default()
+        .name(name)
+        .version("1.0.0")
+        .environment(SELFIE_ENV, |b| {
+            b.install(format!(
+                "echo {name} >> {} && {install} && touch {}",
+                log.display(),
+                marker.display()
+            ))
+            .check(Some(format!("test -f {}", marker.display())))
+        })
+        .build();
+    add_package(temp_dir, &package);
+}
+
+#[test]
+fn test_apply_is_idempotent() {
+    let temp_dir = setup_default_test_config();
+    add_marker_package(&temp_dir, "git", "true");
+    add_marker_package(&temp_dir, "ripgrep", "true");
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["--no-color", "apply"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Converge Report"));
+    assert_eq!(read_log(temp_dir.path().join("install.log")).len(), 2);
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["--no-color", "apply"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("git, ripgrep"));
+    assert_eq!(read_log(temp_dir.path().join("install.log")).len(), 2);
+}
+
+#[test]
+fn test_apply_failure_exit_code() {
+    let temp_dir = setup_default_test_config();
+    add_marker_package(&temp_dir, "broken", "false");
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["--no-color", "apply"]);
+
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("Converge Report"));
+}
+
+#[test]
+fn test_apply_set() {
+    let temp_dir = setup_default_test_config();
+    add_marker_package(&temp_dir, "git", "true");
+    add_marker_package(&temp_dir, "ripgrep", "true");
+
+    let config_path = temp_dir.path().join(".config/selfie/config.yaml");
+    let mut config_file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(config_path)
+        .unwrap();
+    writeln!(config_file, "sets:\n  ci: [git]").unwrap();
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["--no-color", "apply", "--set", "ci"]);
+    cmd.assert().success();
+    assert_eq!(read_log(temp_dir.path().join("install.log")), ["git"]);
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["apply", "--set", "work"]);
+    cmd.assert().code(2).stderr(predicate::str::contains(
+        "Package set 'work' is not defined",
+    ));
+}
+
+#[test]
+fn test_apply_unresolvable_package() {
+    let temp_dir = setup_default_test_config();
+    add_marker_package(&temp_dir, "git", "true");
+    let package = PackageBuilder::default()
+        .name("broken")
+        .version("1.0.0")
+        .environment(SELFIE_ENV, |b| {
+            b.install("true").dependencies(vec!["missing"])
+        })
+        .build();
+    add_package(&temp_dir, &package);
+
+    let config_path = temp_dir.path().join(".config/selfie/config.yaml");
+    let mut config_file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(config_path)
+        .unwrap();
+    writeln!(config_file, "stop_on_error: false").unwrap();
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["--no-color", "apply"]);
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("Converge Report"))
+        .stdout(predicate::str::contains("broken"));
+    assert_eq!(read_log(temp_dir.path().join("install.log")), ["git"]);
+}
@@ -137,9 +137,50 @@ fn test_cli_package_check() {
     cmd.assert().success();
 }
 
+#[test]
+fn test_cli_package_check_all() {
+    let temp_dir = setup_default_test_config();
+    for (name, check) in [("installed", "true"), ("missing", "false")] {
+        let package = PackageBuilder::default()
+            .name(name)
+            .version("0.1.0")
+            .environment(SELFIE_ENV, |builder| {
+                builder.install("echo 'hi'").check_some(check)
+            })
+            .build();
+        add_package(&temp_dir, &package);
+    }
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["package", "check", "--all"]);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Check Summary"))
+        .stdout(predicate::str::contains("installed"))
+        .stdout(predicate::str::contains("missing"));
+}
+
+#[test]
+fn test_cli_package_check_requires_name_or_all() {
+    let temp_dir = setup_default_test_config();
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["package", "check"]);
+    cmd.assert().failure();
+}
+
 #[test]
 fn test_cli_package_install() {
     let temp_dir = setup_default_test_config();
+
+    let package = PackageBuilder::default()
+        .name("test-package")
+        .version("1.0.0")
+        .environment(SELFIE_ENV, |b| b.install("echo 'installed'"))
+        .build();
+
+    add_package(&temp_dir, &package);
+
     let mut cmd = get_command_with_test_config(&temp_dir);
     cmd.args(["package", "install", "test-package"]);
     cmd.assert().success();
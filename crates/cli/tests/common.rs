@@ -48,12 +48,7 @@ fn _setup_test_config(config_yaml: Option<&str>) -> TempDir {
 }
 
 pub fn add_package(base_dir: &TempDir, package: &Package) {
-    let yaml = serde_yaml::to_string(package).unwrap();
-    let packages_path = base_dir.path().join("packages");
-    fs::create_dir_all(&packages_path).unwrap();
-    let package_path = packages_path.join(format!("{}.yaml", package.name()));
-
-    fs::write(package_path, yaml).unwrap();
+    test_common::create_package_file(base_dir.path().join("packages"), package);
 }
 
 // Helper function to get a command instance with environment variables pointing to our test config
@@ -68,6 +63,12 @@ pub fn get_command_with_test_config(temp_dir: &TempDir) -> Command {
         temp_dir.path().join(".config").join("selfie"),
     );
 
+    // Keep install state out of the real user data directory
+    cmd.env(
+        "SELFIE_DATA_DIR",
+        temp_dir.path().join(".local").join("share").join("selfie"),
+    );
+
     cmd
 }
 
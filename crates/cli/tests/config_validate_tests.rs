@@ -52,3 +52,23 @@ package_directory: "relative/path"
         .failure()
         .stderr(predicates::str::contains("exists, but cannot be expanded"));
 }
+
+#[test]
+fn test_validate_reports_detected_environment() {
+    let yaml = r#"
+environment: auto
+package_directory: "/test/packages"
+detect:
+  linux: { kernel: linux }
+  macos: { kernel: darwin }
+"#;
+
+    let temp_dir = setup_test_config(yaml);
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["config", "validate"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("(detected)"))
+        .stdout(predicates::str::contains("detected from:"));
+}
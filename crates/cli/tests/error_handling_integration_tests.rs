@@ -206,7 +206,8 @@ environments:
 
     cmd.assert()
         .failure()
-        .stderr(predicate::str::contains("Failed to load package"));
+        .stdout(predicate::str::contains("Install command is required"))
+        .stderr(predicate::str::contains("validation failed"));
 }
 
 // =============================================================================
@@ -275,7 +276,7 @@ fn test_package_install_missing_environment_error() {
     let mut cmd = get_command_with_test_config(&temp_dir);
     cmd.args(["package", "install", "wrong-env-package"]);
 
-    cmd.assert().success(); // Install command doesn't validate environment yet
+    cmd.assert().failure();
 }
 
 // =============================================================================
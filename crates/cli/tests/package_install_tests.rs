@@ -24,5 +24,169 @@ fn test_package_install() {
 
     cmd.assert()
         .success()
-        .stdout(predicate::str::contains("will be installed in"));
+        .stdout(predicate::str::contains("already installed"));
+}
+
+#[test]
+fn test_package_install_force() {
+    let temp_dir = setup_default_test_config();
+
+    let package = PackageBuilder::default()
+        .name("test-package")
+        .version("1.0.0")
+        .environment(SELFIE_ENV, |b| {
+            b.install("echo 'Installing test package'")
+                .check_some("echo 'Checking test package'")
+        })
+        .build();
+    add_package(&temp_dir, &package);
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["package", "install", "--force", "test-package"]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Installation completed successfully",
+    ));
+}
+
+#[test]
+fn test_package_install_not_installed() {
+    let temp_dir = setup_default_test_config();
+    let marker = temp_dir.path().join("installed");
+
+    let package = PackageBuilder::default()
+        .name("test-package")
+        .version("1.0.0")
+        .environment(SELFIE_ENV, |b| {
+            b.install(format!("touch {}", marker.display()))
+                .check_some(format!("test -f {}", marker.display()))
+        })
+        .build();
+    add_package(&temp_dir, &package);
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["package", "install", "test-package"]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Installation completed successfully",
+    ));
+}
+
+#[test]
+fn test_package_install_not_detected_after_install() {
+    let temp_dir = setup_default_test_config();
+
+    let package = PackageBuilder::default()
+        .name("test-package")
+        .version("1.0.0")
+        .environment(SELFIE_ENV, |b| {
+            b.install("echo 'Installing test package'")
+                .check_some("exit 1")
+        })
+        .build();
+    add_package(&temp_dir, &package);
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["package", "install", "test-package"]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "install command succeeded but package not detected",
+    ));
+}
+
+#[test]
+fn test_package_install_dry_run() {
+    let temp_dir = setup_default_test_config();
+
+    let package = PackageBuilder::default()
+        .name("test-package")
+        .version("1.0.0")
+        .environment(SELFIE_ENV, |b| {
+            b.install("echo 'Installing test package'")
+                .check_some("false")
+        })
+        .build();
+    add_package(&temp_dir, &package);
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["package", "install", "--dry-run", "test-package"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Install plan"))
+        .stdout(predicate::str::contains("echo 'Installing test package'"))
+        .stdout(predicate::str::contains("Installing test package\n").not());
+}
+
+#[test]
+fn test_package_install_several_packages() {
+    let temp_dir = setup_default_test_config();
+
+    for name in ["first-package", "second-package"] {
+        let package = PackageBuilder::default()
+            .name(name)
+            .version("1.0.0")
+            .environment(SELFIE_ENV, |b| {
+                b.install(format!("echo 'Installing {name}'"))
+            })
+            .build();
+        add_package(&temp_dir, &package);
+    }
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["package", "install", "first-package", "second-package"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2 installed"));
+}
+
+#[test]
+fn test_package_install_all() {
+    let temp_dir = setup_default_test_config();
+
+    let package = PackageBuilder::default()
+        .name("test-package")
+        .version("1.0.0")
+        .environment(SELFIE_ENV, |b| b.install("echo 'Installing test package'"))
+        .build();
+    add_package(&temp_dir, &package);
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["package", "install", "--all"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1 installed"));
+}
+
+#[test]
+fn test_package_install_rollback_on_failure() {
+    let temp_dir = setup_default_test_config();
+    let leftover = temp_dir.path().join("leftover");
+
+    let package = PackageBuilder::default()
+        .name("test-package")
+        .version("1.0.0")
+        .environment(SELFIE_ENV, |b| {
+            b.install(format!("touch {} && exit 1", leftover.display()))
+                .check_some("false")
+                .rollback(vec![format!("rm {}", leftover.display())])
+        })
+        .build();
+    add_package(&temp_dir, &package);
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args([
+        "package",
+        "install",
+        "--rollback-on-failure",
+        "test-package",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Rolling back 'test-package'"))
+        .stderr(predicate::str::contains("(rolled back)"));
+    assert!(!leftover.exists(), "The rollback should have cleaned up");
 }
@@ -0,0 +1,74 @@
+pub mod common;
+
+use std::io::Write;
+
+use common::{SELFIE_ENV, add_package, get_command_with_test_config, setup_default_test_config};
+use predicates::prelude::*;
+use selfie::package::PackageBuilder;
+use tempfile::TempDir;
+
+/// Set up the default config plus `dev` and `ci` sets that share `git`, where
+/// each package's install command logs its name to `install.log`
+fn setup_set_config() -> TempDir {
+    let temp_dir = setup_default_test_config();
+
+    let config_path = temp_dir.path().join(".config/selfie/config.yaml");
+    let mut config_file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(config_path)
+        .unwrap();
+    writeln!(config_file, "sets:\n  dev: [git, ripgrep]\n  ci: [git]").unwrap();
+
+    let log = temp_dir.path().join("install.log");
+    for name in ["git", "ripgrep"] {
+        let package = PackageBuilder::default()
+            .name(name)
+            .version("1.0.0")
+            .environment(SELFIE_ENV, |b| {
+                b.install(format!("echo {name} >> {}", log.display()))
+            })
+            .build();
+        add_package(&temp_dir, &package);
+    }
+
+    temp_dir
+}
+
+#[test]
+fn test_set_list() {
+    let temp_dir = setup_set_config();
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["--no-color", "set", "list"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("git, ripgrep"));
+}
+
+#[test]
+fn test_set_install_shared_package_once() {
+    let temp_dir = setup_set_config();
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["--no-color", "set", "install", "dev", "ci"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Install Summary by Set"));
+
+    let log = std::fs::read_to_string(temp_dir.path().join("install.log")).unwrap();
+    assert_eq!(log.lines().collect::<Vec<_>>(), ["git", "ripgrep"]);
+}
+
+#[test]
+fn test_set_install_unknown_set() {
+    let temp_dir = setup_set_config();
+
+    let mut cmd = get_command_with_test_config(&temp_dir);
+    cmd.args(["set", "install", "work"]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Package set 'work' is not defined",
+    ));
+}
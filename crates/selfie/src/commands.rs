@@ -3,7 +3,7 @@
 pub mod runner;
 pub mod shell;
 
-pub use runner::{CommandError, CommandOutput, CommandRunner, OutputChunk};
+pub use runner::{CommandError, CommandOutput, CommandRequest, CommandRunner, OutputChunk};
 pub use shell::ShellCommandRunner;
 
 #[cfg(test)]
@@ -4,7 +4,15 @@
 //! in a cross-platform manner. It implements the Command Runner port pattern
 //! to allow different command execution strategies while maintaining a consistent interface.
 
-use std::{borrow::Cow, fmt, path::PathBuf, process::Output, sync::Arc, time::Duration};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    process::Output,
+    sync::Arc,
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use thiserror::Error;
@@ -30,6 +38,98 @@ impl fmt::Display for OutputChunk {
     }
 }
 
+/// A command to run, with everything about how it runs spelled out
+///
+/// Unlike [`CommandRunner::execute_with_timeout`], a request says which directory
+/// the command runs in and which environment variables it gets on top of the
+/// caller's environment, so nothing depends on the process's current directory.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use selfie::commands::CommandRequest;
+///
+/// let request = CommandRequest::new("make install", Duration::from_secs(60))
+///     .working_directory("/tmp/build")
+///     .env("PREFIX", "/usr/local");
+/// assert_eq!(request.command(), "make install");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandRequest {
+    command: String,
+    timeout: Duration,
+    working_directory: Option<PathBuf>,
+    env: BTreeMap<String, String>,
+}
+
+impl CommandRequest {
+    /// Create a request to run `command` for at most `timeout`, in the current
+    /// directory and with no extra environment variables
+    #[must_use]
+    pub fn new(command: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            command: command.into(),
+            timeout,
+            working_directory: None,
+            env: BTreeMap::new(),
+        }
+    }
+
+    /// Run the command in `directory`
+    #[must_use]
+    pub fn working_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.working_directory = Some(directory.into());
+        self
+    }
+
+    /// Set an environment variable for the command, replacing any earlier value
+    #[must_use]
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set several environment variables for the command
+    #[must_use]
+    pub fn envs<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env.extend(
+            vars.into_iter()
+                .map(|(name, value)| (name.into(), value.into())),
+        );
+        self
+    }
+
+    /// The shell command to run
+    #[must_use]
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// How long the command may run
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// The directory the command runs in, or `None` for the current directory
+    #[must_use]
+    pub fn working_dir(&self) -> Option<&Path> {
+        self.working_directory.as_deref()
+    }
+
+    /// The environment variables the command gets on top of the caller's
+    #[must_use]
+    pub fn env_vars(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+}
+
 /// Port for command execution (Hexagonal Architecture)
 ///
 /// This trait abstracts command execution to allow different implementations
@@ -93,6 +193,24 @@ pub trait CommandRunner: Send + Sync {
         timeout: Duration,
     ) -> Result<CommandOutput, CommandError>;
 
+    /// Run a [`CommandRequest`] and wait for completion
+    ///
+    /// Like [`execute_with_timeout`](CommandRunner::execute_with_timeout), but
+    /// the command runs in the request's working directory with its environment
+    /// variables set.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The command, its timeout, working directory and environment
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CommandError`] if:
+    /// - The command cannot be started (IO error), e.g. because the working
+    ///   directory doesn't exist
+    /// - The command times out before completion
+    async fn run(&self, request: &CommandRequest) -> Result<CommandOutput, CommandError>;
+
     /// Execute a command with streaming output
     ///
     /// Runs the command and streams stdout/stderr output through the provided
@@ -5,7 +5,7 @@
 //! and streaming execution modes with configurable timeouts.
 
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     process::{Output, Stdio},
     sync::Arc,
     time::{Duration, Instant},
@@ -15,7 +15,7 @@ use async_trait::async_trait;
 
 use tokio::{io::AsyncReadExt, process::Command, sync::mpsc};
 
-use super::runner::{CommandError, CommandOutput, CommandRunner, OutputChunk};
+use super::runner::{CommandError, CommandOutput, CommandRequest, CommandRunner, OutputChunk};
 
 /// Shell command runner implementation
 ///
@@ -123,20 +123,49 @@ impl CommandRunner for ShellCommandRunner {
         command: &str,
         timeout: Duration,
     ) -> Result<CommandOutput, CommandError> {
+        self.run(&CommandRequest::new(command, timeout)).await
+    }
+
+    /// Run a command request
+    ///
+    /// Runs the request's shell command in its working directory, with its
+    /// environment variables added to the ones this process has, and waits for
+    /// completion within its timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The command, its timeout, working directory and environment
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CommandError`] if:
+    /// - The command cannot be started (IO error), e.g. because the working
+    ///   directory doesn't exist
+    /// - The command times out before completion
+    async fn run(&self, request: &CommandRequest) -> Result<CommandOutput, CommandError> {
         let start_time = Instant::now();
+        let command = request.command();
 
         let mut cmd = Command::new(&self.shell);
-        cmd.arg("-c").arg(command).stdin(Stdio::null());
+        cmd.arg("-c")
+            .arg(command)
+            .envs(request.env_vars())
+            .stdin(Stdio::null());
 
-        let working_directory =
-            std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf());
+        let working_directory = match request.working_dir() {
+            Some(directory) => {
+                cmd.current_dir(directory);
+                directory.to_path_buf()
+            }
+            None => current_dir(),
+        };
 
         // Execute the command within the context of a timeout
-        let output = tokio::time::timeout(timeout, cmd.output())
+        let output = tokio::time::timeout(request.timeout(), cmd.output())
             .await
             .map_err(|_| CommandError::Timeout {
                 command: command.to_string(),
-                timeout,
+                timeout: request.timeout(),
                 working_directory: working_directory.clone(),
             })?
             .map_err(|e| CommandError::IoError {
@@ -193,7 +222,7 @@ impl CommandRunner for ShellCommandRunner {
             Err(e) => {
                 return Err(CommandError::IoError {
                     command: command.to_string(),
-                    working_directory: Path::new(".").to_path_buf(),
+                    working_directory: current_dir(),
                     source: Arc::new(e),
                 });
             }
@@ -240,8 +269,7 @@ impl CommandRunner for ShellCommandRunner {
                     status = child.wait(), if !process_done => {
                         exit_status = Some(status.map_err(|e| CommandError::IoError {
                             command: command.to_string(),
-                            working_directory: std::env::current_dir()
-                                .unwrap_or_else(|_| Path::new(".").to_path_buf()),
+                            working_directory: current_dir(),
                             source: Arc::new(e),
                         })?);
                         process_done = true;
@@ -280,13 +308,18 @@ impl CommandRunner for ShellCommandRunner {
             Err(CommandError::Timeout {
                 command: command.to_string(),
                 timeout,
-                working_directory: std::env::current_dir()
-                    .unwrap_or_else(|_| Path::new(".").to_path_buf()),
+                working_directory: current_dir(),
             })
         }
     }
 }
 
+/// The directory commands run in when a request doesn't name one, for error
+/// reports
+fn current_dir() -> PathBuf {
+    std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf())
+}
+
 /// Handle the result of reading a chunk from stdout or stderr
 ///
 /// Processes the result of an async read operation, updating the full output
@@ -330,8 +363,7 @@ async fn handle_chunked_read_result(
         }
         Err(e) => Err(CommandError::IoError {
             command: "streaming command".to_string(),
-            working_directory: std::env::current_dir()
-                .unwrap_or_else(|_| Path::new(".").to_path_buf()),
+            working_directory: current_dir(),
             source: Arc::new(e),
         }),
     }
@@ -340,7 +372,6 @@ async fn handle_chunked_read_result(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
 
     // These tests will actually run commands on the system
     // They could be skipped in CI environments if necessary
@@ -363,6 +394,29 @@ mod tests {
         assert_eq!(output.exit_code(), 1);
     }
 
+    #[tokio::test]
+    async fn test_run_request_directory_and_env() {
+        let runner = ShellCommandRunner::new("/bin/sh", Duration::from_secs(10));
+        let directory = tempfile::tempdir().unwrap();
+        let directory = directory.path().canonicalize().unwrap();
+
+        let request = CommandRequest::new("pwd -P; echo \"$GREETING\"", Duration::from_secs(10))
+            .working_directory(&directory)
+            .env("GREETING", "hello there");
+        let output = runner.run(&request).await.unwrap();
+        assert_eq!(
+            output.stdout_str(),
+            format!("{}\nhello there\n", directory.display())
+        );
+
+        let missing = CommandRequest::new("true", Duration::from_secs(10))
+            .working_directory(directory.join("missing"));
+        assert!(matches!(
+            runner.run(&missing).await,
+            Err(CommandError::IoError { working_directory, .. }) if working_directory == directory.join("missing")
+        ));
+    }
+
     #[tokio::test]
     async fn test_command_availability() {
         let runner = ShellCommandRunner::new("/bin/sh", Duration::from_secs(10));
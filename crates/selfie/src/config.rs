@@ -1,29 +1,62 @@
+pub mod detect;
 pub mod loader;
 pub mod validate;
 pub mod yaml;
 
 pub use self::yaml::YamlLoader;
 
+use self::detect::{AUTO_ENVIRONMENT, DetectRule, OsFacts};
+
 use std::{
+    collections::BTreeMap,
     num::{NonZeroU64, NonZeroUsize},
-    path::PathBuf,
+    path::{Path, PathBuf},
     time::Duration,
 };
 
 use serde::Deserialize;
 
+use crate::{commands::runner::CommandRunner, fs::FileSystem};
+
 const VERBOSE_DEFAULT: bool = false;
 const USE_COLORS_DEFAULT: bool = true;
 const STOP_ON_ERROR_DEFAULT: bool = true;
+const RETRIES_DEFAULT: u32 = 0;
+const RETRY_DELAY_DEFAULT: u64 = 1;
 
 /// Comprehensive application configuration that combines file config and CLI args
 #[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct AppConfig {
     // Core settings
+    /// The environment name, or `auto` to detect it from the machine
     pub(crate) environment: String,
     pub(crate) package_directory: PathBuf,
 
+    /// Rules mapping environment names to the machine facts they expect, used
+    /// when `environment` is `auto`
+    #[serde(default)]
+    pub(crate) detect: BTreeMap<String, DetectRule>,
+
+    /// Environments to fall back to, in order, for packages that don't configure
+    /// the current one, e.g. `[debian, linux, default]`
+    #[serde(default)]
+    pub(crate) environment_fallbacks: Vec<String>,
+
+    /// Facts the environment was detected from (not deserialized)
+    #[serde(skip)]
+    pub(crate) os_facts: Option<OsFacts>,
+
+    /// The machine architecture from `uname -m`, found when the environment is
+    /// detected (not deserialized)
+    #[serde(skip)]
+    pub(crate) arch: Option<String>,
+
+    /// The user's home directory, found when the config is loaded (not
+    /// deserialized)
+    #[serde(skip)]
+    pub(crate) home_directory: Option<PathBuf>,
+
     // UI settings
     #[serde(default)]
     pub(crate) verbose: bool,
@@ -40,12 +73,40 @@ pub struct AppConfig {
 
     #[serde(default = "default_max_parallel")]
     pub(crate) max_parallel_installations: NonZeroUsize,
+
+    /// How many times to retry a package command that fails or times out
+    #[serde(default)]
+    pub(crate) retries: u32,
+
+    /// Seconds to wait before the first retry; doubles with every further retry
+    #[serde(default = "default_retry_delay")]
+    pub(crate) retry_delay: u64,
+
+    /// Variables for the `{{placeholders}}` in package commands; a package's own
+    /// `vars` take precedence
+    #[serde(default)]
+    pub(crate) vars: BTreeMap<String, String>,
+
+    // Package sets
+    /// Named groups of packages, e.g. `dev: [git, ripgrep, neovim]`
+    #[serde(default)]
+    pub(crate) sets: BTreeMap<String, Vec<String>>,
+
+    // State settings
+    /// Where selfie keeps its record of what it installed; defaults to the user
+    /// data directory
+    #[serde(default)]
+    pub(crate) state_directory: Option<PathBuf>,
 }
 
 fn default_command_timeout() -> NonZeroU64 {
     unsafe { NonZeroU64::new_unchecked(60) }
 }
 
+fn default_retry_delay() -> u64 {
+    RETRY_DELAY_DEFAULT
+}
+
 fn default_stop_on_error() -> bool {
     true
 }
@@ -64,11 +125,118 @@ impl AppConfig {
         &self.environment
     }
 
+    #[must_use]
+    pub fn environment_fallbacks(&self) -> &[String] {
+        &self.environment_fallbacks
+    }
+
+    /// The environments to look for in a package, in order: the current
+    /// environment, then its fallbacks
+    #[must_use]
+    pub fn environment_chain(&self) -> Vec<&str> {
+        let mut chain = vec![self.environment.as_str()];
+        for fallback in &self.environment_fallbacks {
+            if !chain.contains(&fallback.as_str()) {
+                chain.push(fallback);
+            }
+        }
+        chain
+    }
+
     #[must_use]
     pub fn package_directory(&self) -> &PathBuf {
         &self.package_directory
     }
 
+    #[must_use]
+    pub fn detect(&self) -> &BTreeMap<String, DetectRule> {
+        &self.detect
+    }
+
+    /// The machine facts the environment was detected from, if the config asked
+    /// for `environment: auto`
+    #[must_use]
+    pub fn os_facts(&self) -> Option<&OsFacts> {
+        self.os_facts.as_ref()
+    }
+
+    /// The machine architecture, e.g. `x86_64` or `aarch64`
+    ///
+    /// This is `uname -m` once [`detect_environment`](Self::detect_environment)
+    /// has run, or the architecture selfie was built for before then. Detection
+    /// and `when: { arch: ... }` compare against this name, after
+    /// [`normalize_arch`](detect::normalize_arch)
+    #[must_use]
+    pub fn arch(&self) -> &str {
+        self.arch.as_deref().unwrap_or(std::env::consts::ARCH)
+    }
+
+    /// The user's home directory, for `{{home}}` in package commands
+    #[must_use]
+    pub fn home_directory(&self) -> Option<&Path> {
+        self.home_directory.as_deref()
+    }
+
+    /// Whether the environment was detected from the machine rather than named
+    #[must_use]
+    pub fn environment_detected(&self) -> bool {
+        self.os_facts.is_some() && self.environment != AUTO_ENVIRONMENT
+    }
+
+    /// Resolve `environment: auto` from the machine's facts and the `detect`
+    /// rules
+    ///
+    /// A named environment is left alone. If no environment can be detected, it
+    /// stays `auto`, and [`undetected_environment`](Self::undetected_environment)
+    /// says why.
+    pub fn resolve_environment(&mut self, facts: OsFacts) {
+        if self.environment != AUTO_ENVIRONMENT {
+            return;
+        }
+        if let Some(environment) = facts.environment(&self.detect) {
+            self.environment = environment;
+        }
+        self.arch = Some(facts.arch.clone());
+        self.os_facts = Some(facts);
+    }
+
+    /// Gather the machine's facts and resolve `environment: auto` from them
+    ///
+    /// A named environment only has its architecture looked up, for `{{arch}}`
+    /// and `when: { arch: ... }`; the rest of the machine is only inspected when
+    /// the config asks for it.
+    pub async fn detect_environment<F, CR>(&mut self, fs: &F, command_runner: &CR)
+    where
+        F: FileSystem,
+        CR: CommandRunner,
+    {
+        if self.environment == AUTO_ENVIRONMENT {
+            self.resolve_environment(OsFacts::gather(fs, command_runner).await);
+        } else {
+            self.arch = Some(detect::machine_arch(command_runner).await);
+        }
+    }
+
+    /// Why `environment: auto` couldn't be resolved for this machine, or `None`
+    /// if the environment is known
+    #[must_use]
+    pub fn undetected_environment(&self) -> Option<String> {
+        (self.environment == AUTO_ENVIRONMENT).then(|| {
+            let facts = self.os_facts.as_ref().map_or_else(
+                || "no machine facts were gathered".to_string(),
+                |facts| {
+                    format!(
+                        "kernel `{}`, arch `{}`, os_id `{}`",
+                        facts.kernel(),
+                        facts.arch(),
+                        facts.os_id().unwrap_or("unknown")
+                    )
+                },
+            );
+            format!("The environment couldn't be detected for this machine ({facts})")
+        })
+    }
+
     #[must_use]
     pub fn verbose(&self) -> bool {
         self.verbose
@@ -94,6 +262,31 @@ impl AppConfig {
         self.stop_on_error
     }
 
+    #[must_use]
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    #[must_use]
+    pub fn retry_delay(&self) -> Duration {
+        Duration::from_secs(self.retry_delay)
+    }
+
+    #[must_use]
+    pub fn vars(&self) -> &BTreeMap<String, String> {
+        &self.vars
+    }
+
+    #[must_use]
+    pub fn sets(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.sets
+    }
+
+    #[must_use]
+    pub fn state_directory(&self) -> Option<&PathBuf> {
+        self.state_directory.as_ref()
+    }
+
     pub fn environment_mut(&mut self) -> &mut String {
         &mut self.environment
     }
@@ -116,12 +309,19 @@ impl AppConfig {
 #[derive(Default, Debug)]
 pub struct AppConfigBuilder {
     environment: String,
+    environment_fallbacks: Vec<String>,
     package_directory: PathBuf,
     verbose: Option<bool>,
     use_colors: Option<bool>,
     command_timeout: Option<NonZeroU64>,
     max_parallel: Option<NonZeroUsize>,
     stop_on_error: Option<bool>,
+    retries: Option<u32>,
+    retry_delay: Option<u64>,
+    vars: BTreeMap<String, String>,
+    sets: BTreeMap<String, Vec<String>>,
+    state_directory: Option<PathBuf>,
+    home_directory: Option<PathBuf>,
 }
 
 impl AppConfigBuilder {
@@ -131,6 +331,12 @@ impl AppConfigBuilder {
         self
     }
 
+    #[must_use]
+    pub fn environment_fallbacks<T: ToString>(mut self, fallbacks: Vec<T>) -> Self {
+        self.environment_fallbacks = fallbacks.into_iter().map(|f| f.to_string()).collect();
+        self
+    }
+
     #[must_use]
     pub fn package_directory<D>(mut self, package_directory: D) -> Self
     where
@@ -170,16 +376,71 @@ impl AppConfigBuilder {
         self
     }
 
+    #[must_use]
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    #[must_use]
+    pub fn retry_delay(mut self, seconds: u64) -> Self {
+        self.retry_delay = Some(seconds);
+        self
+    }
+
+    #[must_use]
+    pub fn var(mut self, name: &str, value: &str) -> Self {
+        self.vars.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn set<T: ToString>(mut self, name: &str, members: Vec<T>) -> Self {
+        self.sets.insert(
+            name.to_string(),
+            members.into_iter().map(|m| m.to_string()).collect(),
+        );
+        self
+    }
+
+    #[must_use]
+    pub fn state_directory<D>(mut self, state_directory: D) -> Self
+    where
+        D: AsRef<std::ffi::OsStr>,
+    {
+        self.state_directory = Some(PathBuf::from(state_directory.as_ref()));
+        self
+    }
+
+    #[must_use]
+    pub fn home_directory<D>(mut self, home_directory: D) -> Self
+    where
+        D: AsRef<std::ffi::OsStr>,
+    {
+        self.home_directory = Some(PathBuf::from(home_directory.as_ref()));
+        self
+    }
+
     #[must_use]
     pub fn build(self) -> AppConfig {
         AppConfig {
             environment: self.environment,
             package_directory: self.package_directory,
+            detect: BTreeMap::new(),
+            environment_fallbacks: self.environment_fallbacks,
+            os_facts: None,
+            arch: None,
+            home_directory: self.home_directory,
             verbose: self.verbose.unwrap_or(VERBOSE_DEFAULT),
             use_colors: self.use_colors.unwrap_or(USE_COLORS_DEFAULT),
             command_timeout: self.command_timeout.unwrap_or(default_command_timeout()),
             max_parallel_installations: self.max_parallel.unwrap_or(default_max_parallel()),
             stop_on_error: self.stop_on_error.unwrap_or(STOP_ON_ERROR_DEFAULT),
+            retries: self.retries.unwrap_or(RETRIES_DEFAULT),
+            retry_delay: self.retry_delay.unwrap_or(RETRY_DELAY_DEFAULT),
+            vars: self.vars,
+            sets: self.sets,
+            state_directory: self.state_directory,
         }
     }
 }
@@ -221,6 +482,8 @@ mod tests {
             .command_timeout_unchecked(120)
             .max_parallel_unchecked(8)
             .stop_on_error(false)
+            .retries(3)
+            .retry_delay(5)
             .build();
 
         // Test read accessors
@@ -231,6 +494,8 @@ mod tests {
         assert_eq!(config.command_timeout(), Duration::from_secs(120));
         assert_eq!(config.max_parallel_installations().get(), 8);
         assert!(!config.stop_on_error());
+        assert_eq!(config.retries(), 3);
+        assert_eq!(config.retry_delay(), Duration::from_secs(5));
     }
 
     #[test]
@@ -271,6 +536,8 @@ mod tests {
         assert_eq!(config.command_timeout().as_secs(), 60);
         assert!(config.max_parallel_installations().get() > 0); // Should be based on CPUs or default
         assert_eq!(config.stop_on_error(), STOP_ON_ERROR_DEFAULT);
+        assert_eq!(config.retries(), RETRIES_DEFAULT);
+        assert_eq!(config.retry_delay().as_secs(), RETRY_DELAY_DEFAULT);
     }
 
     #[test]
@@ -332,6 +599,60 @@ mod tests {
         assert!(config.stop_on_error); // Default
     }
 
+    #[test]
+    fn test_environment_chain() {
+        let config = AppConfigBuilder::default()
+            .environment("ubuntu")
+            .environment_fallbacks(vec!["ubuntu", "debian", "linux", "default"])
+            .package_directory("/test/path")
+            .build();
+
+        assert_eq!(
+            config.environment_chain(),
+            ["ubuntu", "debian", "linux", "default"]
+        );
+
+        let config = AppConfigBuilder::default()
+            .environment("macos")
+            .package_directory("/test/path")
+            .build();
+        assert_eq!(config.environment_chain(), ["macos"]);
+    }
+
+    #[test]
+    fn test_resolve_auto_environment() {
+        let yaml = r"
+            environment: auto
+            package_directory: /opt/packages
+            detect:
+              arch-linux: { os_id: arch }
+        ";
+        let mut config: AppConfig = serde_yaml::from_str(yaml).unwrap();
+        config.resolve_environment(OsFacts::new(Some("ID=arch"), "Linux", "x86_64"));
+        assert_eq!(config.environment(), "arch-linux");
+        assert_eq!(config.arch(), "x86_64");
+        assert!(config.environment_detected());
+
+        let mut undetected: AppConfig = serde_yaml::from_str(yaml).unwrap();
+        undetected.resolve_environment(OsFacts::new(None, "FreeBSD", "amd64"));
+        assert_eq!(undetected.environment(), AUTO_ENVIRONMENT);
+        assert!(!undetected.environment_detected());
+        assert!(
+            undetected
+                .undetected_environment()
+                .is_some_and(|reason| reason.contains("kernel `freebsd`"))
+        );
+        assert_eq!(config.undetected_environment(), None);
+
+        let mut named = AppConfigBuilder::default()
+            .environment("macos")
+            .package_directory("/opt/packages")
+            .build();
+        named.resolve_environment(OsFacts::new(Some("ID=arch"), "Linux", "x86_64"));
+        assert_eq!(named.environment(), "macos");
+        assert!(named.os_facts().is_none());
+    }
+
     #[test]
     fn test_deny_unknown_fields() {
         // YAML string with an unknown field `unknown_field`
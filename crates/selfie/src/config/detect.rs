@@ -0,0 +1,393 @@
+//! Environment detection
+//!
+//! Instead of naming its environment, a config file can say `environment: auto`
+//! and let selfie work it out from facts about the machine: the `ID`, `ID_LIKE`
+//! and `VERSION_ID` in `/etc/os-release`, the kernel name from `uname -s`, and
+//! the architecture from `uname -m`.
+//!
+//! The config's `detect` rules map environment names to the facts they expect,
+//! e.g. `detect: { arch-linux: { os_id: arch } }`. The rule with the most
+//! matching conditions wins. Without a matching rule, the environment is the
+//! `os-release` `ID` (e.g. `ubuntu`), or `macos` on Darwin.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::Deserialize;
+
+use crate::{commands::runner::CommandRunner, fs::FileSystem};
+
+/// The `environment` value that asks selfie to detect the environment
+pub const AUTO_ENVIRONMENT: &str = "auto";
+
+const OS_RELEASE_PATHS: [&str; 2] = ["/etc/os-release", "/usr/lib/os-release"];
+
+/// Facts about the machine that environments are detected from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OsFacts {
+    /// `ID` from `os-release`, e.g. `ubuntu` or `arch`
+    pub(crate) os_id: Option<String>,
+
+    /// `ID_LIKE` from `os-release`, e.g. `[debian]` on Ubuntu
+    pub(crate) os_id_like: Vec<String>,
+
+    /// `VERSION_ID` from `os-release`, e.g. `24.04`
+    pub(crate) os_version: Option<String>,
+
+    /// Lowercased kernel name from `uname -s`, e.g. `linux` or `darwin`
+    pub(crate) kernel: String,
+
+    /// Machine architecture, normalized with [`normalize_arch`], e.g. `x86_64`
+    /// or `aarch64`
+    pub(crate) arch: String,
+}
+
+/// Conditions a machine must meet for a `detect` rule to pick its environment
+///
+/// Every condition that's set must match, case-insensitively. A rule with no
+/// conditions matches every machine.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DetectRule {
+    /// Matches the `os-release` `ID`
+    #[serde(default)]
+    pub(crate) os_id: Option<String>,
+
+    /// Matches the `os-release` `ID` or any of its `ID_LIKE` entries
+    #[serde(default)]
+    pub(crate) os_like: Option<String>,
+
+    /// Matches the `os-release` `VERSION_ID`
+    #[serde(default)]
+    pub(crate) os_version: Option<String>,
+
+    /// Matches the kernel name from `uname -s`
+    #[serde(default)]
+    pub(crate) kernel: Option<String>,
+
+    /// Matches the architecture; `arm64` and `amd64` are accepted for `aarch64`
+    /// and `x86_64`
+    #[serde(default)]
+    pub(crate) arch: Option<String>,
+}
+
+impl OsFacts {
+    /// Gather facts about the machine selfie is running on
+    ///
+    /// Missing facts are left empty rather than treated as errors: a machine
+    /// without an `os-release` file (like macOS) can still be detected by its
+    /// kernel name.
+    pub async fn gather<F, CR>(fs: &F, command_runner: &CR) -> Self
+    where
+        F: FileSystem,
+        CR: CommandRunner,
+    {
+        let os_release = OS_RELEASE_PATHS
+            .iter()
+            .find_map(|path| fs.read_file(Path::new(path)).ok());
+
+        let kernel = uname(command_runner, "-s")
+            .await
+            .unwrap_or_else(|| std::env::consts::OS.to_string());
+
+        Self::new(
+            os_release.as_deref(),
+            &kernel,
+            &machine_arch(command_runner).await,
+        )
+    }
+
+    /// Build facts from the contents of an `os-release` file, the kernel name and
+    /// the architecture
+    #[must_use]
+    pub fn new(os_release: Option<&str>, kernel: &str, arch: &str) -> Self {
+        let mut facts = Self {
+            kernel: kernel.trim().to_lowercase(),
+            arch: normalize_arch(arch),
+            ..Self::default()
+        };
+
+        for line in os_release.unwrap_or_default().lines() {
+            let Some((key, value)) = line.trim().split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+
+            match key {
+                "ID" => facts.os_id = Some(value.to_string()),
+                "ID_LIKE" => {
+                    facts.os_id_like = value.split_whitespace().map(str::to_string).collect();
+                }
+                "VERSION_ID" => facts.os_version = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        facts
+    }
+
+    #[must_use]
+    pub fn os_id(&self) -> Option<&str> {
+        self.os_id.as_deref()
+    }
+
+    #[must_use]
+    pub fn os_version(&self) -> Option<&str> {
+        self.os_version.as_deref()
+    }
+
+    #[must_use]
+    pub fn kernel(&self) -> &str {
+        &self.kernel
+    }
+
+    #[must_use]
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+
+    /// The environment these facts point to
+    ///
+    /// The matching rule with the most conditions wins, and ties go to the rule
+    /// whose name sorts first. Without a matching rule, falls back to the
+    /// `os-release` `ID`, or `macos` on Darwin.
+    #[must_use]
+    pub fn environment(&self, rules: &BTreeMap<String, DetectRule>) -> Option<String> {
+        let mut best: Option<(&String, usize)> = None;
+
+        for (name, rule) in rules {
+            if !rule.matches(self) {
+                continue;
+            }
+            let conditions = rule.condition_count();
+            if best.is_none_or(|(_, most)| conditions > most) {
+                best = Some((name, conditions));
+            }
+        }
+
+        if let Some((name, _)) = best {
+            return Some(name.clone());
+        }
+
+        if self.kernel == "darwin" {
+            Some("macos".to_string())
+        } else {
+            self.os_id.clone()
+        }
+    }
+}
+
+impl DetectRule {
+    fn matches(&self, facts: &OsFacts) -> bool {
+        fn is(expected: Option<&String>, actual: Option<&str>) -> bool {
+            expected.is_none_or(|expected| {
+                actual.is_some_and(|actual| expected.eq_ignore_ascii_case(actual))
+            })
+        }
+
+        let like = self.os_like.as_ref().is_none_or(|like| {
+            facts
+                .os_id
+                .iter()
+                .chain(&facts.os_id_like)
+                .any(|id| like.eq_ignore_ascii_case(id))
+        });
+
+        is(self.os_id.as_ref(), facts.os_id.as_deref())
+            && like
+            && is(self.os_version.as_ref(), facts.os_version.as_deref())
+            && is(self.kernel.as_ref(), Some(&facts.kernel))
+            && self
+                .arch
+                .as_ref()
+                .is_none_or(|arch| normalize_arch(arch) == facts.arch)
+    }
+
+    pub(crate) fn condition_count(&self) -> usize {
+        [
+            &self.os_id,
+            &self.os_like,
+            &self.os_version,
+            &self.kernel,
+            &self.arch,
+        ]
+        .into_iter()
+        .filter(|condition| condition.is_some())
+        .count()
+    }
+}
+
+/// The name selfie uses for a machine architecture
+///
+/// `uname -m` and Rust don't always agree (`arm64` and `aarch64`, `amd64` and
+/// `x86_64`), so every architecture is compared by the name
+/// [`std::env::consts::ARCH`] uses.
+#[must_use]
+pub fn normalize_arch(arch: &str) -> String {
+    let arch = arch.trim().to_ascii_lowercase();
+    match arch.as_str() {
+        "arm64" => "aarch64".to_string(),
+        "amd64" => "x86_64".to_string(),
+        _ => arch,
+    }
+}
+
+/// The machine architecture from `uname -m`, normalized with [`normalize_arch`]
+///
+/// Falls back to the architecture selfie was built for when `uname -m` can't
+/// be run.
+pub async fn machine_arch<CR: CommandRunner>(command_runner: &CR) -> String {
+    let arch = uname(command_runner, "-m")
+        .await
+        .unwrap_or_else(|| std::env::consts::ARCH.to_string());
+    normalize_arch(&arch)
+}
+
+/// The output of `uname` with the given flag, or `None` if it can't be run
+async fn uname<CR: CommandRunner>(command_runner: &CR, flag: &str) -> Option<String> {
+    let output = command_runner
+        .execute(&format!("uname {flag}"))
+        .await
+        .ok()?;
+    output
+        .is_success()
+        .then(|| output.stdout_str().trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::process::ExitStatusExt, process::ExitStatus, time::Duration};
+
+    use super::*;
+    use crate::{
+        commands::runner::{CommandError, CommandOutput, MockCommandRunner},
+        fs::filesystem::MockFileSystem,
+    };
+
+    const UBUNTU_OS_RELEASE: &str = r#"
+NAME="Ubuntu"
+VERSION_ID="24.04"
+ID=ubuntu
+ID_LIKE=debian
+PRETTY_NAME="Ubuntu 24.04 LTS"
+"#;
+
+    fn rules(yaml: &str) -> BTreeMap<String, DetectRule> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_parse_os_release() {
+        let facts = OsFacts::new(Some(UBUNTU_OS_RELEASE), "Linux\n", "x86_64\n");
+
+        assert_eq!(facts.os_id(), Some("ubuntu"));
+        assert_eq!(facts.os_id_like, ["debian"]);
+        assert_eq!(facts.os_version(), Some("24.04"));
+        assert_eq!(facts.kernel(), "linux");
+        assert_eq!(facts.arch(), "x86_64");
+    }
+
+    #[tokio::test]
+    async fn test_gather_through_ports() {
+        let mut fs = MockFileSystem::default();
+        fs.mock_read_file("/etc/os-release", UBUNTU_OS_RELEASE);
+
+        let mut runner = MockCommandRunner::new();
+        for (command, stdout) in [("uname -s", "Linux\n"), ("uname -m", "arm64\n")] {
+            runner
+                .expect_execute()
+                .with(mockall::predicate::eq(command))
+                .times(1)
+                .returning(move |_| {
+                    Ok(CommandOutput {
+                        output: std::process::Output {
+                            status: ExitStatus::from_raw(0),
+                            stdout: stdout.as_bytes().to_vec(),
+                            stderr: Vec::new(),
+                        },
+                        duration: Duration::ZERO,
+                    })
+                });
+        }
+
+        let facts = OsFacts::gather(&fs, &runner).await;
+        assert_eq!(facts.os_id(), Some("ubuntu"));
+        assert_eq!(facts.kernel(), "linux");
+        assert_eq!(facts.arch(), "aarch64");
+    }
+
+    #[tokio::test]
+    async fn test_machine_arch_falls_back_to_build_arch() {
+        let mut runner = MockCommandRunner::new();
+        runner
+            .expect_execute()
+            .with(mockall::predicate::eq("uname -m"))
+            .times(1)
+            .returning(|command| {
+                Err(CommandError::Timeout {
+                    command: command.to_string(),
+                    timeout: Duration::from_secs(1),
+                    working_directory: std::path::PathBuf::from("/"),
+                })
+            });
+
+        assert_eq!(
+            machine_arch(&runner).await,
+            normalize_arch(std::env::consts::ARCH)
+        );
+    }
+
+    #[test]
+    fn test_most_specific_rule_wins() {
+        let facts = OsFacts::new(Some(UBUNTU_OS_RELEASE), "Linux", "aarch64");
+        let rules = rules(
+            r"
+debian-family: { os_like: debian }
+ubuntu: { os_id: ubuntu }
+ubuntu-arm: { os_id: ubuntu, arch: aarch64 }
+arch-linux: { os_id: arch }
+",
+        );
+
+        assert_eq!(facts.environment(&rules).as_deref(), Some("ubuntu-arm"));
+    }
+
+    #[test]
+    fn test_arch_is_normalized() {
+        let facts = OsFacts::new(None, "Darwin", "arm64");
+        assert_eq!(facts.arch(), "aarch64");
+
+        let rules = rules("mac-arm: { kernel: darwin, arch: ARM64 }");
+        assert_eq!(facts.environment(&rules).as_deref(), Some("mac-arm"));
+        assert_eq!(normalize_arch("amd64"), "x86_64");
+    }
+
+    #[test]
+    fn test_os_like_matches_id_like() {
+        let facts = OsFacts::new(Some(UBUNTU_OS_RELEASE), "Linux", "x86_64");
+        let rules = rules("debian: { os_like: Debian }");
+
+        assert_eq!(facts.environment(&rules).as_deref(), Some("debian"));
+    }
+
+    #[test]
+    fn test_fallback_without_matching_rule() {
+        let rules = rules("arch-linux: { os_id: arch }");
+
+        let ubuntu = OsFacts::new(Some(UBUNTU_OS_RELEASE), "Linux", "x86_64");
+        assert_eq!(ubuntu.environment(&rules).as_deref(), Some("ubuntu"));
+
+        let macos = OsFacts::new(None, "Darwin", "arm64");
+        assert_eq!(macos.environment(&rules).as_deref(), Some("macos"));
+
+        let unknown = OsFacts::new(None, "FreeBSD", "amd64");
+        assert_eq!(unknown.environment(&rules), None);
+    }
+
+    #[test]
+    fn test_unknown_rule_field_is_rejected() {
+        let result: Result<BTreeMap<String, DetectRule>, _> =
+            serde_yaml::from_str("ubuntu: { distro: ubuntu }");
+        assert!(result.is_err());
+    }
+}
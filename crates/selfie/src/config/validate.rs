@@ -3,13 +3,19 @@
 //! This module provides validation capabilities for application configuration,
 //! ensuring that configuration values are valid and complete before use.
 
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use thiserror::Error;
 
-use crate::validation::{ValidationErrorCategory, ValidationIssue, ValidationIssues};
+use crate::{
+    package::template::validate_var_names,
+    validation::{ValidationErrorCategory, ValidationIssue, ValidationIssues},
+};
 
-use super::AppConfig;
+use super::{AppConfig, detect::DetectRule};
 
 /// Result of configuration validation
 ///
@@ -54,13 +60,18 @@ impl AppConfig {
     pub fn validate(&self) -> ValidationResult {
         let mut issues = Vec::new();
 
-        if let Some(issue) = validate_environment(&self.environment) {
+        if let Some(issue) = validate_environment(self) {
             issues.push(issue);
         }
 
+        issues.extend(validate_detect_rules(&self.detect));
+
         let path_issues = validate_package_directory(&self.package_directory);
         issues.extend_from_slice(&path_issues);
 
+        issues.extend(validate_sets(&self.sets));
+        issues.extend(validate_var_names(self.vars.keys()));
+
         ValidationResult {
             config_file_path: Some(self.package_directory().clone()),
             issues: issues.into(),
@@ -86,18 +97,47 @@ pub enum ConfigValidationError {
 /// Validate the environment field
 ///
 /// Ensures the environment name is not empty, as it's required for
-/// determining which package installation commands to use.
-fn validate_environment(environment: &str) -> Option<ValidationIssue> {
-    environment.is_empty().then(|| {
-        ValidationIssue::error(
+/// determining which package installation commands to use, and that
+/// `environment: auto` was resolved to an environment for this machine.
+fn validate_environment(config: &AppConfig) -> Option<ValidationIssue> {
+    if config.environment.is_empty() {
+        return Some(ValidationIssue::error(
             ValidationErrorCategory::RequiredField,
             "environment",
             "The `environment` field exists, but has no value",
             Some("Set a value for `environment`. Ex. `environment: macos`"),
+        ));
+    }
+
+    config.undetected_environment().map(|reason| {
+        ValidationIssue::error(
+            ValidationErrorCategory::Environment,
+            "environment",
+            &reason,
+            Some("Add a `detect` rule that matches this machine, or name the environment"),
         )
     })
 }
 
+/// Validate the environment detection rules
+///
+/// A rule without conditions matches every machine, which is only useful as a
+/// catch-all, so it warns.
+fn validate_detect_rules(rules: &BTreeMap<String, DetectRule>) -> Vec<ValidationIssue> {
+    rules
+        .iter()
+        .filter(|(_, rule)| rule.condition_count() == 0)
+        .map(|(name, _)| {
+            ValidationIssue::warning(
+                ValidationErrorCategory::InvalidValue,
+                &format!("detect.{name}"),
+                &format!("The `{name}` detect rule has no conditions, so it matches every machine"),
+                Some("Add `os_id`, `os_like`, `os_version`, `kernel` or `arch` to the rule"),
+            )
+        })
+        .collect()
+}
+
 /// Validate the package directory path
 ///
 /// Ensures the package directory path is not empty and can be expanded
@@ -131,3 +171,96 @@ fn validate_package_directory(package_directory: &Path) -> Vec<ValidationIssue>
 
     issues
 }
+
+/// Validate the package sets
+///
+/// An empty set is allowed but almost certainly a mistake, so it only warns. A
+/// blank member name can never match a package file.
+fn validate_sets(sets: &BTreeMap<String, Vec<String>>) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    for (name, members) in sets {
+        if members.is_empty() {
+            issues.push(ValidationIssue::warning(
+                ValidationErrorCategory::InvalidValue,
+                &format!("sets.{name}"),
+                &format!("The `{name}` set has no packages"),
+                Some("Add package names to the set, or remove it"),
+            ));
+        }
+
+        for (i, member) in members.iter().enumerate() {
+            if member.trim().is_empty() {
+                issues.push(ValidationIssue::error(
+                    ValidationErrorCategory::RequiredField,
+                    &format!("sets.{name}[{i}]"),
+                    "The set member exists, but has no value",
+                    Some("Use the name of a package file, without its extension"),
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{AppConfig, AppConfigBuilder, detect::OsFacts};
+
+    #[test]
+    fn test_validate_undetected_environment() {
+        let mut config: AppConfig = serde_yaml::from_str(
+            r"
+            environment: auto
+            package_directory: /test/path
+            ",
+        )
+        .unwrap();
+        config.resolve_environment(OsFacts::new(None, "FreeBSD", "amd64"));
+
+        let result = config.validate();
+        let errors = result.issues().errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field(), "environment");
+        assert!(errors[0].message().contains("freebsd"));
+    }
+
+    #[test]
+    fn test_validate_detect_rule_without_conditions() {
+        let config: AppConfig = serde_yaml::from_str(
+            r"
+            environment: linux
+            package_directory: /test/path
+            detect:
+              linux: {}
+            ",
+        )
+        .unwrap();
+
+        let result = config.validate();
+        let warnings = result.issues().warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].field(), "detect.linux");
+    }
+
+    #[test]
+    fn test_validate_sets() {
+        let config = AppConfigBuilder::default()
+            .environment("test-env")
+            .package_directory("/test/path")
+            .set("dev", vec!["git", " "])
+            .set("empty", Vec::<String>::new())
+            .build();
+
+        let result = config.validate();
+
+        let errors = result.issues().errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field(), "sets.dev[1]");
+
+        let warnings = result.issues().warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].field(), "sets.empty");
+    }
+}
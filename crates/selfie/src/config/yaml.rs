@@ -35,6 +35,7 @@ impl<F: FileSystem> ConfigLoader for YamlLoader<'_, F> {
     ///
     /// Searches for `config.yaml` or `config.yml` in the user's configuration directory
     /// and loads the first one found. Performs path expansion for the package directory.
+    /// An `auto` environment is left for [`AppConfig::detect_environment`] to resolve.
     ///
     /// # Errors
     ///
@@ -89,6 +90,13 @@ impl<F: FileSystem> ConfigLoader for YamlLoader<'_, F> {
         if let Ok(expanded) = self.fs.expand_path(app_config.package_directory()) {
             app_config.package_directory = expanded;
         }
+        if let Some(state_directory) = app_config.state_directory.as_mut()
+            && let Ok(expanded) = self.fs.expand_path(state_directory)
+        {
+            *state_directory = expanded;
+        }
+
+        app_config.home_directory = self.fs.home_dir().ok();
 
         Ok(app_config)
     }
@@ -154,6 +162,7 @@ mod tests {
         fs.mock_path_exists(&config_path, true);
         fs.mock_path_exists(&config_dir.join("config.yml"), false);
         fs.mock_read_file(config_path, config_yaml);
+        fs.mock_home_dir(home_dir);
 
         (fs, home_dir.into())
     }
@@ -270,10 +279,13 @@ mod tests {
             command_timeout: 120
             stop_on_error: false
             max_parallel_installations: 8
+            retries: 2
+            retry_delay: 5
         "#;
 
             fs.mock_config_file(config_dir, config_yaml);
             fs.mock_expand_path("/test/packages", "/test/packages");
+            fs.mock_home_dir("/home/test");
 
             let loader = YamlLoader::new(&fs);
             let config = loader.load_config().unwrap();
@@ -286,6 +298,8 @@ mod tests {
             assert_eq!(config.command_timeout, 120.try_into().unwrap());
             assert!(!config.stop_on_error);
             assert_eq!(config.max_parallel_installations, 8.try_into().unwrap());
+            assert_eq!(config.retries, 2);
+            assert_eq!(config.retry_delay, 5);
         }
 
         #[test]
@@ -379,11 +393,64 @@ mod tests {
 
             fs.mock_config_file(config_dir, tilde_yaml);
             fs.mock_expand_path(Path::new("~/packages"), &expanded_path);
+            fs.mock_home_dir(home_dir);
 
             let loader = YamlLoader::new(&fs);
             let config = loader.load_config().unwrap();
 
             assert_eq!(config.package_directory, expanded_path);
+            assert_eq!(config.home_directory(), Some(home_dir));
+        }
+
+        #[test]
+        fn test_load_config_with_state_directory() {
+            let mut fs = MockFileSystem::default();
+            let config_dir = Path::new("/home/test/.config/selfie");
+
+            let state_yaml = r#"
+        environment: "test-env"
+        package_directory: "/test/packages"
+        state_directory: "~/.selfie-state"
+    "#;
+
+            let expanded_path = Path::new("/home/test/.selfie-state");
+
+            fs.mock_config_file(config_dir, state_yaml);
+            fs.mock_expand_path(Path::new("/test/packages"), Path::new("/test/packages"));
+            fs.mock_expand_path(Path::new("~/.selfie-state"), expanded_path);
+            fs.mock_home_dir("/home/test");
+
+            let loader = YamlLoader::new(&fs);
+            let config = loader.load_config().unwrap();
+
+            assert_eq!(config.state_directory(), Some(&expanded_path.to_path_buf()));
+        }
+
+        #[test]
+        fn test_load_config_with_sets() {
+            let mut fs = MockFileSystem::default();
+            let config_dir = Path::new("/home/test/.config/selfie");
+
+            let sets_yaml = r#"
+        environment: "test-env"
+        package_directory: "/test/packages"
+        sets:
+          dev: [git, ripgrep, neovim]
+          ci:
+            - git
+            - curl
+    "#;
+
+            fs.mock_config_file(config_dir, sets_yaml);
+            fs.mock_expand_path(Path::new("/test/packages"), Path::new("/test/packages"));
+            fs.mock_home_dir("/home/test");
+
+            let loader = YamlLoader::new(&fs);
+            let config = loader.load_config().unwrap();
+
+            assert_eq!(config.sets().len(), 2);
+            assert_eq!(config.sets()["dev"], ["git", "ripgrep", "neovim"]);
+            assert_eq!(config.sets()["ci"], ["git", "curl"]);
         }
 
         #[test]
@@ -404,6 +471,7 @@ mod tests {
             fs.mock_path_exists(&config_dir.join("config.yml"), false);
             fs.mock_read_file(&config_path, minimal_yaml);
             fs.mock_expand_path(Path::new("/test/packages"), Path::new("/test/packages"));
+            fs.mock_home_dir("/home/test");
 
             let loader = YamlLoader::new(&fs);
             let config = loader.load_config().unwrap();
@@ -414,6 +482,10 @@ mod tests {
             assert!(!config.verbose); // Default
             assert!(config.use_colors); // Default
             assert!(config.stop_on_error); // Default
+            assert!(config.state_directory.is_none()); // Default
+            assert_eq!(config.retries, 0); // Default
+            assert_eq!(config.retry_delay, 1); // Default
+            assert!(config.sets.is_empty()); // Default
 
             // Check command_timeout has default value (60)
             assert_eq!(config.command_timeout.get(), 60);
@@ -158,6 +158,25 @@ pub trait FileSystem: Send + Sync {
     /// - The user's home directory cannot be determined
     /// - The configuration directory cannot be accessed
     fn config_dir(&self) -> Result<PathBuf, FileSystemError>;
+
+    /// Get the user's data directory
+    ///
+    /// Returns the standard directory for application data for the current user.
+    /// This follows platform conventions (e.g., ~/.local/share on Unix-like systems).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FileSystemError`] if:
+    /// - The user's home directory cannot be determined
+    fn data_dir(&self) -> Result<PathBuf, FileSystemError>;
+
+    /// Get the user's home directory
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FileSystemError`] if the user's home directory cannot be
+    /// determined
+    fn home_dir(&self) -> Result<PathBuf, FileSystemError>;
 }
 
 /// Errors that can occur during file system operations
@@ -255,6 +274,19 @@ impl MockFileSystem {
         self.expect_config_dir().return_once(|| Ok(p));
     }
 
+    /// Set up a mock for getting the user's home directory
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Home directory path to return
+    pub(crate) fn mock_home_dir<P>(&mut self, path: P)
+    where
+        PathBuf: From<P>,
+    {
+        let p = PathBuf::from(path);
+        self.expect_home_dir().return_once(|| Ok(p));
+    }
+
     /// Set up a complete mock configuration file scenario
     ///
     /// Configures the mock to simulate finding and reading a configuration file
@@ -285,6 +317,7 @@ impl MockFileSystem {
     /// # Arguments
     ///
     /// * `path` - Path where the write should succeed
+    #[allow(dead_code)]
     pub(crate) fn mock_write_file<P>(&mut self, path: P)
     where
         PathBuf: From<P>,
@@ -306,6 +339,7 @@ impl MockFileSystem {
     /// # Arguments
     ///
     /// * `path` - Path where the file removal should succeed
+    #[allow(dead_code)]
     pub(crate) fn mock_remove_file<P>(&mut self, path: P)
     where
         PathBuf: From<P>,
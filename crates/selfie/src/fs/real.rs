@@ -68,14 +68,33 @@ impl FileSystem for RealFileSystem {
             return Ok(PathBuf::from(dir));
         }
 
-        choose_app_strategy(AppStrategyArgs {
-            top_level_domain: "net".to_string(),
-            author: "turboladen".to_string(),
-            app_name: "selfie".to_string(),
-        })
-        .map(|xdg| xdg.config_dir())
-        .map_err(|_| FileSystemError::HomeDirNotFound)
+        app_strategy()
+            .map(|xdg| xdg.config_dir())
+            .map_err(|_| FileSystemError::HomeDirNotFound)
     }
+
+    fn data_dir(&self) -> Result<PathBuf, FileSystemError> {
+        // Check for environment variable override first
+        if let Ok(dir) = std::env::var("SELFIE_DATA_DIR") {
+            return Ok(PathBuf::from(dir));
+        }
+
+        app_strategy()
+            .map(|xdg| xdg.data_dir())
+            .map_err(|_| FileSystemError::HomeDirNotFound)
+    }
+
+    fn home_dir(&self) -> Result<PathBuf, FileSystemError> {
+        etcetera::home_dir().map_err(|_| FileSystemError::HomeDirNotFound)
+    }
+}
+
+fn app_strategy() -> Result<impl AppStrategy, etcetera::HomeDirError> {
+    choose_app_strategy(AppStrategyArgs {
+        top_level_domain: "net".to_string(),
+        author: "turboladen".to_string(),
+        app_name: "selfie".to_string(),
+    })
 }
 
 #[cfg(test)]
@@ -1,17 +1,36 @@
 mod builder;
+pub mod condition;
 pub mod event;
+pub mod graph;
+pub mod inherit;
 pub mod port;
 pub mod repository;
+pub mod script;
 pub mod service;
+pub mod set;
+pub mod state;
+pub mod template;
 pub mod validate;
+pub mod version;
 
 pub use self::builder::{EnvironmentConfigBuilder, PackageBuilder};
 
 // Core package entity and related types
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    time::Duration,
+};
 
 use serde::{Deserialize, Serialize};
 
+use crate::commands::CommandRequest;
+
+use self::{
+    condition::{Condition, ConditionalCommand},
+    script::InstallScript,
+};
+
 /// Package data for editing operations
 ///
 /// Contains a package and its file metadata for editing workflows.
@@ -85,10 +104,26 @@ pub struct Package {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub(crate) description: Option<String>,
 
+    /// Default `check` command for environments that don't set their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) check: Option<String>,
+
+    /// Default dependencies for environments that don't list their own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) dependencies: Vec<String>,
+
+    /// Variables for the `{{placeholders}}` in this package's commands
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) vars: BTreeMap<String, String>,
+
     /// Map of environment configurations
     #[serde(default)]
     pub(crate) environments: HashMap<String, EnvironmentConfig>,
 
+    /// Environments whose `use:` chain couldn't be resolved (not serialized/deserialized)
+    #[serde(skip)]
+    pub(crate) inheritance_errors: BTreeMap<String, inherit::InheritError>,
+
     /// Path to the package file (not serialized/deserialized)
     #[serde(skip)]
     pub(crate) path: PathBuf,
@@ -97,16 +132,110 @@ pub struct Package {
 /// Configuration for a specific environment
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EnvironmentConfig {
-    /// Command to install the package
-    pub(crate) install: String,
+    /// Another environment of the package whose config this one reuses, for
+    /// everything it doesn't set itself
+    #[serde(default, rename = "use", skip_serializing_if = "Option::is_none")]
+    pub(crate) uses: Option<String>,
+
+    /// Conditions the machine must meet for this environment's config to apply;
+    /// without them, the package is skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) when: Option<Condition>,
+
+    /// Command, or ordered list of commands, to install the package
+    #[serde(default)]
+    pub(crate) install: InstallScript,
 
     /// Optional command to check if the package is already installed
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub(crate) check: Option<String>,
 
+    /// Optional command whose output holds the installed version of the software
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) version_command: Option<String>,
+
+    /// Optional regex that picks the version out of the `version_command` output;
+    /// its first capture group is the version, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) version_regex: Option<String>,
+
+    /// Optional constraint the installed version must meet, e.g. `>=14.0`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) required_version: Option<String>,
+
+    /// Commands run in order before the install command, e.g. to add a tap
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) pre_install: Vec<ConditionalCommand>,
+
+    /// Commands run in order after the install command succeeds, e.g. to add the
+    /// package's binaries to `PATH`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) post_install: Vec<ConditionalCommand>,
+
     /// Dependencies that must be installed before this package
-    #[serde(default)]
-    pub(crate) dependencies: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) dependencies: Option<Vec<String>>,
+
+    /// Optional command to remove the package from the machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) uninstall: Option<String>,
+
+    /// Optional command to upgrade an installed package to its latest version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) update: Option<String>,
+
+    /// Commands that undo a partial install, run in order when the install
+    /// command fails and rollback is requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) rollback: Vec<String>,
+
+    /// Overrides the global `retries` setting for this environment's commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) retries: Option<u32>,
+
+    /// Overrides the global `retry_delay` setting (in seconds) for this
+    /// environment's commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) retry_delay: Option<u64>,
+
+    /// Overrides the global `command_timeout` setting (in seconds) for this
+    /// environment's commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) timeout: Option<u64>,
+
+    /// Overrides `timeout` (in seconds) for this environment's install commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) install_timeout: Option<u64>,
+
+    /// Overrides `timeout` (in seconds) for this environment's `check` and
+    /// `version_command` commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) check_timeout: Option<u64>,
+
+    /// Directory this environment's commands run in; `~` is the home directory,
+    /// and a relative path is taken from the package directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cwd: Option<String>,
+
+    /// Environment variables set for this environment's commands
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) env: BTreeMap<String, String>,
+
+    /// The `SELFIE_*` variables every command gets, filled in when the block's
+    /// commands are rendered (not serialized/deserialized)
+    #[serde(skip)]
+    pub(crate) context: BTreeMap<String, String>,
+}
+
+/// The kind of command a timeout is picked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandKind {
+    /// An `install` command
+    Install,
+    /// A `check` or `version_command` command
+    Check,
+    /// Any other command, e.g. a hook, `uninstall` or `update`
+    Other,
 }
 
 impl EnvironmentConfig {
@@ -114,14 +243,115 @@ impl EnvironmentConfig {
     #[must_use]
     pub fn new(install: String, check: Option<String>, dependencies: Vec<String>) -> Self {
         Self {
-            install,
+            uses: None,
+            when: None,
+            install: install.into(),
             check,
-            dependencies,
+            version_command: None,
+            version_regex: None,
+            required_version: None,
+            pre_install: Vec::new(),
+            post_install: Vec::new(),
+            dependencies: Some(dependencies),
+            uninstall: None,
+            update: None,
+            rollback: Vec::new(),
+            retries: None,
+            retry_delay: None,
+            timeout: None,
+            install_timeout: None,
+            check_timeout: None,
+            cwd: None,
+            env: BTreeMap::new(),
+            context: BTreeMap::new(),
         }
     }
 
+    /// Set the command that prints the installed version, with an optional regex
+    /// that picks the version out of its output
+    #[must_use]
+    pub fn with_version_command(
+        mut self,
+        version_command: Option<String>,
+        version_regex: Option<String>,
+    ) -> Self {
+        self.version_command = version_command;
+        self.version_regex = version_regex;
+        self
+    }
+
+    /// Set the constraint the installed version must meet
+    #[must_use]
+    pub fn with_required_version(mut self, required_version: Option<String>) -> Self {
+        self.required_version = required_version;
+        self
+    }
+
+    /// Set the conditions the machine must meet for this config to apply
+    #[must_use]
+    pub fn with_when(mut self, when: Option<Condition>) -> Self {
+        self.when = when;
+        self
+    }
+
+    /// Set the commands that run before the install command
+    #[must_use]
+    pub fn with_pre_install(mut self, pre_install: Vec<String>) -> Self {
+        self.pre_install = pre_install.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the commands that run after the install command succeeds
+    #[must_use]
+    pub fn with_post_install(mut self, post_install: Vec<String>) -> Self {
+        self.post_install = post_install.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the command that removes the package from the machine
+    #[must_use]
+    pub fn with_uninstall(mut self, uninstall: Option<String>) -> Self {
+        self.uninstall = uninstall;
+        self
+    }
+
+    /// Set the command that upgrades the package once it's installed
     #[must_use]
-    pub fn install(&self) -> &str {
+    pub fn with_update(mut self, update: Option<String>) -> Self {
+        self.update = update;
+        self
+    }
+
+    /// Set the commands that undo a failed install
+    #[must_use]
+    pub fn with_rollback(mut self, rollback: Vec<String>) -> Self {
+        self.rollback = rollback;
+        self
+    }
+
+    /// Set how many times this environment's commands are retried
+    #[must_use]
+    pub fn with_retries(mut self, retries: Option<u32>) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set how long to wait, in seconds, before retrying a command
+    #[must_use]
+    pub fn with_retry_delay(mut self, retry_delay: Option<u64>) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Set how long, in seconds, this environment's commands may run
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Option<u64>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    #[must_use]
+    pub fn install(&self) -> &InstallScript {
         &self.install
     }
 
@@ -130,9 +360,116 @@ impl EnvironmentConfig {
         self.check.as_deref()
     }
 
+    #[must_use]
+    pub fn version_command(&self) -> Option<&str> {
+        self.version_command.as_deref()
+    }
+
+    #[must_use]
+    pub fn version_regex(&self) -> Option<&str> {
+        self.version_regex.as_deref()
+    }
+
+    #[must_use]
+    pub fn required_version(&self) -> Option<&str> {
+        self.required_version.as_deref()
+    }
+
+    /// The conditions the machine must meet for this config to apply, if any
+    #[must_use]
+    pub fn when(&self) -> Option<&Condition> {
+        self.when.as_ref()
+    }
+
+    #[must_use]
+    pub fn pre_install(&self) -> &[ConditionalCommand] {
+        &self.pre_install
+    }
+
+    #[must_use]
+    pub fn post_install(&self) -> &[ConditionalCommand] {
+        &self.post_install
+    }
+
     #[must_use]
     pub fn dependencies(&self) -> &[String] {
-        &self.dependencies
+        self.dependencies.as_deref().unwrap_or_default()
+    }
+
+    /// The environment this one reuses, if any
+    #[must_use]
+    pub fn uses(&self) -> Option<&str> {
+        self.uses.as_deref()
+    }
+
+    #[must_use]
+    pub fn uninstall(&self) -> Option<&str> {
+        self.uninstall.as_deref()
+    }
+
+    #[must_use]
+    pub fn update(&self) -> Option<&str> {
+        self.update.as_deref()
+    }
+
+    #[must_use]
+    pub fn rollback(&self) -> &[String] {
+        &self.rollback
+    }
+
+    #[must_use]
+    pub fn retries(&self) -> Option<u32> {
+        self.retries
+    }
+
+    #[must_use]
+    pub fn retry_delay(&self) -> Option<Duration> {
+        self.retry_delay.map(Duration::from_secs)
+    }
+
+    /// The directory this environment's commands run in, if not the current one
+    #[must_use]
+    pub fn cwd(&self) -> Option<&str> {
+        self.cwd.as_deref()
+    }
+
+    #[must_use]
+    pub fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    /// A request to run one of this environment's commands for at most `timeout`
+    ///
+    /// The command runs in `cwd`, if set, with the `env` variables and the
+    /// `SELFIE_*` context variables added to its environment. The context
+    /// variables win over `env`.
+    #[must_use]
+    pub fn command_request(&self, command: &str, timeout: Duration) -> CommandRequest {
+        let request = CommandRequest::new(command, timeout)
+            .envs(&self.env)
+            .envs(&self.context);
+
+        match &self.cwd {
+            Some(cwd) => request.working_directory(cwd),
+            None => request,
+        }
+    }
+
+    /// How long a `kind` command may run, with the name of the setting that says so
+    ///
+    /// `install_timeout` and `check_timeout` win over `timeout`. Returns `None`
+    /// when the environment doesn't override the global `command_timeout`.
+    #[must_use]
+    pub fn timeout(&self, kind: CommandKind) -> Option<(Duration, &'static str)> {
+        let per_kind = match kind {
+            CommandKind::Install => self.install_timeout.map(|t| (t, "install_timeout")),
+            CommandKind::Check => self.check_timeout.map(|t| (t, "check_timeout")),
+            CommandKind::Other => None,
+        };
+
+        per_kind
+            .or(self.timeout.map(|t| (t, "timeout")))
+            .map(|(seconds, setting)| (Duration::from_secs(seconds), setting))
     }
 }
 
@@ -152,7 +489,11 @@ impl Package {
             version,
             homepage,
             description,
+            check: None,
+            dependencies: Vec::new(),
+            vars: BTreeMap::new(),
             environments,
+            inheritance_errors: BTreeMap::new(),
             path,
         }
     }
@@ -171,9 +512,27 @@ impl Package {
         environments.insert(
             "default".to_string(),
             EnvironmentConfig {
-                install: format!("# TODO: Add install command for {name}"),
+                uses: None,
+                when: None,
+                install: format!("# TODO: Add install command for {name}").into(),
                 check: Some(format!("# TODO: Add check command for {name}")),
-                dependencies: Vec::new(),
+                version_command: None,
+                version_regex: None,
+                required_version: None,
+                pre_install: Vec::new(),
+                post_install: Vec::new(),
+                dependencies: None,
+                uninstall: None,
+                update: None,
+                rollback: Vec::new(),
+                retries: None,
+                retry_delay: None,
+                timeout: None,
+                install_timeout: None,
+                check_timeout: None,
+                cwd: None,
+                env: BTreeMap::new(),
+                context: BTreeMap::new(),
             },
         );
 
@@ -182,7 +541,11 @@ impl Package {
             version: "0.1.0".to_string(),
             homepage: None,
             description: None,
+            check: None,
+            dependencies: Vec::new(),
+            vars: BTreeMap::new(),
             environments,
+            inheritance_errors: BTreeMap::new(),
             path: PathBuf::new(), // Will be set by GetPackage::new
         }
     }
@@ -207,17 +570,82 @@ impl Package {
         self.description.as_deref()
     }
 
+    #[must_use]
+    pub fn vars(&self) -> &BTreeMap<String, String> {
+        &self.vars
+    }
+
     #[must_use]
     pub fn environments(&self) -> &HashMap<String, EnvironmentConfig> {
         &self.environments
     }
 
+    /// Pick the environment block to use from an ordered chain of environments
+    ///
+    /// Each environment in `chain` is tried in turn, usually the current
+    /// environment followed by its fallbacks (see
+    /// [`AppConfig::environment_chain`](crate::config::AppConfig::environment_chain)).
+    /// A block named exactly like the environment wins; otherwise a wildcard block
+    /// such as `ubuntu-*` matches, the longest pattern first. A `*` block catches
+    /// everything, but only once nothing in the chain matched. Returns the name of
+    /// the selected block with its config.
+    #[must_use]
+    pub fn select_environment(&self, chain: &[&str]) -> Option<(&str, &EnvironmentConfig)> {
+        chain
+            .iter()
+            .find_map(|environment| {
+                self.environments.get_key_value(*environment).or_else(|| {
+                    self.environments
+                        .iter()
+                        .filter(|(name, _)| {
+                            name.contains('*')
+                                && name.as_str() != "*"
+                                && wildcard_matches(name, environment)
+                        })
+                        .max_by(|a, b| a.0.len().cmp(&b.0.len()).then_with(|| b.0.cmp(a.0)))
+                })
+            })
+            .or_else(|| self.environments.get_key_value("*"))
+            .map(|(name, env_config)| (name.as_str(), env_config))
+    }
+
+    /// Whether the package has a block for any environment in `chain`
+    #[must_use]
+    pub fn supports_environment(&self, chain: &[&str]) -> bool {
+        self.select_environment(chain).is_some()
+    }
+
     #[must_use]
     pub fn path(&self) -> &PathBuf {
         &self.path
     }
 }
 
+/// Whether `name` matches `pattern`, where each `*` in the pattern stands for any
+/// run of characters
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` at all
+        return rest.is_empty();
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
 #[cfg(test)]
 mod package_tests {
     use std::path::PathBuf;
@@ -228,6 +656,66 @@ mod package_tests {
 
     use super::*;
 
+    #[test]
+    fn test_select_environment() {
+        let package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("linux", |b| b.install("linux install"))
+            .environment("ubuntu-*", |b| b.install("ubuntu install"))
+            .environment("*", |b| b.install("anywhere install"))
+            .build();
+
+        let select = |chain: &[&str]| package.select_environment(chain).map(|(name, _)| name);
+
+        assert_eq!(select(&["ubuntu-24.04", "linux"]), Some("ubuntu-*"));
+        assert_eq!(select(&["debian", "linux"]), Some("linux"));
+        assert_eq!(select(&["macos"]), Some("*"));
+
+        let no_wildcard = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("linux", |b| b.install("linux install"))
+            .build();
+        assert!(!no_wildcard.supports_environment(&["macos", "default"]));
+    }
+
+    #[test]
+    fn test_wildcard_matches() {
+        assert!(wildcard_matches("*", "macos"));
+        assert!(wildcard_matches("ubuntu-*", "ubuntu-24.04"));
+        assert!(wildcard_matches("*-arm64", "macos-arm64"));
+        assert!(wildcard_matches("linux-*-arm64", "linux-ubuntu-arm64"));
+        assert!(!wildcard_matches("ubuntu-*", "debian-12"));
+        assert!(!wildcard_matches("a*a", "a"));
+        assert!(!wildcard_matches("linux", "linux-arm64"));
+    }
+
+    #[test]
+    fn test_environment_timeout_overrides() {
+        let env_config = EnvironmentConfigBuilder::default()
+            .install("brew install llvm")
+            .timeout(120)
+            .install_timeout(1800)
+            .build();
+
+        assert_eq!(
+            env_config.timeout(CommandKind::Install),
+            Some((Duration::from_secs(1800), "install_timeout"))
+        );
+        assert_eq!(
+            env_config.timeout(CommandKind::Check),
+            Some((Duration::from_secs(120), "timeout"))
+        );
+        assert_eq!(
+            EnvironmentConfigBuilder::default()
+                .check_timeout(5)
+                .build()
+                .timeout(CommandKind::Other),
+            None
+        );
+    }
+
     #[test]
     fn test_create_package_node() {
         let package = PackageBuilder::default()
@@ -241,7 +729,7 @@ mod package_tests {
         assert_eq!(package.environments.len(), 1);
         assert_eq!(
             package.environments.get("test-env").unwrap().install,
-            "test install"
+            "test install".into()
         );
     }
 
@@ -435,7 +923,7 @@ mod package_tests {
             packages_path: PathBuf::from("/packages"),
             failed_file: PathBuf::from("/packages/broken.yml"),
             file_size_bytes: 1024,
-            source: parse_error,
+            source: Box::new(parse_error),
         };
 
         // Test that file context is available
@@ -453,7 +941,7 @@ mod package_tests {
                 assert_eq!(file_size_bytes, 1024);
 
                 // Verify the parse error is preserved
-                match source {
+                match *source {
                     PackageParseError::YamlParse { package_path, .. } => {
                         assert_eq!(package_path, PathBuf::from("/packages/broken.yml"));
                     }
@@ -1,6 +1,13 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
 
-use super::{EnvironmentConfig, Package};
+use super::{
+    EnvironmentConfig, Package,
+    condition::{Condition, ConditionalCommand},
+    script::InstallScript,
+};
 
 #[derive(Default)]
 pub struct PackageBuilder {
@@ -8,6 +15,9 @@ pub struct PackageBuilder {
     version: String,
     homepage: Option<String>,
     description: Option<String>,
+    check: Option<String>,
+    dependencies: Vec<String>,
+    vars: BTreeMap<String, String>,
     environments: HashMap<String, EnvironmentConfig>,
     path: PathBuf,
 }
@@ -37,6 +47,27 @@ impl PackageBuilder {
         self
     }
 
+    /// Set the default `check` command for environments that don't set their own
+    #[must_use]
+    pub fn check(mut self, check: &str) -> Self {
+        self.check = Some(check.to_string());
+        self
+    }
+
+    /// Set the default dependencies for environments that don't list their own
+    #[must_use]
+    pub fn dependencies<T: ToString>(mut self, dependencies: Vec<T>) -> Self {
+        self.dependencies = dependencies.into_iter().map(|d| d.to_string()).collect();
+        self
+    }
+
+    /// Set a variable for the `{{placeholders}}` in the package's commands
+    #[must_use]
+    pub fn var(mut self, name: &str, value: &str) -> Self {
+        self.vars.insert(name.to_string(), value.to_string());
+        self
+    }
+
     pub fn environment<T, F>(mut self, name: T, env_builder: F) -> Self
     where
         T: ToString,
@@ -59,26 +90,58 @@ impl PackageBuilder {
 
     #[must_use]
     pub fn build(self) -> Package {
-        Package::new(
+        let mut package = Package::new(
             self.name,
             self.version,
             self.homepage,
             self.description,
             self.environments,
             self.path,
-        )
+        );
+        package.check = self.check;
+        package.dependencies = self.dependencies;
+        package.vars = self.vars;
+        package.resolve_environments();
+        package
     }
 }
 
 #[derive(Default)]
 pub struct EnvironmentConfigBuilder {
-    install: String,
+    uses: Option<String>,
+    when: Option<Condition>,
+    install: InstallScript,
     check: Option<String>,
-    dependencies: Vec<String>,
+    version_command: Option<String>,
+    version_regex: Option<String>,
+    required_version: Option<String>,
+    pre_install: Vec<ConditionalCommand>,
+    post_install: Vec<ConditionalCommand>,
+    dependencies: Option<Vec<String>>,
+    uninstall: Option<String>,
+    update: Option<String>,
+    rollback: Vec<String>,
+    retries: Option<u32>,
+    retry_delay: Option<u64>,
+    timeout: Option<u64>,
+    install_timeout: Option<u64>,
+    check_timeout: Option<u64>,
+    cwd: Option<String>,
+    env: BTreeMap<String, String>,
 }
 impl EnvironmentConfigBuilder {
     pub fn install<T: ToString>(mut self, install: T) -> Self {
-        self.install = install.to_string();
+        self.install = install.to_string().into();
+        self
+    }
+
+    #[must_use]
+    pub fn install_script<T: ToString>(mut self, install: Vec<T>) -> Self {
+        self.install = install
+            .into_iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .into();
         self
     }
 
@@ -92,18 +155,151 @@ impl EnvironmentConfigBuilder {
         self
     }
 
+    pub fn version_command_some<T: ToString>(mut self, version_command: T) -> Self {
+        self.version_command = Some(version_command.to_string());
+        self
+    }
+
+    pub fn version_regex_some<T: ToString>(mut self, version_regex: T) -> Self {
+        self.version_regex = Some(version_regex.to_string());
+        self
+    }
+
+    pub fn required_version_some<T: ToString>(mut self, required_version: T) -> Self {
+        self.required_version = Some(required_version.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn pre_install<T: ToString>(mut self, pre_install: Vec<T>) -> Self {
+        self.pre_install = pre_install
+            .into_iter()
+            .map(|c| c.to_string().into())
+            .collect();
+        self
+    }
+
+    #[must_use]
+    pub fn post_install<T: ToString>(mut self, post_install: Vec<T>) -> Self {
+        self.post_install = post_install
+            .into_iter()
+            .map(|c| c.to_string().into())
+            .collect();
+        self
+    }
+
     #[must_use]
     pub fn dependencies<T: ToString>(mut self, dependencies: Vec<T>) -> Self {
-        self.dependencies = dependencies.into_iter().map(|d| d.to_string()).collect();
+        self.dependencies = Some(dependencies.into_iter().map(|d| d.to_string()).collect());
+        self
+    }
+
+    /// Only apply this config on machines that meet `when`
+    #[must_use]
+    pub fn when(mut self, when: Condition) -> Self {
+        self.when = Some(when);
+        self
+    }
+
+    /// Reuse another environment's config for everything this one doesn't set
+    #[must_use]
+    pub fn uses<T: ToString>(mut self, environment: T) -> Self {
+        self.uses = Some(environment.to_string());
+        self
+    }
+
+    pub fn uninstall<T: ToString>(mut self, uninstall: Option<T>) -> Self {
+        self.uninstall = uninstall.map(|u| u.to_string());
+        self
+    }
+
+    pub fn uninstall_some<T: ToString>(mut self, uninstall: T) -> Self {
+        self.uninstall = Some(uninstall.to_string());
+        self
+    }
+
+    pub fn update<T: ToString>(mut self, update: Option<T>) -> Self {
+        self.update = update.map(|u| u.to_string());
+        self
+    }
+
+    pub fn update_some<T: ToString>(mut self, update: T) -> Self {
+        self.update = Some(update.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn rollback<T: ToString>(mut self, rollback: Vec<T>) -> Self {
+        self.rollback = rollback.into_iter().map(|r| r.to_string()).collect();
+        self
+    }
+
+    #[must_use]
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    #[must_use]
+    pub fn retry_delay(mut self, seconds: u64) -> Self {
+        self.retry_delay = Some(seconds);
+        self
+    }
+
+    #[must_use]
+    pub fn timeout(mut self, seconds: u64) -> Self {
+        self.timeout = Some(seconds);
+        self
+    }
+
+    #[must_use]
+    pub fn install_timeout(mut self, seconds: u64) -> Self {
+        self.install_timeout = Some(seconds);
+        self
+    }
+
+    #[must_use]
+    pub fn check_timeout(mut self, seconds: u64) -> Self {
+        self.check_timeout = Some(seconds);
+        self
+    }
+
+    #[must_use]
+    pub fn cwd<T: ToString>(mut self, cwd: T) -> Self {
+        self.cwd = Some(cwd.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn env<K: ToString, V: ToString>(mut self, name: K, value: V) -> Self {
+        self.env.insert(name.to_string(), value.to_string());
         self
     }
 
     #[must_use]
     pub fn build(self) -> EnvironmentConfig {
         EnvironmentConfig {
+            uses: self.uses,
+            when: self.when,
             install: self.install,
             check: self.check,
+            version_command: self.version_command,
+            version_regex: self.version_regex,
+            required_version: self.required_version,
+            pre_install: self.pre_install,
+            post_install: self.post_install,
             dependencies: self.dependencies,
+            uninstall: self.uninstall,
+            update: self.update,
+            rollback: self.rollback,
+            retries: self.retries,
+            retry_delay: self.retry_delay,
+            timeout: self.timeout,
+            install_timeout: self.install_timeout,
+            check_timeout: self.check_timeout,
+            cwd: self.cwd,
+            env: self.env,
+            context: BTreeMap::new(),
         }
     }
 }
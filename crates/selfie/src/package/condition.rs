@@ -0,0 +1,310 @@
+//! `when:` conditions
+//!
+//! Some setup only applies on certain machines, e.g. on Apple Silicon or when
+//! `/opt/homebrew` exists. An environment block, or a single `pre_install` or
+//! `post_install` command, can carry a `when:` clause:
+//!
+//! ```yaml
+//! environments:
+//!   macos:
+//!     when: { arch: arm64 }
+//!     install: brew install ripgrep
+//!     post_install:
+//!       - run: fish_add_path /opt/homebrew/bin
+//!         when: { command_exists: fish }
+//! ```
+//!
+//! Every predicate that's set must hold. A block or command whose conditions
+//! aren't met is skipped, and reported as skipped rather than as a failure.
+
+use std::{fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{commands::runner::CommandRunner, config::detect::normalize_arch, fs::FileSystem};
+
+/// Predicates that must all hold for a block or command to apply
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    /// A command that must be available, e.g. `brew`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) command_exists: Option<String>,
+
+    /// A file or directory that must exist, e.g. `~/.cargo`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) file_exists: Option<String>,
+
+    /// An environment variable that must be set (`CI`), or must have a given
+    /// value (`CI=true`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) env_var: Option<String>,
+
+    /// The machine architecture, e.g. `arm64`; `arm64` and `aarch64` are the same,
+    /// and so are `x86_64` and `amd64`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) arch: Option<String>,
+}
+
+impl Condition {
+    /// Whether the clause has no predicates, and so always holds
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.command_exists.is_none()
+            && self.file_exists.is_none()
+            && self.env_var.is_none()
+            && self.arch.is_none()
+    }
+
+    /// Evaluate the predicates and describe the first one that doesn't hold
+    ///
+    /// Returns `None` when every predicate holds. The cheap predicates are
+    /// evaluated first, so `command_exists` only runs when it matters.
+    pub async fn unmet<CR, F>(&self, command_runner: &CR, fs: &F, arch: &str) -> Option<String>
+    where
+        CR: CommandRunner,
+        F: FileSystem,
+    {
+        if let Some(expected) = &self.arch
+            && !same_arch(expected, arch)
+        {
+            return Some(format!("arch is `{arch}`, not `{expected}`"));
+        }
+
+        if let Some(env_var) = &self.env_var {
+            match env_var.split_once('=') {
+                Some((name, expected)) => {
+                    if std::env::var(name).ok().as_deref() != Some(expected) {
+                        return Some(format!("`{name}` isn't `{expected}`"));
+                    }
+                }
+                None => {
+                    if std::env::var_os(env_var).is_none_or(|value| value.is_empty()) {
+                        return Some(format!("`{env_var}` isn't set"));
+                    }
+                }
+            }
+        }
+
+        if let Some(path) = &self.file_exists {
+            let exists = fs
+                .expand_path(Path::new(path))
+                .is_ok_and(|expanded| fs.path_exists(&expanded));
+            if !exists {
+                return Some(format!("`{path}` doesn't exist"));
+            }
+        }
+
+        if let Some(command) = &self.command_exists
+            && !command_runner.is_command_available(command).await
+        {
+            return Some(format!("`{command}` isn't available"));
+        }
+
+        None
+    }
+}
+
+impl fmt::Display for Condition {
+    /// Lists the predicates as they're written, e.g. `arch: arm64, command_exists: brew`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let predicates = [
+            ("command_exists", &self.command_exists),
+            ("file_exists", &self.file_exists),
+            ("env_var", &self.env_var),
+            ("arch", &self.arch),
+        ];
+        let set: Vec<String> = predicates
+            .into_iter()
+            .filter_map(|(name, value)| value.as_ref().map(|value| format!("{name}: {value}")))
+            .collect();
+
+        f.write_str(&set.join(", "))
+    }
+}
+
+/// Whether two architecture names mean the same machine
+fn same_arch(expected: &str, actual: &str) -> bool {
+    normalize_arch(expected) == normalize_arch(actual)
+}
+
+/// A command that can carry its own `when:` clause
+///
+/// Written either as a plain string, or as `{ run: <command>, when: {...} }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "CommandDef", into = "CommandDef")]
+pub struct ConditionalCommand {
+    pub(crate) run: String,
+    pub(crate) when: Option<Condition>,
+}
+
+impl ConditionalCommand {
+    /// The command to run
+    #[must_use]
+    pub fn run(&self) -> &str {
+        &self.run
+    }
+
+    /// The conditions that must hold for the command to run, if any
+    #[must_use]
+    pub fn when(&self) -> Option<&Condition> {
+        self.when.as_ref()
+    }
+}
+
+impl From<String> for ConditionalCommand {
+    fn from(run: String) -> Self {
+        Self { run, when: None }
+    }
+}
+
+impl From<&str> for ConditionalCommand {
+    fn from(run: &str) -> Self {
+        run.to_string().into()
+    }
+}
+
+/// How a [`ConditionalCommand`] is written in a package file
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum CommandDef {
+    Plain(String),
+    Conditional(ConditionalDef),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConditionalDef {
+    run: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    when: Option<Condition>,
+}
+
+impl From<CommandDef> for ConditionalCommand {
+    fn from(def: CommandDef) -> Self {
+        match def {
+            CommandDef::Plain(run) => Self { run, when: None },
+            CommandDef::Conditional(ConditionalDef { run, when }) => Self { run, when },
+        }
+    }
+}
+
+impl From<ConditionalCommand> for CommandDef {
+    fn from(command: ConditionalCommand) -> Self {
+        match command.when {
+            None => Self::Plain(command.run),
+            when @ Some(_) => Self::Conditional(ConditionalDef {
+                run: command.run,
+                when,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::runner::MockCommandRunner, fs::filesystem::MockFileSystem};
+
+    fn condition(yaml: &str) -> Condition {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_arch_aliases() {
+        let runner = MockCommandRunner::new();
+        let fs = MockFileSystem::default();
+        let arm = condition("arch: arm64");
+
+        assert_eq!(arm.unmet(&runner, &fs, "aarch64").await, None);
+        assert_eq!(
+            arm.unmet(&runner, &fs, "x86_64").await.as_deref(),
+            Some("arch is `x86_64`, not `arm64`")
+        );
+        assert_eq!(
+            condition("arch: amd64").unmet(&runner, &fs, "x86_64").await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_and_command_predicates() {
+        let mut runner = MockCommandRunner::new();
+        runner
+            .expect_is_command_available()
+            .returning(|command| command == "brew");
+        let mut fs = MockFileSystem::default();
+        fs.expect_expand_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        fs.expect_path_exists()
+            .returning(|path| path == Path::new("/opt/homebrew"));
+
+        let homebrew = condition("{ file_exists: /opt/homebrew, command_exists: brew }");
+        assert_eq!(homebrew.unmet(&runner, &fs, "arm64").await, None);
+
+        let missing_file = condition("file_exists: /opt/local");
+        assert_eq!(
+            missing_file.unmet(&runner, &fs, "arm64").await.as_deref(),
+            Some("`/opt/local` doesn't exist")
+        );
+
+        let missing_command = condition("command_exists: port");
+        assert_eq!(
+            missing_command
+                .unmet(&runner, &fs, "arm64")
+                .await
+                .as_deref(),
+            Some("`port` isn't available")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_env_var_predicate() {
+        let runner = MockCommandRunner::new();
+        let fs = MockFileSystem::default();
+
+        assert_eq!(
+            condition("env_var: PATH")
+                .unmet(&runner, &fs, "arm64")
+                .await,
+            None
+        );
+        assert_eq!(
+            condition("env_var: SELFIE_TEST_UNSET_VARIABLE")
+                .unmet(&runner, &fs, "arm64")
+                .await
+                .as_deref(),
+            Some("`SELFIE_TEST_UNSET_VARIABLE` isn't set")
+        );
+        assert!(
+            condition("env_var: PATH=/nowhere")
+                .unmet(&runner, &fs, "arm64")
+                .await
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_conditional_command_forms() {
+        let commands: Vec<ConditionalCommand> = serde_yaml::from_str(
+            r"
+- echo plain
+- run: echo arm
+  when: { arch: arm64 }
+",
+        )
+        .unwrap();
+
+        assert_eq!(commands[0], ConditionalCommand::from("echo plain"));
+        assert_eq!(commands[1].run(), "echo arm");
+        assert_eq!(commands[1].when(), Some(&condition("arch: arm64")));
+
+        // Plain commands are written back as plain strings
+        let yaml = serde_yaml::to_string(&commands).unwrap();
+        assert!(yaml.starts_with("- echo plain\n"));
+
+        let unknown: Result<Vec<ConditionalCommand>, _> =
+            serde_yaml::from_str("- { run: echo, when: { os: macos } }");
+        assert!(unknown.is_err());
+    }
+}
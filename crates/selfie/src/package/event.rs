@@ -12,6 +12,7 @@ use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use self::{error::StreamedError, metadata::OperationType};
+use super::state::InstallRecord;
 
 pub type EventStream = Pin<Box<dyn Stream<Item = PackageEvent> + Send>>;
 
@@ -31,6 +32,7 @@ impl EventSender {
     ) -> Self {
         let operation_info = OperationInfo {
             id: Uuid::new_v4(),
+            parent_id: None,
             operation_type,
             package_name,
             environment,
@@ -41,6 +43,42 @@ impl EventSender {
         Self { operation_info, tx }
     }
 
+    /// Create a sender for a sub-operation on another package
+    ///
+    /// Events sent through the child go to the same stream, but carry their own
+    /// [`OperationInfo::id`] and point back to this operation through
+    /// [`OperationInfo::parent_id`]. This keeps events from concurrent work on
+    /// different packages attributable.
+    pub(crate) fn child(&self, package_name: impl Into<String>) -> Self {
+        self.child_operation(self.operation_info.operation_type, package_name)
+    }
+
+    /// Create a sender for a different kind of sub-operation, like rolling back a
+    /// failed install
+    ///
+    /// Works like [`EventSender::child`], but the child's events are tagged with
+    /// `operation_type` so they can be shown as their own section.
+    pub(crate) fn child_operation(
+        &self,
+        operation_type: OperationType,
+        package_name: impl Into<String>,
+    ) -> Self {
+        let operation_info = OperationInfo {
+            id: Uuid::new_v4(),
+            parent_id: Some(self.operation_info.id),
+            operation_type,
+            package_name: package_name.into(),
+            environment: self.operation_info.environment.clone(),
+            context: OperationContext::default(),
+            timestamp: Instant::now(),
+        };
+
+        Self {
+            operation_info,
+            tx: self.tx.clone(),
+        }
+    }
+
     pub(crate) async fn send(&self, event: PackageEvent) {
         let _ = self.tx.send(event).await;
     }
@@ -95,7 +133,13 @@ impl EventSender {
             operation_type = operation_info.operation_type.to_string(),
             package_name = &operation_info.package_name,
             environment = &operation_info.environment,
-            success = matches!(result, OperationResult::Success(_)),
+            success = matches!(
+                result,
+                OperationResult::Success(_)
+                    | OperationResult::AlreadyInstalled(_)
+                    | OperationResult::NotInstalled(_)
+                    | OperationResult::Skipped(_)
+            ),
             "operation completed",
         );
 
@@ -225,7 +269,7 @@ impl EventSender {
         let operation_info = self.touch_operation_info();
         self.send(PackageEvent::EnvironmentStatusChecked {
             operation_info,
-            environment_status,
+            environment_status: Box::new(environment_status),
         })
         .await;
     }
@@ -250,6 +294,66 @@ impl EventSender {
         .await;
     }
 
+    /// Send the summary of checking every package
+    pub(crate) async fn send_check_summary(&self, check_summary: CheckSummaryData) {
+        let operation_info = self.touch_operation_info();
+        self.send(PackageEvent::CheckSummaryCompleted {
+            operation_info,
+            check_summary,
+        })
+        .await;
+    }
+
+    /// Send the outcome of upgrading a package
+    pub(crate) async fn send_upgrade_result(&self, upgrade_result: UpgradeResultData) {
+        let operation_info = self.touch_operation_info();
+        self.send(PackageEvent::UpgradeResultCompleted {
+            operation_info,
+            upgrade_result,
+        })
+        .await;
+    }
+
+    /// Send the summary of upgrading every package
+    pub(crate) async fn send_upgrade_summary(&self, upgrade_summary: UpgradeSummaryData) {
+        let operation_info = self.touch_operation_info();
+        self.send(PackageEvent::UpgradeSummaryCompleted {
+            operation_info,
+            upgrade_summary,
+        })
+        .await;
+    }
+
+    /// Send the summary of installing several packages
+    pub(crate) async fn send_install_summary(&self, install_summary: InstallSummaryData) {
+        let operation_info = self.touch_operation_info();
+        self.send(PackageEvent::InstallSummaryCompleted {
+            operation_info,
+            install_summary,
+        })
+        .await;
+    }
+
+    /// Send the outcome of rolling back a failed install
+    pub(crate) async fn send_rollback(&self, rollback: RollbackData) {
+        let operation_info = self.touch_operation_info();
+        self.send(PackageEvent::RollbackCompleted {
+            operation_info,
+            rollback,
+        })
+        .await;
+    }
+
+    /// Send the plan of what an install would do
+    pub(crate) async fn send_install_plan(&self, install_plan: InstallPlanData) {
+        let operation_info = self.touch_operation_info();
+        self.send(PackageEvent::InstallPlanCompleted {
+            operation_info,
+            install_plan,
+        })
+        .await;
+    }
+
     /// Send validation result data
     pub(crate) async fn send_validation_result(&self, validation_result: ValidationResultData) {
         let operation_info = self.touch_operation_info();
@@ -272,6 +376,9 @@ impl EventSender {
 pub struct OperationInfo {
     /// Unique ID for the operation
     pub id: Uuid,
+    /// ID of the operation this one is part of, e.g. the install of the package
+    /// that a dependency is being installed for
+    pub parent_id: Option<Uuid>,
     /// Type of operation
     pub operation_type: OperationType,
     /// Name of the package being operated on
@@ -319,6 +426,13 @@ pub struct OperationContext {
 pub enum OperationResult {
     Success(String),
     Failure(String),
+    /// The package's `check` command passed, so there was nothing to install
+    AlreadyInstalled(String),
+    /// The package's `check` command failed, so there was nothing to uninstall
+    NotInstalled(String),
+    /// The environment's `when:` conditions aren't met on this machine, so
+    /// nothing was run
+    Skipped(String),
 }
 
 /// Events that can be emitted during package operations
@@ -388,7 +502,7 @@ pub enum PackageEvent {
     /// Environment status checked
     EnvironmentStatusChecked {
         operation_info: OperationInfo,
-        environment_status: EnvironmentStatusData,
+        environment_status: Box<EnvironmentStatusData>,
     },
 
     /// Package list loaded
@@ -403,6 +517,42 @@ pub enum PackageEvent {
         check_result: CheckResultData,
     },
 
+    /// Checks of every package completed
+    CheckSummaryCompleted {
+        operation_info: OperationInfo,
+        check_summary: CheckSummaryData,
+    },
+
+    /// Install plan worked out for a dry run
+    InstallPlanCompleted {
+        operation_info: OperationInfo,
+        install_plan: InstallPlanData,
+    },
+
+    /// Installation of several packages completed
+    InstallSummaryCompleted {
+        operation_info: OperationInfo,
+        install_summary: InstallSummaryData,
+    },
+
+    /// Rollback of a failed install completed
+    RollbackCompleted {
+        operation_info: OperationInfo,
+        rollback: RollbackData,
+    },
+
+    /// Upgrade of a package completed
+    UpgradeResultCompleted {
+        operation_info: OperationInfo,
+        upgrade_result: UpgradeResultData,
+    },
+
+    /// Upgrades of every package completed
+    UpgradeSummaryCompleted {
+        operation_info: OperationInfo,
+        upgrade_summary: UpgradeSummaryData,
+    },
+
     /// Validation result completed
     ValidationResultCompleted {
         operation_info: OperationInfo,
@@ -410,6 +560,35 @@ pub enum PackageEvent {
     },
 }
 
+impl PackageEvent {
+    /// The operation that emitted this event
+    #[must_use]
+    pub fn operation_info(&self) -> &OperationInfo {
+        match self {
+            Self::Started { operation_info }
+            | Self::Progress { operation_info, .. }
+            | Self::Completed { operation_info, .. }
+            | Self::Canceled { operation_info, .. }
+            | Self::Trace { operation_info, .. }
+            | Self::Debug { operation_info, .. }
+            | Self::Info { operation_info, .. }
+            | Self::Warning { operation_info, .. }
+            | Self::Error { operation_info, .. }
+            | Self::PackageInfoLoaded { operation_info, .. }
+            | Self::EnvironmentStatusChecked { operation_info, .. }
+            | Self::PackageListLoaded { operation_info, .. }
+            | Self::CheckResultCompleted { operation_info, .. }
+            | Self::CheckSummaryCompleted { operation_info, .. }
+            | Self::InstallSummaryCompleted { operation_info, .. }
+            | Self::InstallPlanCompleted { operation_info, .. }
+            | Self::RollbackCompleted { operation_info, .. }
+            | Self::UpgradeResultCompleted { operation_info, .. }
+            | Self::UpgradeSummaryCompleted { operation_info, .. }
+            | Self::ValidationResultCompleted { operation_info, .. } => operation_info,
+        }
+    }
+}
+
 /// Structured data for package information
 #[derive(Debug, Clone)]
 pub struct PackageInfoData {
@@ -419,17 +598,31 @@ pub struct PackageInfoData {
     pub homepage: Option<String>,
     pub environments: Vec<String>,
     pub current_environment: String,
+    /// The environment block used in the current environment; differs from
+    /// `current_environment` when a fallback or wildcard block was picked
+    pub selected_environment: Option<String>,
 }
 
 /// Structured data for environment status
 #[derive(Debug, Clone)]
 pub struct EnvironmentStatusData {
     pub environment_name: String,
+    /// Whether this is the block selected for the current environment
     pub is_current: bool,
     pub install_command: String,
     pub check_command: Option<String>,
+    pub pre_install_commands: Vec<String>,
+    pub post_install_commands: Vec<String>,
+    pub uninstall_command: Option<String>,
+    pub update_command: Option<String>,
+    pub rollback_commands: Vec<String>,
     pub dependencies: Vec<String>,
     pub status: Option<EnvironmentStatus>,
+    /// The installed version found by the environment's `version_command`
+    pub installed_version: Option<String>,
+    pub required_version: Option<String>,
+    /// What selfie recorded when it installed the package in this environment
+    pub install_record: Option<InstallRecord>,
 }
 
 /// Status of a package in an environment
@@ -455,6 +648,10 @@ pub struct PackageListItem {
     pub name: String,
     pub version: String,
     pub environments: Vec<String>,
+    /// The environment block used in the current environment, if any
+    pub selected_environment: Option<String>,
+    /// What selfie recorded when it installed the package in the current environment
+    pub install_record: Option<InstallRecord>,
 }
 
 /// Information about an invalid package
@@ -477,6 +674,12 @@ pub struct CheckResultData {
 #[derive(Debug, Clone)]
 pub enum CheckResult {
     Success,
+    /// The check command passed, but the installed version doesn't meet the
+    /// environment's `required_version`
+    Outdated {
+        installed_version: String,
+        required_version: String,
+    },
     Failed {
         stdout: String,
         stderr: String,
@@ -485,6 +688,208 @@ pub enum CheckResult {
     CommandNotFound,
     NoCheckCommand,
     Error(String),
+    /// The environment's `when:` conditions aren't met on this machine, so the
+    /// check command wasn't run
+    Skipped(String),
+}
+
+/// Structured data summarizing the checks of every package in an environment
+#[derive(Debug, Clone, Default)]
+pub struct CheckSummaryData {
+    pub environment: String,
+    /// Packages whose check command passed
+    pub installed: Vec<String>,
+    /// Packages that are installed, but older than their `required_version`
+    pub outdated: Vec<String>,
+    /// Packages whose check command ran and failed
+    pub missing: Vec<String>,
+    /// Packages that couldn't be checked, e.g. because they have no check command or
+    /// the command couldn't be run
+    pub failed: Vec<String>,
+    /// Packages left unchecked because the sweep stopped at the first failure
+    pub skipped: Vec<String>,
+    /// Packages left unchecked because their `when:` conditions aren't met
+    pub not_applicable: Vec<String>,
+}
+
+impl CheckSummaryData {
+    /// Record the outcome of one package's check
+    pub fn record(&mut self, check_result: &CheckResultData) {
+        let name = check_result.package_name.clone();
+
+        match check_result.result {
+            CheckResult::Success => self.installed.push(name),
+            CheckResult::Outdated { .. } => self.outdated.push(name),
+            CheckResult::Failed { .. } => self.missing.push(name),
+            CheckResult::CommandNotFound | CheckResult::NoCheckCommand | CheckResult::Error(_) => {
+                self.failed.push(name);
+            }
+            CheckResult::Skipped(_) => self.not_applicable.push(name),
+        }
+    }
+
+    /// Number of packages that were checked
+    #[must_use]
+    pub fn checked(&self) -> usize {
+        self.installed.len() + self.outdated.len() + self.missing.len() + self.failed.len()
+    }
+}
+
+/// Structured data describing what installing a package would do
+#[derive(Debug, Clone)]
+pub struct InstallPlanData {
+    /// The packages that were asked to be installed
+    pub requested: Vec<String>,
+    pub environment: String,
+    /// Every package involved, in the order they'd be handled
+    pub steps: Vec<InstallPlanStep>,
+    /// Requested packages that can't be installed, because they couldn't be loaded
+    /// or their dependencies couldn't be resolved
+    pub failed: Vec<String>,
+}
+
+impl InstallPlanData {
+    /// Steps that would run an install command
+    pub fn to_install(&self) -> impl Iterator<Item = &InstallPlanStep> {
+        self.steps
+            .iter()
+            .filter(|step| step.action == InstallPlanAction::Install)
+    }
+}
+
+/// Structured data summarizing the installation of several packages
+///
+/// Lists requested packages and dependencies alike.
+#[derive(Debug, Clone, Default)]
+pub struct InstallSummaryData {
+    pub environment: String,
+    /// Packages whose install command ran successfully
+    pub installed: Vec<String>,
+    /// Packages whose check command showed they were already installed
+    pub already_installed: Vec<String>,
+    /// Packages whose install failed
+    pub failed: Vec<String>,
+    /// Packages left alone because something they depend on failed, or because
+    /// installation stopped at the first failure
+    pub skipped: Vec<String>,
+    /// Packages left alone because their `when:` conditions aren't met
+    pub not_applicable: Vec<String>,
+}
+
+/// A single package in an [`InstallPlanData`]
+#[derive(Debug, Clone)]
+pub struct InstallPlanStep {
+    pub package_name: String,
+    /// The exact command that would be run to install the package
+    pub install_command: String,
+    pub action: InstallPlanAction,
+    /// The package that depends on this one; `None` for a requested package
+    pub required_by: Option<String>,
+}
+
+/// What an install would do with a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallPlanAction {
+    /// The install command would run
+    Install,
+    /// The package's check command passed, so nothing would run
+    AlreadyInstalled,
+    /// The environment's `when:` conditions aren't met, so nothing would run
+    Skipped,
+}
+
+/// Structured data for rolling back a package whose install command failed
+#[derive(Debug, Clone)]
+pub struct RollbackData {
+    pub package_name: String,
+    pub environment: String,
+    /// Every `rollback` command of the package, in the order they're run
+    pub steps: Vec<RollbackStep>,
+}
+
+impl RollbackData {
+    /// Whether every rollback command ran successfully
+    #[must_use]
+    pub fn succeeded(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| step.result == RollbackStepResult::Succeeded)
+    }
+}
+
+/// A single command in a [`RollbackData`]
+#[derive(Debug, Clone)]
+pub struct RollbackStep {
+    pub command: String,
+    pub result: RollbackStepResult,
+}
+
+/// What happened to a rollback command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RollbackStepResult {
+    Succeeded,
+    /// The command failed or couldn't be run
+    Failed(String),
+    /// The command never ran because an earlier rollback command failed
+    Skipped,
+}
+
+/// Structured data for the outcome of upgrading a package
+#[derive(Debug, Clone)]
+pub struct UpgradeResultData {
+    pub package_name: String,
+    pub environment: String,
+    pub update_command: Option<String>,
+    pub result: UpgradeResult,
+}
+
+/// Result of an upgrade operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpgradeResult {
+    /// The update command ran and the package is still detected afterwards
+    Upgraded,
+    /// The package has no `update` command for the environment, so it was left alone
+    NotUpgradable,
+    /// The package's check command failed, so there was nothing to upgrade
+    NotInstalled,
+    /// The update command failed, couldn't be run, or broke the package
+    Failed(String),
+    /// The environment's `when:` conditions aren't met on this machine, so
+    /// nothing was run
+    Skipped(String),
+}
+
+/// Structured data summarizing the upgrades of every package in an environment
+#[derive(Debug, Clone, Default)]
+pub struct UpgradeSummaryData {
+    pub environment: String,
+    /// Packages whose update command ran successfully
+    pub upgraded: Vec<String>,
+    /// Packages without an `update` command
+    pub not_upgradable: Vec<String>,
+    /// Packages that were skipped because they aren't installed
+    pub not_installed: Vec<String>,
+    /// Packages whose upgrade failed
+    pub failed: Vec<String>,
+    /// Packages left alone because the upgrades stopped at the first failure
+    pub skipped: Vec<String>,
+    /// Packages left alone because their `when:` conditions aren't met
+    pub not_applicable: Vec<String>,
+}
+
+impl UpgradeSummaryData {
+    /// Record the outcome of one package's upgrade
+    pub fn record(&mut self, upgrade_result: &UpgradeResultData) {
+        let name = upgrade_result.package_name.clone();
+
+        match upgrade_result.result {
+            UpgradeResult::Upgraded => self.upgraded.push(name),
+            UpgradeResult::NotUpgradable => self.not_upgradable.push(name),
+            UpgradeResult::NotInstalled => self.not_installed.push(name),
+            UpgradeResult::Failed(_) => self.failed.push(name),
+            UpgradeResult::Skipped(_) => self.not_applicable.push(name),
+        }
+    }
 }
 
 /// Structured data for validation results
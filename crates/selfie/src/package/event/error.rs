@@ -1,6 +1,14 @@
 use thiserror::Error;
 
-use crate::{commands::runner::CommandError, package::port::PackageRepoError};
+use crate::{
+    commands::runner::CommandError,
+    package::{
+        graph::DependencyError,
+        port::PackageRepoError,
+        service::{InstallError, UninstallError},
+        template::TemplateError,
+    },
+};
 
 #[derive(Debug, Error, Clone)]
 pub enum StreamedError {
@@ -8,4 +16,12 @@ pub enum StreamedError {
     PackageRepoError(#[from] PackageRepoError),
     #[error(transparent)]
     CommandError(#[from] CommandError),
+    #[error(transparent)]
+    DependencyError(#[from] DependencyError),
+    #[error(transparent)]
+    InstallError(#[from] InstallError),
+    #[error(transparent)]
+    UninstallError(#[from] UninstallError),
+    #[error(transparent)]
+    TemplateError(#[from] TemplateError),
 }
@@ -11,6 +11,9 @@ pub enum OperationType {
     PackageInfo,
     PackageInstall,
     PackageList,
+    PackageRollback,
+    PackageUninstall,
+    PackageUpgrade,
     PackageValidate,
 }
 
@@ -23,6 +26,9 @@ impl fmt::Display for OperationType {
             Self::PackageInfo => f.write_str("package_info"),
             Self::PackageInstall => f.write_str("package_install"),
             Self::PackageList => f.write_str("package_list"),
+            Self::PackageRollback => f.write_str("package_rollback"),
+            Self::PackageUninstall => f.write_str("package_uninstall"),
+            Self::PackageUpgrade => f.write_str("package_upgrade"),
             Self::PackageValidate => f.write_str("package_validate"),
         }
     }
@@ -0,0 +1,633 @@
+//! Dependency graph resolution for packages
+//!
+//! Packages list the packages they depend on per environment. This module follows
+//! those dependencies through the [`PackageRepository`] to build the full transitive
+//! closure for a single environment, ordered so every package comes after the
+//! packages it depends on. Each package's environment block is picked from an
+//! ordered chain of environments, so packages that only configure a fallback
+//! (like `default`) can still be part of the graph.
+
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use super::{
+    EnvironmentConfig, Package,
+    port::{PackageRepoError, PackageRepository},
+    template::{TemplateContext, TemplateError},
+};
+
+/// A package in a resolved dependency graph
+#[derive(Debug, Clone)]
+pub struct DependencyNode {
+    /// The package definition
+    package: Package,
+
+    /// The package's configuration for the environment the graph was resolved for
+    env_config: EnvironmentConfig,
+
+    /// Name of the package that first pulled this one into the graph; `None` for the
+    /// requested package itself
+    required_by: Option<String>,
+}
+
+impl DependencyNode {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.package.name()
+    }
+
+    #[must_use]
+    pub fn package(&self) -> &Package {
+        &self.package
+    }
+
+    #[must_use]
+    pub fn env_config(&self) -> &EnvironmentConfig {
+        &self.env_config
+    }
+
+    #[must_use]
+    pub fn required_by(&self) -> Option<&str> {
+        self.required_by.as_deref()
+    }
+}
+
+/// The transitive dependencies of one or more packages for one environment
+///
+/// Nodes are kept in topological order: every package appears after all of the
+/// packages it depends on. When resolved for a single package, that package is
+/// always last. Packages shared by several requested packages appear only once.
+#[derive(Debug, Clone)]
+pub struct DependencyGraph {
+    environment: String,
+    nodes: Vec<DependencyNode>,
+    /// Names of the requested packages, in the order they were requested
+    roots: Vec<String>,
+}
+
+impl DependencyGraph {
+    /// Resolve the dependency graph of `root` for the first environment in
+    /// `environments`
+    ///
+    /// Every dependency is loaded through `repo`, so the whole graph is known before
+    /// anything is installed. Each package uses the block picked by
+    /// [`Package::select_environment`] from `environments`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DependencyError`] if:
+    /// - A dependency can't be loaded from the repository
+    /// - The root package or one of its dependencies supports none of `environments`
+    /// - Packages depend on each other in a cycle
+    pub fn resolve<R>(
+        repo: &R,
+        root: &Package,
+        environments: &[&str],
+    ) -> Result<Self, DependencyError>
+    where
+        R: PackageRepository + ?Sized,
+    {
+        Self::resolve_many(repo, std::slice::from_ref(root), environments)
+    }
+
+    /// Resolve a single graph covering all of `roots` for `environments`
+    ///
+    /// Dependencies shared between the requested packages are only included once,
+    /// so installing the graph runs each of them a single time. A requested package
+    /// that's also a dependency of another requested package keeps its place in
+    /// dependency order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DependencyError`] under the same conditions as
+    /// [`DependencyGraph::resolve`].
+    pub fn resolve_many<R>(
+        repo: &R,
+        roots: &[Package],
+        environments: &[&str],
+    ) -> Result<Self, DependencyError>
+    where
+        R: PackageRepository + ?Sized,
+    {
+        let mut resolver = Resolver::new(repo, environments, false);
+        let mut root_names = Vec::with_capacity(roots.len());
+
+        for root in roots {
+            if root_names.iter().any(|name| name == root.name()) {
+                continue;
+            }
+            root_names.push(root.name().to_string());
+
+            if !resolver.resolved.contains(root.name()) {
+                resolver.visit(root.clone(), None)?;
+            }
+        }
+
+        Ok(Self {
+            environment: environments
+                .first()
+                .copied()
+                .unwrap_or_default()
+                .to_string(),
+            nodes: resolver.nodes,
+            roots: root_names,
+        })
+    }
+
+    /// The part of this graph needed to install `roots`
+    ///
+    /// Keeps only the given requested packages and the packages they depend on,
+    /// still in install order.
+    #[must_use]
+    pub fn subgraph(&self, roots: &[&str]) -> Self {
+        let mut needed: HashSet<&str> = roots.iter().copied().collect();
+
+        // Walking backwards visits every package before its dependencies
+        for node in self.nodes.iter().rev() {
+            if needed.contains(node.name()) {
+                needed.extend(node.env_config().dependencies().iter().map(String::as_str));
+            }
+        }
+
+        Self {
+            environment: self.environment.clone(),
+            nodes: self
+                .nodes
+                .iter()
+                .filter(|node| needed.contains(node.name()))
+                .cloned()
+                .collect(),
+            roots: self
+                .roots
+                .iter()
+                .filter(|root| roots.contains(&root.as_str()))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Expand the command templates of every package in the graph
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`TemplateError`] found in any package's commands.
+    pub fn render_commands(mut self, ctx: &TemplateContext) -> Result<Self, TemplateError> {
+        for node in &mut self.nodes {
+            node.env_config = ctx.render(&node.package, &node.env_config)?;
+        }
+        Ok(self)
+    }
+
+    /// Look for a dependency cycle reachable from `root` in `environments`
+    ///
+    /// Unlike [`DependencyGraph::resolve`], dependencies that can't be loaded or
+    /// don't support the environment are skipped, so this can be used to validate a
+    /// package on its own. Returns the cycle as a path that starts and ends with the
+    /// same package, e.g. `["a", "b", "c", "a"]`.
+    pub fn find_cycle<R>(repo: &R, root: &Package, environments: &[&str]) -> Option<Vec<String>>
+    where
+        R: PackageRepository + ?Sized,
+    {
+        let mut resolver = Resolver::new(repo, environments, true);
+
+        match resolver.visit(root.clone(), None) {
+            Err(DependencyError::Cycle { path }) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The environment the graph was resolved for
+    #[must_use]
+    pub fn environment(&self) -> &str {
+        &self.environment
+    }
+
+    /// All packages in install order, ending with the requested package
+    #[must_use]
+    pub fn install_order(&self) -> &[DependencyNode] {
+        &self.nodes
+    }
+
+    /// The dependencies of the requested package, in install order
+    #[must_use]
+    pub fn dependencies(&self) -> &[DependencyNode] {
+        self.nodes.split_last().map_or(&[], |(_, deps)| deps)
+    }
+
+    /// The package the graph was resolved for
+    ///
+    /// Only meaningful for a graph resolved for a single package; see
+    /// [`DependencyGraph::roots`] otherwise.
+    #[must_use]
+    pub fn root(&self) -> Option<&DependencyNode> {
+        self.nodes.last()
+    }
+
+    /// Names of the packages the graph was resolved for
+    #[must_use]
+    pub fn roots(&self) -> &[String] {
+        &self.roots
+    }
+
+    /// Whether `name` is one of the packages the graph was resolved for
+    #[must_use]
+    pub fn is_root(&self, name: &str) -> bool {
+        self.roots.iter().any(|root| root == name)
+    }
+
+    /// Number of packages in the graph, including the requested package
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+/// Depth-first walk that collects nodes in post-order (dependencies first)
+struct Resolver<'a, R: ?Sized> {
+    repo: &'a R,
+    /// The current environment, then its fallbacks
+    environments: &'a [&'a str],
+    /// Skip dependencies that can't be resolved instead of failing
+    lenient: bool,
+    /// Packages whose dependencies have all been resolved
+    resolved: HashSet<String>,
+    /// Packages currently being resolved; a dependency on one of these is a cycle
+    stack: Vec<String>,
+    nodes: Vec<DependencyNode>,
+}
+
+impl<'a, R> Resolver<'a, R>
+where
+    R: PackageRepository + ?Sized,
+{
+    fn new(repo: &'a R, environments: &'a [&'a str], lenient: bool) -> Self {
+        Self {
+            repo,
+            environments,
+            lenient,
+            resolved: HashSet::new(),
+            stack: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    fn visit(
+        &mut self,
+        package: Package,
+        required_by: Option<String>,
+    ) -> Result<(), DependencyError> {
+        let Some(env_config) = package
+            .select_environment(self.environments)
+            .map(|(_, env_config)| env_config.clone())
+        else {
+            if self.lenient {
+                self.resolved.insert(package.name().to_string());
+                return Ok(());
+            }
+
+            let mut available_environments: Vec<String> =
+                package.environments().keys().cloned().collect();
+            available_environments.sort();
+
+            return Err(DependencyError::UnsupportedEnvironment {
+                package_name: package.name().to_string(),
+                environment: self
+                    .environments
+                    .first()
+                    .copied()
+                    .unwrap_or_default()
+                    .to_string(),
+                required_by,
+                available_environments,
+            });
+        };
+
+        self.stack.push(package.name().to_string());
+
+        for dependency in env_config.dependencies() {
+            if let Some(start) = self.stack.iter().position(|name| name == dependency) {
+                let mut path = self.stack[start..].to_vec();
+                path.push(dependency.clone());
+                return Err(DependencyError::Cycle { path });
+            }
+
+            if self.resolved.contains(dependency) {
+                continue;
+            }
+
+            let dependency_package = match self.repo.get_package(dependency) {
+                Ok(blob) => blob.package,
+                Err(_) if self.lenient => {
+                    self.resolved.insert(dependency.clone());
+                    continue;
+                }
+                Err(source) => {
+                    return Err(DependencyError::MissingDependency {
+                        dependency: dependency.clone(),
+                        required_by: package.name().to_string(),
+                        source,
+                    });
+                }
+            };
+
+            self.visit(dependency_package, Some(package.name().to_string()))?;
+        }
+
+        self.stack.pop();
+        self.resolved.insert(package.name().to_string());
+        self.nodes.push(DependencyNode {
+            package,
+            env_config,
+            required_by,
+        });
+
+        Ok(())
+    }
+}
+
+/// Errors that can occur while resolving a dependency graph
+#[derive(Error, Debug, Clone)]
+pub enum DependencyError {
+    /// A dependency couldn't be loaded from the package repository
+    #[error("Dependency `{dependency}` of package `{required_by}` could not be loaded: {source}")]
+    MissingDependency {
+        dependency: String,
+        required_by: String,
+        #[source]
+        source: PackageRepoError,
+    },
+
+    /// A package in the graph has no configuration for the environment
+    #[error(
+        "Package `{package_name}`{} does not support environment `{environment}`",
+        required_by.as_ref().map(|p| format!(" (required by `{p}`)")).unwrap_or_default()
+    )]
+    UnsupportedEnvironment {
+        package_name: String,
+        environment: String,
+        /// The package that depends on this one, if it isn't the requested package
+        required_by: Option<String>,
+        /// Environments the package does support
+        available_environments: Vec<String>,
+    },
+
+    /// Packages depend on each other in a loop, so there's no valid install order
+    #[error("Dependency cycle detected: {}", path.join(" -> "))]
+    Cycle {
+        /// The packages in the cycle, starting and ending with the same package
+        path: Vec<String>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use super::*;
+    use crate::package::{
+        GetPackage, PackageBuilder,
+        port::{MockPackageRepository, PackageError},
+    };
+
+    fn package(name: &str, dependencies: &[&str]) -> Package {
+        PackageBuilder::default()
+            .name(name)
+            .version("1.0.0")
+            .environment("test-env", |b| {
+                b.install(format!("install {name}"))
+                    .dependencies(dependencies.to_vec())
+            })
+            .build()
+    }
+
+    fn mock_repo(packages: Vec<Package>) -> MockPackageRepository {
+        let packages: HashMap<String, Package> = packages
+            .into_iter()
+            .map(|p| (p.name().to_string(), p))
+            .collect();
+
+        let mut repo = MockPackageRepository::new();
+        repo.expect_get_package().returning(move |name| {
+            packages
+                .get(name)
+                .map(|p| GetPackage::from_existing(p.clone(), PathBuf::from(name)))
+                .ok_or_else(|| {
+                    PackageRepoError::PackageError(Box::new(PackageError::PackageNotFound {
+                        name: name.to_string(),
+                        packages_path: PathBuf::from("/packages"),
+                        files_examined: 0,
+                        search_patterns: Vec::new(),
+                    }))
+                })
+        });
+        repo
+    }
+
+    fn names(nodes: &[DependencyNode]) -> Vec<&str> {
+        nodes.iter().map(DependencyNode::name).collect()
+    }
+
+    #[test]
+    fn test_resolve_without_dependencies() {
+        let root = package("root", &[]);
+        let repo = mock_repo(vec![]);
+
+        let graph = DependencyGraph::resolve(&repo, &root, &["test-env"]).unwrap();
+
+        assert_eq!(names(graph.install_order()), vec!["root"]);
+        assert!(graph.dependencies().is_empty());
+        assert_eq!(graph.root().unwrap().name(), "root");
+    }
+
+    #[test]
+    fn test_resolve_orders_dependencies_first() {
+        // root -> (a, b); a -> c; b -> c
+        let root = package("root", &["a", "b"]);
+        let repo = mock_repo(vec![
+            package("a", &["c"]),
+            package("b", &["c"]),
+            package("c", &[]),
+        ]);
+
+        let graph = DependencyGraph::resolve(&repo, &root, &["test-env"]).unwrap();
+
+        assert_eq!(names(graph.install_order()), vec!["c", "a", "b", "root"]);
+        assert_eq!(names(graph.dependencies()), vec!["c", "a", "b"]);
+        assert_eq!(graph.install_order()[0].required_by(), Some("a"));
+    }
+
+    #[test]
+    fn test_resolve_many_shares_dependencies() {
+        // app -> (a, c); tool -> (b, c); a -> c
+        let app = package("app", &["a", "c"]);
+        let tool = package("tool", &["b", "c"]);
+        let repo = mock_repo(vec![
+            package("a", &["c"]),
+            package("b", &[]),
+            package("c", &[]),
+        ]);
+
+        let graph = DependencyGraph::resolve_many(&repo, &[app, tool], &["test-env"]).unwrap();
+
+        assert_eq!(
+            names(graph.install_order()),
+            vec!["c", "a", "app", "b", "tool"]
+        );
+        assert_eq!(graph.roots(), ["app", "tool"]);
+    }
+
+    #[test]
+    fn test_resolve_many_root_is_dependency_of_another_root() {
+        let app = package("app", &["lib"]);
+        let lib = package("lib", &[]);
+        let repo = mock_repo(vec![lib.clone()]);
+
+        let graph =
+            DependencyGraph::resolve_many(&repo, &[app, lib.clone(), lib], &["test-env"]).unwrap();
+
+        assert_eq!(names(graph.install_order()), vec!["lib", "app"]);
+        assert_eq!(graph.roots(), ["app", "lib"]);
+        assert!(graph.is_root("lib"));
+    }
+
+    #[test]
+    fn test_subgraph_keeps_only_what_roots_need() {
+        let app = package("app", &["a"]);
+        let tool = package("tool", &["b"]);
+        let repo = mock_repo(vec![
+            package("a", &["c"]),
+            package("b", &[]),
+            package("c", &[]),
+        ]);
+        let graph = DependencyGraph::resolve_many(&repo, &[app, tool], &["test-env"]).unwrap();
+
+        let subgraph = graph.subgraph(&["app"]);
+
+        assert_eq!(names(subgraph.install_order()), vec!["c", "a", "app"]);
+        assert_eq!(subgraph.roots(), ["app"]);
+    }
+
+    #[test]
+    fn test_resolve_missing_dependency() {
+        let root = package("root", &["a"]);
+        let repo = mock_repo(vec![package("a", &["missing"])]);
+
+        let err = DependencyGraph::resolve(&repo, &root, &["test-env"]).unwrap_err();
+
+        match err {
+            DependencyError::MissingDependency {
+                dependency,
+                required_by,
+                ..
+            } => {
+                assert_eq!(dependency, "missing");
+                assert_eq!(required_by, "a");
+            }
+            other => panic!("Expected MissingDependency, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_dependency_without_environment() {
+        let root = package("root", &["other-env"]);
+        let other = PackageBuilder::default()
+            .name("other-env")
+            .version("1.0.0")
+            .environment("other", |b| b.install("install"))
+            .build();
+        let repo = mock_repo(vec![other]);
+
+        let err = DependencyGraph::resolve(&repo, &root, &["test-env"]).unwrap_err();
+
+        assert!(matches!(
+            &err,
+            DependencyError::UnsupportedEnvironment { package_name, required_by: Some(parent), .. }
+                if package_name == "other-env" && parent == "root"
+        ));
+        assert!(err.to_string().contains("required by `root`"));
+    }
+
+    #[test]
+    fn test_resolve_detects_cycle() {
+        // a -> b -> c -> a
+        let root = package("a", &["b"]);
+        let repo = mock_repo(vec![
+            package("a", &["b"]),
+            package("b", &["c"]),
+            package("c", &["a"]),
+        ]);
+
+        let err = DependencyGraph::resolve(&repo, &root, &["test-env"]).unwrap_err();
+
+        assert!(matches!(&err, DependencyError::Cycle { path } if path == &["a", "b", "c", "a"]));
+        assert_eq!(
+            err.to_string(),
+            "Dependency cycle detected: a -> b -> c -> a"
+        );
+    }
+
+    #[test]
+    fn test_resolve_detects_self_dependency() {
+        let root = package("a", &["a"]);
+        let repo = mock_repo(vec![]);
+
+        let err = DependencyGraph::resolve(&repo, &root, &["test-env"]).unwrap_err();
+
+        assert!(matches!(err, DependencyError::Cycle { path } if path == ["a", "a"]));
+    }
+
+    #[test]
+    fn test_resolve_dependency_with_fallback_environment() {
+        let root = package("root", &["tool"]);
+        let tool = PackageBuilder::default()
+            .name("tool")
+            .version("1.0.0")
+            .environment("default", |b| b.install("install tool"))
+            .build();
+        let repo = mock_repo(vec![tool]);
+
+        let graph = DependencyGraph::resolve(&repo, &root, &["test-env", "default"]).unwrap();
+
+        assert_eq!(names(graph.install_order()), vec!["tool", "root"]);
+        assert_eq!(graph.environment(), "test-env");
+        assert_eq!(
+            graph.install_order()[0].env_config().install().to_string(),
+            "install tool"
+        );
+    }
+
+    #[test]
+    fn test_find_cycle_below_root() {
+        // root -> a -> b -> a, with a missing dependency that should be ignored
+        let root = package("root", &["missing", "a"]);
+        let repo = mock_repo(vec![package("a", &["b"]), package("b", &["a"])]);
+
+        let cycle = DependencyGraph::find_cycle(&repo, &root, &["test-env"]);
+
+        assert_eq!(
+            cycle,
+            Some(vec!["a".to_string(), "b".to_string(), "a".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_find_cycle_none_for_diamond() {
+        let root = package("root", &["a", "b"]);
+        let repo = mock_repo(vec![
+            package("a", &["c"]),
+            package("b", &["c"]),
+            package("c", &[]),
+        ]);
+
+        assert_eq!(
+            DependencyGraph::find_cycle(&repo, &root, &["test-env"]),
+            None
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Environment inheritance
+//!
+//! Package files often configure several environments the same way (e.g.
+//! `ubuntu` and `debian`). An environment can say `use: ubuntu` to start from
+//! another environment's config and only spell out what differs, and a package
+//! can give top-level `check` and `dependencies` defaults for every environment
+//! that doesn't set its own.
+//!
+//! Inheritance is resolved once, when a package is loaded, so everything that
+//! looks up an environment afterwards sees the complete config. An environment
+//! whose `use:` chain can't be followed is left out of the resolved package and
+//! its error kept, so validation can report it and lookups can explain why the
+//! environment is missing.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use super::{EnvironmentConfig, Package};
+
+/// Errors following an environment's `use:` chain
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InheritError {
+    /// An environment in the chain uses one that isn't defined
+    #[error("Environment `{environment}` uses `{uses}`, which isn't defined")]
+    MissingEnvironment { environment: String, uses: String },
+
+    /// The chain comes back around to an environment already in it
+    #[error("The `use:` chain loops: {}", chain.join(" -> "))]
+    Loop { chain: Vec<String> },
+}
+
+impl EnvironmentConfig {
+    /// Fill in everything this environment doesn't set itself from `parent`
+    fn inherit_from(&mut self, parent: &Self) {
+        fn inherit<T: Clone>(own: &mut Option<T>, parent: &Option<T>) {
+            if own.is_none() {
+                own.clone_from(parent);
+            }
+        }
+        fn inherit_list<T: Clone>(own: &mut Vec<T>, parent: &[T]) {
+            if own.is_empty() {
+                own.extend_from_slice(parent);
+            }
+        }
+
+        if self.install.is_empty() {
+            self.install.clone_from(&parent.install);
+        }
+        inherit(&mut self.when, &parent.when);
+        inherit(&mut self.check, &parent.check);
+        inherit(&mut self.version_command, &parent.version_command);
+        inherit(&mut self.version_regex, &parent.version_regex);
+        inherit(&mut self.required_version, &parent.required_version);
+        inherit(&mut self.dependencies, &parent.dependencies);
+        inherit(&mut self.uninstall, &parent.uninstall);
+        inherit(&mut self.update, &parent.update);
+        inherit(&mut self.retries, &parent.retries);
+        inherit(&mut self.retry_delay, &parent.retry_delay);
+        inherit(&mut self.timeout, &parent.timeout);
+        inherit(&mut self.install_timeout, &parent.install_timeout);
+        inherit(&mut self.check_timeout, &parent.check_timeout);
+        inherit(&mut self.cwd, &parent.cwd);
+        inherit_list(&mut self.pre_install, &parent.pre_install);
+        inherit_list(&mut self.post_install, &parent.post_install);
+        inherit_list(&mut self.rollback, &parent.rollback);
+        for (name, value) in &parent.env {
+            self.env
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+}
+
+impl Package {
+    /// Resolve every environment's `use:` chain and the package-level defaults
+    ///
+    /// Environments are resolved from the configs as written, so the order they're
+    /// resolved in doesn't matter. An environment's own settings win over the
+    /// ones it uses, which win over the package defaults. Environments whose chain
+    /// can't be followed are moved out of [`Package::environments`] and their
+    /// errors kept in [`Package::inheritance_errors`].
+    pub(crate) fn resolve_environments(&mut self) {
+        let mut resolved = std::collections::HashMap::with_capacity(self.environments.len());
+
+        for name in self.environments.keys() {
+            match self.use_chain(name) {
+                Ok(chain) => {
+                    let mut env_config = self.environments[name].clone();
+                    for ancestor in &chain[1..] {
+                        env_config.inherit_from(&self.environments[ancestor]);
+                    }
+                    self.apply_defaults(&mut env_config);
+                    resolved.insert(name.clone(), env_config);
+                }
+                Err(err) => {
+                    self.inheritance_errors.insert(name.clone(), err);
+                }
+            }
+        }
+
+        self.environments = resolved;
+    }
+
+    /// The environments `environment` inherits from, starting with itself
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InheritError::MissingEnvironment`] if an environment in the chain
+    /// uses one that isn't defined, or [`InheritError::Loop`] if the chain comes
+    /// back to an environment already in it.
+    fn use_chain(&self, environment: &str) -> Result<Vec<String>, InheritError> {
+        let mut chain = vec![environment.to_string()];
+        let mut current = environment;
+
+        while let Some(uses) = self
+            .environments
+            .get(current)
+            .and_then(|env_config| env_config.uses.as_deref())
+        {
+            if chain.iter().any(|name| name == uses) {
+                chain.push(uses.to_string());
+                return Err(InheritError::Loop { chain });
+            }
+            if !self.environments.contains_key(uses) {
+                return Err(InheritError::MissingEnvironment {
+                    environment: current.to_string(),
+                    uses: uses.to_string(),
+                });
+            }
+            chain.push(uses.to_string());
+            current = uses;
+        }
+
+        Ok(chain)
+    }
+
+    fn apply_defaults(&self, env_config: &mut EnvironmentConfig) {
+        if env_config.check.is_none() {
+            env_config.check.clone_from(&self.check);
+        }
+        if env_config.dependencies.is_none() && !self.dependencies.is_empty() {
+            env_config.dependencies = Some(self.dependencies.clone());
+        }
+    }
+
+    /// Environments left out of the package because their `use:` chain couldn't
+    /// be followed, with the reason
+    #[must_use]
+    pub fn inheritance_errors(&self) -> &BTreeMap<String, InheritError> {
+        &self.inheritance_errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(yaml: &str) -> Package {
+        let mut package: Package = serde_yaml::from_str(yaml).unwrap();
+        package.resolve_environments();
+        package
+    }
+
+    #[test]
+    fn test_use_reuses_another_environment() {
+        let package = load(
+            r#"
+name: ripgrep
+version: 1.0.0
+environments:
+  ubuntu:
+    install: sudo apt install -y ripgrep
+    check: which rg
+    dependencies: [apt-update]
+  debian:
+    use: ubuntu
+  pop-os:
+    use: debian
+    install: sudo apt install -y ripgrep-pop
+"#,
+        );
+
+        let debian = &package.environments()["debian"];
+        assert_eq!(debian.install().to_string(), "sudo apt install -y ripgrep");
+        assert_eq!(debian.check(), Some("which rg"));
+        assert_eq!(debian.dependencies(), ["apt-update"]);
+
+        let pop_os = &package.environments()["pop-os"];
+        assert_eq!(
+            pop_os.install().to_string(),
+            "sudo apt install -y ripgrep-pop"
+        );
+        assert_eq!(pop_os.check(), Some("which rg"));
+        assert!(package.inheritance_errors().is_empty());
+    }
+
+    #[test]
+    fn test_package_defaults() {
+        let package = load(
+            r#"
+name: ripgrep
+version: 1.0.0
+check: which rg
+dependencies: [homebrew]
+environments:
+  macos:
+    install: brew install ripgrep
+  linux:
+    install: cargo install ripgrep
+    check: test -x ~/.cargo/bin/rg
+    dependencies: []
+"#,
+        );
+
+        let macos = &package.environments()["macos"];
+        assert_eq!(macos.check(), Some("which rg"));
+        assert_eq!(macos.dependencies(), ["homebrew"]);
+
+        let linux = &package.environments()["linux"];
+        assert_eq!(linux.check(), Some("test -x ~/.cargo/bin/rg"));
+        assert!(linux.dependencies().is_empty());
+    }
+
+    #[test]
+    fn test_used_environment_wins_over_defaults() {
+        let package = load(
+            r#"
+name: ripgrep
+version: 1.0.0
+check: which rg
+environments:
+  ubuntu:
+    install: sudo apt install -y ripgrep
+    check: dpkg -s ripgrep
+  debian:
+    use: ubuntu
+"#,
+        );
+
+        assert_eq!(
+            package.environments()["debian"].check(),
+            Some("dpkg -s ripgrep")
+        );
+    }
+
+    #[test]
+    fn test_broken_use_chains() {
+        let package = load(
+            r#"
+name: ripgrep
+version: 1.0.0
+environments:
+  ubuntu:
+    install: sudo apt install -y ripgrep
+  debian:
+    use: ubunt
+  a:
+    use: b
+  b:
+    use: a
+"#,
+        );
+
+        assert_eq!(
+            package.environments().keys().collect::<Vec<_>>(),
+            ["ubuntu"]
+        );
+        assert_eq!(
+            package.inheritance_errors()["debian"],
+            InheritError::MissingEnvironment {
+                environment: "debian".to_string(),
+                uses: "ubunt".to_string(),
+            }
+        );
+        assert_eq!(
+            package.inheritance_errors()["a"],
+            InheritError::Loop {
+                chain: vec!["a".to_string(), "b".to_string(), "a".to_string()],
+            }
+        );
+    }
+}
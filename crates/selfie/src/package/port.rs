@@ -261,7 +261,7 @@ pub enum PackageError {
         /// File size for debugging
         file_size_bytes: u64,
         #[source]
-        source: PackageParseError,
+        source: Box<PackageParseError>,
     },
 
     /// The requested environment is not configured for this package
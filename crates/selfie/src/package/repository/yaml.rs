@@ -64,10 +64,10 @@ impl<F: FileSystem> YamlPackageRepository<F> {
         for path in entries {
             *files_examined += 1;
 
-            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                if file_name == format!("{name}.yml") || file_name == format!("{name}.yaml") {
-                    matching_files.push(path);
-                }
+            if let Some(file_name) = path.file_name().and_then(|n| n.to_str())
+                && (file_name == format!("{name}.yml") || file_name == format!("{name}.yaml"))
+            {
+                matching_files.push(path);
             }
         }
 
@@ -97,6 +97,7 @@ impl<F: FileSystem> YamlPackageRepository<F> {
                 source: Arc::new(e),
             })?;
         package.path = path.to_path_buf();
+        package.resolve_environments();
 
         Ok(package)
     }
@@ -150,7 +151,7 @@ impl<F: FileSystem> PackageRepository for YamlPackageRepository<F> {
                     packages_path: self.package_dir.clone(),
                     failed_file: package_file.clone(),
                     file_size_bytes: file_size,
-                    source,
+                    source: Box::new(source),
                 })
             })?;
 
@@ -599,7 +600,7 @@ mod tests {
         // Mock invalid YAML content
         let invalid_yaml = "invalid: yaml: content: [";
 
-        fs.mock_list_directory(package_dir.clone(), &[package_path.clone()]);
+        fs.mock_list_directory(package_dir.clone(), std::slice::from_ref(&package_path));
         fs.mock_read_file(package_path.clone(), invalid_yaml);
 
         let repo = YamlPackageRepository::new(fs, package_dir.clone());
@@ -616,7 +617,7 @@ mod tests {
                 } => {
                     assert_eq!(name, "invalid");
                     assert_eq!(packages_path, package_dir);
-                    match source {
+                    match *source {
                         PackageParseError::YamlParse {
                             package_path: error_path,
                             ..
@@ -0,0 +1,147 @@
+//! Install commands
+//!
+//! An environment's `install` is usually one command, but setup that takes
+//! several commands reads better as a list than as a long `&&` chain:
+//!
+//! ```yaml
+//! environments:
+//!   ubuntu:
+//!     install:
+//!       - curl -fsSLO https://example.com/tool.tar.gz
+//!       - tar -xzf tool.tar.gz -C ~/.local
+//!       - rm tool.tar.gz
+//! ```
+//!
+//! The commands run in order, each as its own step, and the first one that fails
+//! stops the rest.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The commands that install a package, in the order they run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "ScriptDef", into = "ScriptDef")]
+pub struct InstallScript {
+    commands: Vec<String>,
+}
+
+impl InstallScript {
+    /// The commands, in the order they run
+    #[must_use]
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
+    /// Whether there's more than one command, so each is reported as a numbered step
+    #[must_use]
+    pub fn is_multi_step(&self) -> bool {
+        self.commands.len() > 1
+    }
+
+    /// Whether there's no command to run
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.commands.iter().all(String::is_empty)
+    }
+
+    /// The name of the command at `index` in validation issues, e.g. `install` or
+    /// `install[1]`
+    pub(crate) fn field(&self, index: usize) -> String {
+        if self.is_multi_step() {
+            format!("install[{index}]")
+        } else {
+            "install".to_string()
+        }
+    }
+}
+
+impl fmt::Display for InstallScript {
+    /// Joins the commands with `&&`, which runs them the same way
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.commands.join(" && "))
+    }
+}
+
+impl From<String> for InstallScript {
+    fn from(command: String) -> Self {
+        Self {
+            commands: vec![command],
+        }
+    }
+}
+
+impl From<&str> for InstallScript {
+    fn from(command: &str) -> Self {
+        command.to_string().into()
+    }
+}
+
+impl From<Vec<String>> for InstallScript {
+    fn from(commands: Vec<String>) -> Self {
+        Self { commands }
+    }
+}
+
+/// How an [`InstallScript`] is written in a package file
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ScriptDef {
+    Single(String),
+    Steps(Vec<String>),
+}
+
+impl From<ScriptDef> for InstallScript {
+    fn from(def: ScriptDef) -> Self {
+        match def {
+            ScriptDef::Single(command) => command.into(),
+            ScriptDef::Steps(commands) => commands.into(),
+        }
+    }
+}
+
+impl From<InstallScript> for ScriptDef {
+    fn from(mut script: InstallScript) -> Self {
+        if script.is_multi_step() {
+            Self::Steps(script.commands)
+        } else {
+            Self::Single(script.commands.pop().unwrap_or_default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_script_forms() {
+        let single: InstallScript = serde_yaml::from_str("brew install ripgrep").unwrap();
+        assert_eq!(single.commands(), ["brew install ripgrep"]);
+        assert!(!single.is_multi_step());
+        assert_eq!(single.field(0), "install");
+
+        let steps: InstallScript = serde_yaml::from_str("- make\n- make install").unwrap();
+        assert_eq!(steps.commands(), ["make", "make install"]);
+        assert_eq!(steps.to_string(), "make && make install");
+        assert_eq!(steps.field(1), "install[1]");
+
+        // Each form is written back the way it was read
+        assert_eq!(
+            serde_yaml::to_string(&single).unwrap(),
+            "brew install ripgrep\n"
+        );
+        assert!(
+            serde_yaml::to_string(&steps)
+                .unwrap()
+                .starts_with("- make\n")
+        );
+    }
+
+    #[test]
+    fn test_install_script_is_empty() {
+        assert!(InstallScript::default().is_empty());
+        assert!(InstallScript::from("").is_empty());
+        assert!(!InstallScript::from(vec![String::new(), "make".to_string()]).is_empty());
+    }
+}
@@ -9,14 +9,19 @@
 //! command execution, and event streaming to provide a complete package management experience.
 
 mod check;
+mod hooks;
 mod info;
 mod install;
 mod list;
+mod rollback;
 mod steps;
+mod uninstall;
+mod upgrade;
 mod validate;
 
 use std::path::PathBuf;
 
+use thiserror::Error;
 use tokio::sync::mpsc;
 use tracing::instrument;
 
@@ -26,9 +31,15 @@ use super::{
         metadata::OperationType,
     },
     port::PackageRepository,
+    state::StateStore,
 };
 
-use crate::{commands::runner::CommandRunner, config::AppConfig, package::port::PackageError};
+use crate::{
+    commands::runner::{CommandError, CommandRunner},
+    config::AppConfig,
+    fs::FileSystem,
+    package::port::PackageError,
+};
 
 /// Helper for tracking progress through operation steps
 ///
@@ -73,6 +84,14 @@ impl ProgressTracker {
             .await;
     }
 
+    /// Add steps to the operation once more of its work is known
+    ///
+    /// Some operations (e.g. installing a package's dependencies) only learn how
+    /// many steps they need part way through.
+    pub(crate) fn add_steps(&mut self, steps: u32) {
+        self.total_steps += steps;
+    }
+
     /// Get the current step number (1-based for display)
     pub(crate) fn current_step(&self) -> u32 {
         self.current_step
@@ -84,6 +103,126 @@ impl ProgressTracker {
     }
 }
 
+/// Options that change how [`PackageService::install`] behaves
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InstallOptions {
+    /// Skip the `check` command and always run the install command, even if the
+    /// package already appears to be installed.
+    pub force: bool,
+
+    /// Only work out what would be installed: resolve dependencies and run the
+    /// read-only `check` commands, then report an install plan instead of running
+    /// any install commands.
+    pub dry_run: bool,
+
+    /// When a package's install command, or one of its `pre_install` or
+    /// `post_install` commands, fails, run the package's `rollback` commands to
+    /// undo whatever the failed install left behind.
+    pub rollback_on_failure: bool,
+}
+
+/// The lifecycle hooks a package can run around its install command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallHook {
+    /// The `pre_install` commands, run before the install command
+    PreInstall,
+    /// The `post_install` commands, run after the install command succeeds
+    PostInstall,
+}
+
+impl InstallHook {
+    /// The name of the hook's field in a package file
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::PreInstall => "pre_install",
+            Self::PostInstall => "post_install",
+        }
+    }
+}
+
+impl std::fmt::Display for InstallHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Errors found while installing a package, apart from its install command failing
+#[derive(Error, Debug, Clone)]
+pub enum InstallError {
+    /// The install command exited successfully, but the package's `check` command
+    /// still fails, which usually means the install recipe is broken
+    #[error(
+        "Package `{package_name}` install command succeeded but package not detected: check command `{check_command}` exited with code {exit_code}"
+    )]
+    NotDetected {
+        package_name: String,
+        check_command: String,
+        exit_code: i32,
+    },
+
+    /// The `check` command couldn't be run to verify the install
+    #[error("Unable to verify that package `{package_name}` was installed: {source}")]
+    VerificationFailed {
+        package_name: String,
+        #[source]
+        source: Box<CommandError>,
+    },
+
+    /// One command of a package's multi-step `install` failed, so the commands
+    /// after it never ran
+    #[error("Package `{package_name}` install step {step}/{steps} failed at `{command}`: {reason}")]
+    StepFailed {
+        package_name: String,
+        step: usize,
+        steps: usize,
+        command: String,
+        reason: String,
+    },
+
+    /// One of the package's `pre_install` or `post_install` commands failed
+    #[error("Package `{package_name}` {hook} hook failed at `{command}`: {reason}")]
+    HookFailed {
+        package_name: String,
+        hook: InstallHook,
+        command: String,
+        reason: String,
+    },
+
+    /// The install command failed and so did one of the package's `rollback`
+    /// commands, so the machine may be left partly configured
+    #[error(
+        "Rollback of package `{package_name}` failed at `{command}`: {reason}; the machine may be left partially configured"
+    )]
+    RollbackFailed {
+        package_name: String,
+        command: String,
+        reason: String,
+    },
+}
+
+/// Errors found after a package's uninstall command has run
+#[derive(Error, Debug, Clone)]
+pub enum UninstallError {
+    /// The uninstall command exited successfully, but the package's `check`
+    /// command still passes, so the package is still on the machine
+    #[error(
+        "Package `{package_name}` uninstall command succeeded but package is still detected: check command `{check_command}` still succeeds"
+    )]
+    StillDetected {
+        package_name: String,
+        check_command: String,
+    },
+
+    /// The `check` command couldn't be run to confirm the package is gone
+    #[error("Unable to verify that package `{package_name}` was uninstalled: {source}")]
+    VerificationFailed {
+        package_name: String,
+        #[source]
+        source: Box<CommandError>,
+    },
+}
+
 /// Primary port for package operations (Hexagonal Architecture)
 ///
 /// This trait defines the main interface for all package management operations
@@ -111,20 +250,131 @@ pub trait PackageService: Send + Sync {
     /// An event stream that will emit progress events and the final check result
     async fn check(&self, package_name: &str) -> EventStream;
 
+    /// Check every package that supports the current environment
+    ///
+    /// Runs each package's check command in turn. If the config's `stop_on_error`
+    /// is set, the sweep stops at the first package that can't be checked;
+    /// otherwise every package is checked and failures are collected.
+    ///
+    /// # Returns
+    ///
+    /// An event stream that emits a check result for each package, followed by a
+    /// summary of which packages are installed, missing, or failed to check
+    async fn check_all(&self) -> EventStream;
+
+    /// Check several packages in one operation
+    ///
+    /// Works like [`PackageService::check_all`] with the named packages. Packages
+    /// that don't support the current environment count as failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `package_names` - Names of the packages to check
+    ///
+    /// # Returns
+    ///
+    /// An event stream that emits a check result for each package, followed by a
+    /// summary of which packages are installed, missing, or failed to check
+    async fn check_packages(&self, package_names: &[String]) -> EventStream;
+
     /// Install a package using its configured installation method
     ///
     /// Executes the package's installation command for the current environment.
     /// This includes dependency resolution, command validation, and installation
-    /// execution with progress tracking.
+    /// execution with progress tracking. If the package's `check` command shows
+    /// it's already installed, nothing is run unless `options.force` is set.
+    ///
+    /// With `options.dry_run`, only the read-only `check` commands are run and the
+    /// stream carries an [`InstallPlanData`](super::event::InstallPlanData)
+    /// describing what would be installed, in what order, and with which commands.
     ///
     /// # Arguments
     ///
     /// * `package_name` - Name of the package to install
+    /// * `options` - Options that change how installation behaves
     ///
     /// # Returns
     ///
     /// An event stream that will emit progress events and the final installation result
-    async fn install(&self, package_name: &str) -> EventStream;
+    async fn install(&self, package_name: &str, options: InstallOptions) -> EventStream;
+
+    /// Install several packages in one operation
+    ///
+    /// The dependency plans of all the packages are merged, so dependencies they
+    /// share are only checked and installed once. Requested packages that are
+    /// already installed are skipped unless `options.force` is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `package_names` - Names of the packages to install
+    /// * `options` - Options that change how installation behaves
+    ///
+    /// # Returns
+    ///
+    /// An event stream that will emit progress events for every package involved
+    /// and a final result summarizing what was installed
+    async fn install_packages(
+        &self,
+        package_names: &[String],
+        options: InstallOptions,
+    ) -> EventStream;
+
+    /// Install every package that supports the current environment
+    ///
+    /// Works like [`PackageService::install_packages`] with every valid package in
+    /// the repository that has a configuration for the current environment.
+    ///
+    /// # Returns
+    ///
+    /// An event stream that will emit progress events for every package involved
+    /// and a final result summarizing what was installed
+    async fn install_all(&self, options: InstallOptions) -> EventStream;
+
+    /// Remove a package from the machine using its configured uninstall command
+    ///
+    /// Runs the package's check command first, and does nothing if the package
+    /// isn't installed. Warns, but carries on, when other installed packages
+    /// depend on it. After the uninstall command succeeds, the check command is
+    /// run again to confirm the package is gone.
+    ///
+    /// # Arguments
+    ///
+    /// * `package_name` - Name of the package to uninstall
+    ///
+    /// # Returns
+    ///
+    /// An event stream that will emit progress events and the final uninstall result
+    async fn uninstall(&self, package_name: &str) -> EventStream;
+
+    /// Upgrade an installed package using its configured update command
+    ///
+    /// Packages without an `update` command for the current environment are
+    /// reported as not upgradable rather than being reinstalled. Packages that
+    /// aren't installed are left alone. After the update command succeeds, the
+    /// check command is run again to make sure the package still works.
+    ///
+    /// # Arguments
+    ///
+    /// * `package_name` - Name of the package to upgrade
+    ///
+    /// # Returns
+    ///
+    /// An event stream that will emit progress events, an
+    /// [`UpgradeResultData`](super::event::UpgradeResultData), and the final
+    /// upgrade result
+    async fn upgrade(&self, package_name: &str) -> EventStream;
+
+    /// Upgrade every installed package that supports the current environment
+    ///
+    /// Runs each package's update command in turn. If the config's
+    /// `stop_on_error` is set, upgrades stop at the first package that fails.
+    ///
+    /// # Returns
+    ///
+    /// An event stream that emits an upgrade result for each package, followed by
+    /// a summary of which packages were upgraded, not upgradable, not installed,
+    /// failed, or skipped
+    async fn upgrade_all(&self) -> EventStream;
 
     /// Get detailed information about a package
     ///
@@ -223,19 +473,25 @@ pub trait PackageService: Send + Sync {
 /// The implementation uses dependency injection through generic parameters to
 /// support different storage backends and command execution strategies.
 #[derive(Debug)]
-pub struct PackageServiceImpl<R, CR> {
+pub struct PackageServiceImpl<R, CR, SS, FS> {
     /// Repository for loading and managing package definitions
     package_repository: R,
     /// Command runner for executing system commands
     command_runner: CR,
+    /// Store recording what selfie has installed
+    state_store: SS,
+    /// File system for evaluating `when:` conditions
+    file_system: FS,
     /// Application configuration including environment and settings
     config: AppConfig,
 }
 
-impl<R, CR> PackageServiceImpl<R, CR>
+impl<R, CR, SS, FS> PackageServiceImpl<R, CR, SS, FS>
 where
     R: PackageRepository + Clone + 'static,
     CR: CommandRunner + Clone + 'static,
+    SS: StateStore + Clone + 'static,
+    FS: FileSystem + Clone + 'static,
 {
     /// Create a new package service instance
     ///
@@ -243,11 +499,21 @@ where
     ///
     /// * `package_repository` - Repository implementation for package storage
     /// * `command_runner` - Command runner implementation for executing system commands
+    /// * `state_store` - State store implementation for recording installs
+    /// * `file_system` - File system implementation for evaluating `when:` conditions
     /// * `config` - Application configuration
-    pub fn new(package_repository: R, command_runner: CR, config: AppConfig) -> Self {
+    pub fn new(
+        package_repository: R,
+        command_runner: CR,
+        state_store: SS,
+        file_system: FS,
+        config: AppConfig,
+    ) -> Self {
         Self {
             package_repository,
             command_runner,
+            state_store,
+            file_system,
             config,
         }
     }
@@ -307,11 +573,13 @@ where
         handler: F,
     ) -> EventStream
     where
-        F: FnOnce(R, CR, AppConfig, EventSender, ProgressTracker) -> Fut + Send + 'static,
+        F: FnOnce(R, CR, SS, FS, AppConfig, EventSender, ProgressTracker) -> Fut + Send + 'static,
         Fut: std::future::Future<Output = OperationResult> + Send,
     {
         let repo = self.package_repository.clone();
         let command_runner = self.command_runner.clone();
+        let state_store = self.state_store.clone();
+        let file_system = self.file_system.clone();
         let config = self.config.clone();
         let package_name = package_name.to_string();
 
@@ -330,11 +598,57 @@ where
                 .await;
 
             let progress = ProgressTracker::new(total_steps);
-            let result = handler(repo, command_runner, config, sender.clone(), progress).await;
+            let result = handler(
+                repo,
+                command_runner,
+                state_store,
+                file_system,
+                config,
+                sender.clone(),
+                progress,
+            )
+            .await;
             sender.send_completed(result).await;
         })
     }
 
+    /// Start installing a selection of packages as one operation
+    fn install_selection(
+        &self,
+        package_name: &str,
+        selection: steps::PackageSelection,
+        options: InstallOptions,
+    ) -> EventStream {
+        self.execute_operation_with_deps(
+            OperationType::PackageInstall,
+            package_name,
+            OperationContext::default(),
+            // load packages + resolve dependencies + summary or plan; steps for
+            // checking and installing each package are added once they're known
+            3,
+            move |repo,
+                  command_runner,
+                  state_store,
+                  file_system,
+                  config,
+                  sender,
+                  mut progress| async move {
+                install::handle_install_many(
+                    selection,
+                    options,
+                    &repo,
+                    &config,
+                    &command_runner,
+                    &file_system,
+                    &state_store,
+                    &sender,
+                    &mut progress,
+                )
+                .await
+            },
+        )
+    }
+
     /// Execute an operation with simplified event handling
     ///
     /// A simpler version of operation execution that doesn't require full dependency
@@ -389,10 +703,12 @@ where
 }
 
 #[async_trait::async_trait]
-impl<R, CR> PackageService for PackageServiceImpl<R, CR>
+impl<R, CR, SS, FS> PackageService for PackageServiceImpl<R, CR, SS, FS>
 where
     R: PackageRepository + Clone + std::fmt::Debug + Send + Sync + 'static,
     CR: CommandRunner + Clone + std::fmt::Debug + Send + Sync + 'static,
+    SS: StateStore + Clone + std::fmt::Debug + 'static,
+    FS: FileSystem + Clone + std::fmt::Debug + 'static,
 {
     /// Check if a package is already installed
     ///
@@ -423,12 +739,100 @@ where
             package_name,
             OperationContext::default(),
             3, // Load package + check environment + run check command
-            move |repo, command_runner, config, sender, mut progress| async move {
+            move |repo,
+                  command_runner,
+                  _state_store,
+                  file_system,
+                  config,
+                  sender,
+                  mut progress| async move {
                 check::handle_check(
                     &package_name_owned,
                     &repo,
                     &config,
                     &command_runner,
+                    &file_system,
+                    &sender,
+                    &mut progress,
+                )
+                .await
+            },
+        )
+    }
+
+    /// Check every package that supports the current environment
+    ///
+    /// The operation consists of:
+    /// 1. Loading every package definition and keeping those for the current
+    ///    environment
+    /// 2. Running each package's check command, stopping at the first failure
+    ///    when `stop_on_error` is set
+    /// 3. Summarizing the results
+    ///
+    /// # Returns
+    ///
+    /// An event stream that emits:
+    /// - A check result for each package
+    /// - A summary of installed, missing, failed, and skipped packages
+    /// - Success if every package is installed, failure otherwise
+    #[instrument]
+    async fn check_all(&self) -> EventStream {
+        self.execute_operation_with_deps(
+            OperationType::PackageCheck,
+            "", // No specific package when checking all of them
+            OperationContext::default(),
+            // Load packages + summarize; one step per package is added once the
+            // packages are known
+            2,
+            move |repo,
+                  command_runner,
+                  _state_store,
+                  file_system,
+                  config,
+                  sender,
+                  mut progress| async move {
+                check::handle_check_many(
+                    &steps::PackageSelection::All,
+                    &repo,
+                    &config,
+                    &command_runner,
+                    &file_system,
+                    &sender,
+                    &mut progress,
+                )
+                .await
+            },
+        )
+    }
+
+    /// Check several named packages in one operation
+    ///
+    /// Runs the same steps as [`PackageService::check_all`] for the named
+    /// packages. A name that can't be loaded fails the operation before anything
+    /// is checked.
+    #[instrument]
+    async fn check_packages(&self, package_names: &[String]) -> EventStream {
+        let selection = steps::PackageSelection::Named(package_names.to_vec());
+        self.execute_operation_with_deps(
+            OperationType::PackageCheck,
+            &package_names.join(", "),
+            OperationContext::default(),
+            // Load packages + summarize; one step per package is added once the
+            // packages are known
+            2,
+            move |repo,
+                  command_runner,
+                  _state_store,
+                  file_system,
+                  config,
+                  sender,
+                  mut progress| async move {
+                check::handle_check_many(
+                    &selection,
+                    &repo,
+                    &config,
+                    &command_runner,
+                    &file_system,
                     &sender,
                     &mut progress,
                 )
@@ -446,34 +850,248 @@ where
     /// The installation operation consists of:
     /// 1. Loading the package definition from the repository
     /// 2. Validating the current environment configuration
-    /// 3. Resolving and checking dependencies
-    /// 4. Executing the package's installation command
-    /// 5. Verifying the installation was successful
+    /// 3. Resolving the package's dependencies for the current environment
+    /// 4. Checking and installing each dependency, in dependency order
+    /// 5. Running the package's check command, stopping early if it's already
+    ///    installed (skipped when `options.force` is set)
+    /// 6. Running the package's `pre_install` commands
+    /// 7. Executing the package's installation command
+    /// 8. Running the package's `post_install` commands
+    /// 9. Re-running the package's check command to verify the installation,
+    ///    failing with [`InstallError::NotDetected`] if it still fails
+    ///
+    /// A failing `pre_install` or `post_install` command stops the install with
+    /// [`InstallError::HookFailed`]. Like a failing install command, it's rolled
+    /// back when `options.rollback_on_failure` is set.
+    ///
+    /// For a dry run, steps 4 through 9 are replaced by checking the package and
+    /// each dependency and sending the resulting install plan.
     ///
     /// # Arguments
     ///
     /// * `package_name` - Name of the package to install
+    /// * `options` - Options that change how installation behaves
     ///
     /// # Returns
     ///
     /// An event stream that emits:
     /// - Progress events for each installation step
-    /// - Success/failure result with installation details
+    /// - Success/failure result with installation details, or
+    ///   [`OperationResult::AlreadyInstalled`] if there was nothing to do
     /// - Error events if the installation fails
     #[instrument]
-    async fn install(&self, package_name: &str) -> EventStream {
+    async fn install(&self, package_name: &str, options: InstallOptions) -> EventStream {
         let package_name_owned = package_name.to_string();
         self.execute_operation_with_deps(
             OperationType::PackageInstall,
             package_name,
             OperationContext::default(),
-            5, // fetch_package + find_env + get_command + execute_command + result processing
-            move |repo, command_runner, config, sender, mut progress| async move {
+            // fetch_package + find_env + resolve_dependencies + check, then either
+            // get_command + execute_command + verification, or building the plan
+            // for a dry run; one more step per dependency, per hook command and per
+            // extra install command is added once they're known
+            if options.dry_run { 5 } else { 7 },
+            move |repo,
+                  command_runner,
+                  state_store,
+                  file_system,
+                  config,
+                  sender,
+                  mut progress| async move {
                 install::handle_install(
+                    &package_name_owned,
+                    options,
+                    &repo,
+                    &config,
+                    &command_runner,
+                    &file_system,
+                    &state_store,
+                    &sender,
+                    &mut progress,
+                )
+                .await
+            },
+        )
+    }
+
+    /// Install several packages in one operation
+    ///
+    /// The operation consists of:
+    /// 1. Loading every requested package definition
+    /// 2. Resolving one dependency graph for all of them
+    /// 3. Running each requested package's check command (skipped when
+    ///    `options.force` is set)
+    /// 4. Checking and installing everything still needed, dependencies first and
+    ///    independent packages in parallel
+    /// 5. Summarizing what was installed
+    ///
+    /// For a dry run, step 4 only checks the dependencies and an install plan is
+    /// sent instead.
+    #[instrument]
+    async fn install_packages(
+        &self,
+        package_names: &[String],
+        options: InstallOptions,
+    ) -> EventStream {
+        let selection = steps::PackageSelection::Named(package_names.to_vec());
+        self.install_selection(&package_names.join(", "), selection, options)
+    }
+
+    /// Install every package that supports the current environment
+    ///
+    /// Runs the same steps as [`PackageService::install_packages`] for every valid
+    /// package that has a configuration for the current environment.
+    #[instrument]
+    async fn install_all(&self, options: InstallOptions) -> EventStream {
+        // No specific package when installing all of them
+        self.install_selection("", steps::PackageSelection::All, options)
+    }
+
+    /// Remove a package from the machine using its configured uninstall command
+    ///
+    /// The uninstall operation consists of:
+    /// 1. Loading the package definition from the repository
+    /// 2. Validating the current environment configuration
+    /// 3. Warning about installed packages that depend on this one
+    /// 4. Running the package's check command, stopping early if it's not
+    ///    installed
+    /// 5. Executing the package's uninstall command
+    /// 6. Re-running the package's check command to confirm the package is gone,
+    ///    failing with [`UninstallError::StillDetected`] if it still passes
+    ///
+    /// # Arguments
+    ///
+    /// * `package_name` - Name of the package to uninstall
+    ///
+    /// # Returns
+    ///
+    /// An event stream that emits:
+    /// - Progress events for each uninstall step
+    /// - Success/failure result, or [`OperationResult::NotInstalled`] if there
+    ///   was nothing to do
+    /// - Error events if the uninstall fails
+    #[instrument]
+    async fn uninstall(&self, package_name: &str) -> EventStream {
+        let package_name_owned = package_name.to_string();
+        self.execute_operation_with_deps(
+            OperationType::PackageUninstall,
+            package_name,
+            OperationContext::default(),
+            // fetch_package + find_env + dependents + check + get_command +
+            // execute_command + verification
+            7,
+            move |repo,
+                  command_runner,
+                  state_store,
+                  file_system,
+                  config,
+                  sender,
+                  mut progress| async move {
+                uninstall::handle_uninstall(
+                    &package_name_owned,
+                    &repo,
+                    &config,
+                    &command_runner,
+                    &file_system,
+                    &state_store,
+                    &sender,
+                    &mut progress,
+                )
+                .await
+            },
+        )
+    }
+
+    /// Upgrade an installed package using its configured update command
+    ///
+    /// The upgrade operation consists of:
+    /// 1. Loading the package definition from the repository
+    /// 2. Validating the current environment configuration
+    /// 3. Finding the package's update command, stopping early if it has none
+    /// 4. Running the package's check command, stopping early if it's not
+    ///    installed
+    /// 5. Executing the package's update command
+    /// 6. Re-running the package's check command to verify the upgrade
+    ///
+    /// # Arguments
+    ///
+    /// * `package_name` - Name of the package to upgrade
+    ///
+    /// # Returns
+    ///
+    /// An event stream that emits:
+    /// - Progress events for each upgrade step
+    /// - The package's upgrade result
+    /// - Success if the package was upgraded, [`OperationResult::NotInstalled`]
+    ///   if it isn't installed, and failure otherwise (including when it has no
+    ///   update command)
+    #[instrument]
+    async fn upgrade(&self, package_name: &str) -> EventStream {
+        let package_name_owned = package_name.to_string();
+        self.execute_operation_with_deps(
+            OperationType::PackageUpgrade,
+            package_name,
+            OperationContext::default(),
+            // fetch_package + find_env + the steps to upgrade the package itself
+            2 + upgrade::STEPS_PER_UPGRADE,
+            move |repo,
+                  command_runner,
+                  _state_store,
+                  file_system,
+                  config,
+                  sender,
+                  mut progress| async move {
+                upgrade::handle_upgrade(
                     &package_name_owned,
                     &repo,
                     &config,
                     &command_runner,
+                    &file_system,
+                    &sender,
+                    &mut progress,
+                )
+                .await
+            },
+        )
+    }
+
+    /// Upgrade every installed package that supports the current environment
+    ///
+    /// The operation consists of:
+    /// 1. Loading every package definition and keeping those for the current
+    ///    environment
+    /// 2. Upgrading each package, stopping at the first failure when
+    ///    `stop_on_error` is set
+    /// 3. Summarizing the results
+    ///
+    /// # Returns
+    ///
+    /// An event stream that emits:
+    /// - An upgrade result for each package
+    /// - A summary of upgraded, not upgradable, not installed, failed, and
+    ///   skipped packages
+    /// - Success unless an upgrade failed or packages were skipped
+    #[instrument]
+    async fn upgrade_all(&self) -> EventStream {
+        self.execute_operation_with_deps(
+            OperationType::PackageUpgrade,
+            "", // No specific package when upgrading all of them
+            OperationContext::default(),
+            // Load packages + summarize; one step per package is added once the
+            // packages are known
+            2,
+            move |repo,
+                  command_runner,
+                  _state_store,
+                  file_system,
+                  config,
+                  sender,
+                  mut progress| async move {
+                upgrade::handle_upgrade_all(
+                    &repo,
+                    &config,
+                    &command_runner,
+                    &file_system,
                     &sender,
                     &mut progress,
                 )
@@ -524,7 +1142,13 @@ where
             package_name,
             context,
             3, // load_package + validate_package + result processing
-            move |repo, command_runner, config, sender, mut progress| async move {
+            move |repo,
+                  command_runner,
+                  _state_store,
+                  _file_system,
+                  config,
+                  sender,
+                  mut progress| async move {
                 validate::handle_validate(
                     &package_name_owned,
                     &repo,
@@ -560,15 +1184,31 @@ where
     /// - Package definition files cannot be read
     /// - File system operations fail
     async fn list(&self) -> Result<EventStream, PackageError> {
-        Ok(self.execute_operation_with_deps(
-            OperationType::PackageList,
-            "", // No specific package for list operation
-            OperationContext::default(),
-            3, // Load packages + process + finalize
-            move |repo, command_runner, config, sender, mut progress| async move {
-                list::handle_list(&repo, &config, &command_runner, &sender, &mut progress).await
-            },
-        ))
+        Ok(
+            self.execute_operation_with_deps(
+                OperationType::PackageList,
+                "", // No specific package for list operation
+                OperationContext::default(),
+                3, // Load packages + process + finalize
+                move |repo,
+                      command_runner,
+                      state_store,
+                      _file_system,
+                      config,
+                      sender,
+                      mut progress| async move {
+                    list::handle_list(
+                        &repo,
+                        &config,
+                        &command_runner,
+                        &state_store,
+                        &sender,
+                        &mut progress,
+                    )
+                    .await
+                },
+            ),
+        )
     }
 
     /// Get detailed information about a package
@@ -599,23 +1239,32 @@ where
     /// - File system access fails
     async fn info(&self, package_name: &str) -> Result<EventStream, PackageError> {
         let package_name_owned = package_name.to_string();
-        Ok(self.execute_operation_with_deps(
-            OperationType::PackageInfo,
-            package_name,
-            OperationContext::default(),
-            3, // Load package + gather info + check status
-            move |repo, command_runner, config, sender, mut progress| async move {
-                info::handle_info(
-                    &package_name_owned,
-                    &repo,
-                    &config,
-                    &command_runner,
-                    &sender,
-                    &mut progress,
-                )
-                .await
-            },
-        ))
+        Ok(
+            self.execute_operation_with_deps(
+                OperationType::PackageInfo,
+                package_name,
+                OperationContext::default(),
+                3, // Load package + gather info + check status
+                move |repo,
+                      command_runner,
+                      state_store,
+                      _file_system,
+                      config,
+                      sender,
+                      mut progress| async move {
+                    info::handle_info(
+                        &package_name_owned,
+                        &repo,
+                        &config,
+                        &command_runner,
+                        &state_store,
+                        &sender,
+                        &mut progress,
+                    )
+                    .await
+                },
+            ),
+        )
     }
 
     /// Create a new package definition file
@@ -1,25 +1,34 @@
 //! Helps break down the pieces of running the `package check` command.
 
+use std::time::Duration;
+
 use crate::{
     commands::runner::CommandRunner,
     config::AppConfig,
+    fs::FileSystem,
     package::{
-        event::{CheckResult, CheckResultData, EventSender, OperationResult},
+        CommandKind, EnvironmentConfig,
+        event::{CheckResult, CheckResultData, CheckSummaryData, EventSender, OperationResult},
         port::{PackageRepoError, PackageRepository},
+        version::VersionRequirement,
     },
 };
 
-pub(super) async fn handle_check<PR, CR>(
+use super::steps;
+
+pub(super) async fn handle_check<PR, CR, F>(
     package_name: &str,
     repo: &PR,
     config: &AppConfig,
     command_runner: &CR,
+    file_system: &F,
     sender: &EventSender,
     progress: &mut crate::package::service::ProgressTracker,
 ) -> OperationResult
 where
     PR: PackageRepository + Clone,
     CR: CommandRunner + Clone,
+    F: FileSystem,
 {
     progress.next(sender, "Loading package definition").await;
 
@@ -42,8 +51,23 @@ where
 
     // Step 2: Get environment-specific check command with rich error context
     let current_env = config.environment();
-    let env_config = if let Some(config) = package_blob.package.environments().get(current_env) {
-        config
+    let selected = match package_blob.package.environment_for(config) {
+        Ok(selected) => selected,
+        Err(err) => {
+            let error_msg = format!("Environment configuration error: {err}");
+            sender.send_error(err, &error_msg).await;
+            return OperationResult::Failure(error_msg);
+        }
+    };
+    let env_config = if let Some((selected, env_config)) = selected {
+        if selected != current_env {
+            sender
+                .send_debug(format!(
+                    "Package '{package_name}' has no '{current_env}' environment, using '{selected}'"
+                ))
+                .await;
+        }
+        env_config
     } else {
         use crate::package::port::PackageError;
         let err = Box::new(PackageError::EnvironmentNotFound {
@@ -64,6 +88,27 @@ where
         return OperationResult::Failure(error_msg);
     };
 
+    // There's nothing to check for an environment that doesn't apply to this machine
+    if let Some(reason) = steps::unmet_condition(
+        env_config.when(),
+        command_runner,
+        file_system,
+        config,
+        sender,
+    )
+    .await
+    {
+        sender
+            .send_check_result(CheckResultData {
+                package_name: package_name.to_string(),
+                environment: current_env.to_string(),
+                check_command: env_config.check.clone(),
+                result: CheckResult::Skipped(reason.clone()),
+            })
+            .await;
+        return OperationResult::Skipped(format!("Package '{package_name}' was skipped: {reason}"));
+    }
+
     let check_command = if let Some(check_cmd) = env_config.check.as_ref() {
         check_cmd
     } else {
@@ -112,39 +157,20 @@ where
 
     progress.next(sender, "Running package check command").await;
 
-    // Step 3: Execute the check command
-    let check_result = match command_runner.execute(check_command).await {
-        Ok(output) => {
-            if output.is_success() {
-                sender
-                    .send_debug(format!("Check command output: {}", output.stdout_str()))
-                    .await;
-                CheckResultData {
-                    package_name: package_name.to_string(),
-                    environment: current_env.to_string(),
-                    check_command: Some(check_command.to_string()),
-                    result: CheckResult::Success,
-                }
-            } else {
-                CheckResultData {
-                    package_name: package_name.to_string(),
-                    environment: current_env.to_string(),
-                    check_command: Some(check_command.to_string()),
-                    result: CheckResult::Failed {
-                        stdout: output.stdout_str().to_string(),
-                        stderr: output.stderr_str().to_string(),
-                        exit_code: Some(output.exit_code()),
-                    },
-                }
-            }
-        }
-        Err(err) => CheckResultData {
-            package_name: package_name.to_string(),
-            environment: current_env.to_string(),
-            check_command: Some(check_command.to_string()),
-            result: CheckResult::Error(err.to_string()),
-        },
-    };
+    // Step 3: Execute the check command, then make sure the installed version is
+    // new enough
+    let check_result = run_check_command(
+        command_runner,
+        package_name,
+        current_env,
+        check_command,
+        &env_config,
+        steps::command_timeout(&env_config, CommandKind::Check, config),
+        sender,
+    )
+    .await;
+    let check_result =
+        check_required_version(check_result, &env_config, config, command_runner, sender).await;
 
     // Send structured check result
     sender.send_check_result(check_result.clone()).await;
@@ -160,6 +186,12 @@ where
             );
             OperationResult::Success(success_msg)
         }
+        CheckResult::Outdated {
+            installed_version,
+            required_version,
+        } => OperationResult::Failure(format!(
+            "Package '{package_name}' is installed but outdated: found version {installed_version}, requires {required_version}"
+        )),
         CheckResult::Failed { .. } => {
             let error_msg = format!(
                 "Package '{}' check failed at step {}/{}",
@@ -184,3 +216,260 @@ where
         }
     }
 }
+
+/// Check the packages picked by `selection`
+///
+/// Sends a [`CheckResultData`] for each package as it's checked and a
+/// [`CheckSummaryData`] at the end. Packages whose `when:` conditions aren't met
+/// are recorded as not applicable. Packages that can't be checked count as failed,
+/// including named packages that don't support the current environment; with
+/// [`AppConfig::stop_on_error`] set, the first of those stops the sweep and the
+/// remaining packages are reported as skipped.
+pub(super) async fn handle_check_many<PR, CR, F>(
+    selection: &steps::PackageSelection,
+    repo: &PR,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    sender: &EventSender,
+    progress: &mut crate::package::service::ProgressTracker,
+) -> OperationResult
+where
+    PR: PackageRepository,
+    CR: CommandRunner,
+    F: FileSystem,
+{
+    // Step 1: Load the packages to check
+    let packages = match steps::load_packages(selection, repo, config, sender, progress).await {
+        Ok(packages) => packages,
+        Err(error_msg) => return OperationResult::Failure(error_msg),
+    };
+    let current_env = config.environment();
+    progress.add_steps(packages.len() as u32);
+
+    // Step 2: Check each package
+    let mut summary = CheckSummaryData {
+        environment: current_env.to_string(),
+        ..CheckSummaryData::default()
+    };
+
+    for (i, package) in packages.iter().enumerate() {
+        let package_name = package.name();
+        progress
+            .next(sender, format!("Checking package `{package_name}`"))
+            .await;
+
+        let failed = match package.environment_for(config) {
+            Ok(Some((_, env_config))) => {
+                let unmet = steps::unmet_condition(
+                    env_config.when(),
+                    command_runner,
+                    file_system,
+                    config,
+                    sender,
+                )
+                .await;
+                let check_result = match (unmet, env_config.check.as_deref()) {
+                    (Some(reason), check_command) => CheckResultData {
+                        package_name: package_name.to_string(),
+                        environment: current_env.to_string(),
+                        check_command: check_command.map(str::to_string),
+                        result: CheckResult::Skipped(reason),
+                    },
+                    (None, Some(check_command)) => {
+                        let check_result = run_check_command(
+                            command_runner,
+                            package_name,
+                            current_env,
+                            check_command,
+                            &env_config,
+                            steps::command_timeout(&env_config, CommandKind::Check, config),
+                            sender,
+                        )
+                        .await;
+                        check_required_version(
+                            check_result,
+                            &env_config,
+                            config,
+                            command_runner,
+                            sender,
+                        )
+                        .await
+                    }
+                    (None, None) => CheckResultData {
+                        package_name: package_name.to_string(),
+                        environment: current_env.to_string(),
+                        check_command: None,
+                        result: CheckResult::NoCheckCommand,
+                    },
+                };
+
+                summary.record(&check_result);
+                let failed = matches!(
+                    check_result.result,
+                    CheckResult::CommandNotFound
+                        | CheckResult::NoCheckCommand
+                        | CheckResult::Error(_)
+                );
+                sender.send_check_result(check_result).await;
+                failed
+            }
+            Ok(None) => {
+                sender
+                    .send_warning(format!(
+                        "Package '{package_name}' does not support environment '{current_env}'"
+                    ))
+                    .await;
+                summary.failed.push(package_name.to_string());
+                true
+            }
+            Err(err) => {
+                let error_msg =
+                    format!("Could not expand commands of package '{package_name}': {err}");
+                sender.send_error(err, &error_msg).await;
+                summary.failed.push(package_name.to_string());
+                true
+            }
+        };
+
+        if failed && config.stop_on_error() {
+            sender
+                .send_warning(format!(
+                    "Stopping after package '{package_name}' could not be checked"
+                ))
+                .await;
+            summary.skipped = packages[i + 1..]
+                .iter()
+                .map(|package| package.name().to_string())
+                .collect();
+            break;
+        }
+    }
+
+    // Step 3: Summarize
+    progress.next(sender, "Summarizing check results").await;
+    let mut message = format!(
+        "Checked {} package(s): {} installed, {} outdated, {} missing, {} failed",
+        summary.checked(),
+        summary.installed.len(),
+        summary.outdated.len(),
+        summary.missing.len(),
+        summary.failed.len()
+    );
+    if !summary.not_applicable.is_empty() {
+        message.push_str(&format!(
+            ", {} not applicable",
+            summary.not_applicable.len()
+        ));
+    }
+    let all_installed = summary.checked() == summary.installed.len() && summary.skipped.is_empty();
+    sender.send_check_summary(summary).await;
+
+    if all_installed {
+        OperationResult::Success(message)
+    } else {
+        OperationResult::Failure(message)
+    }
+}
+
+/// Run a package's check command and turn its outcome into a [`CheckResultData`]
+///
+/// The command runs in `env_config`'s directory and environment. `timeout` is how
+/// long it may run, with the name of the setting that says so.
+async fn run_check_command<CR>(
+    command_runner: &CR,
+    package_name: &str,
+    environment: &str,
+    check_command: &str,
+    env_config: &EnvironmentConfig,
+    (timeout, timeout_setting): (Duration, &str),
+    sender: &EventSender,
+) -> CheckResultData
+where
+    CR: CommandRunner,
+{
+    match command_runner
+        .run(&env_config.command_request(check_command, timeout))
+        .await
+    {
+        Ok(output) => {
+            if output.is_success() {
+                sender
+                    .send_debug(format!("Check command output: {}", output.stdout_str()))
+                    .await;
+                CheckResultData {
+                    package_name: package_name.to_string(),
+                    environment: environment.to_string(),
+                    check_command: Some(check_command.to_string()),
+                    result: CheckResult::Success,
+                }
+            } else {
+                CheckResultData {
+                    package_name: package_name.to_string(),
+                    environment: environment.to_string(),
+                    check_command: Some(check_command.to_string()),
+                    result: CheckResult::Failed {
+                        stdout: output.stdout_str().to_string(),
+                        stderr: output.stderr_str().to_string(),
+                        exit_code: Some(output.exit_code()),
+                    },
+                }
+            }
+        }
+        Err(err) => CheckResultData {
+            package_name: package_name.to_string(),
+            environment: environment.to_string(),
+            check_command: Some(check_command.to_string()),
+            result: CheckResult::Error(format!(
+                "{err}{}",
+                steps::timeout_note(&err, timeout_setting)
+            )),
+        },
+    }
+}
+
+/// Compare the installed version against the environment's `required_version`,
+/// once the check command has shown the package is installed
+///
+/// Any other check result, or an environment without a `required_version`, is
+/// passed through untouched. A version that can't be worked out is an error
+/// rather than a pass, since there's no telling whether it's new enough.
+async fn check_required_version<CR>(
+    check_result: CheckResultData,
+    env_config: &EnvironmentConfig,
+    config: &AppConfig,
+    command_runner: &CR,
+    sender: &EventSender,
+) -> CheckResultData
+where
+    CR: CommandRunner,
+{
+    let (CheckResult::Success, Some(required_version)) =
+        (&check_result.result, env_config.required_version())
+    else {
+        return check_result;
+    };
+
+    let result = match required_version.parse::<VersionRequirement>() {
+        Err(err) => CheckResult::Error(err.to_string()),
+        Ok(requirement) => {
+            match steps::installed_version(command_runner, env_config, config, sender).await {
+                None => CheckResult::Error(
+                    "`required_version` is set, but there's no `version_command` to get the installed version"
+                        .to_string(),
+                ),
+                Some(Err(err)) => CheckResult::Error(err.into_owned()),
+                Some(Ok(version)) if requirement.matches(&version) => CheckResult::Success,
+                Some(Ok(version)) => CheckResult::Outdated {
+                    installed_version: version.to_string(),
+                    required_version: requirement.to_string(),
+                },
+            }
+        }
+    };
+
+    CheckResultData {
+        result,
+        ..check_result
+    }
+}
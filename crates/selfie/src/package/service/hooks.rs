@@ -0,0 +1,94 @@
+//! Helps break down the pieces of running a package's install hooks.
+
+use crate::{
+    commands::runner::CommandRunner,
+    config::AppConfig,
+    fs::FileSystem,
+    package::{CommandKind, EnvironmentConfig, condition::ConditionalCommand, event::EventSender},
+};
+
+use super::{InstallError, InstallHook, ProgressTracker, steps};
+
+/// The commands of `hook` for the environment, in the order they run
+pub(super) fn hook_commands(
+    env_config: &EnvironmentConfig,
+    hook: InstallHook,
+) -> &[ConditionalCommand] {
+    match hook {
+        InstallHook::PreInstall => env_config.pre_install(),
+        InstallHook::PostInstall => env_config.post_install(),
+    }
+}
+
+/// Run a package's `pre_install` or `post_install` commands
+///
+/// Each command is its own progress step, so the caller must have added
+/// [`hook_commands`]`.len()` steps to `progress` beforehand. A command whose
+/// `when:` conditions aren't met is skipped, and still takes its step.
+///
+/// The first failing command stops the hook; it's reported as an
+/// [`InstallError::HookFailed`] error on `sender`, so it can be told apart from
+/// the install command itself failing.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn run_hook<CR, F>(
+    hook: InstallHook,
+    package_name: &str,
+    env_config: &EnvironmentConfig,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> Result<(), InstallError>
+where
+    CR: CommandRunner,
+    F: FileSystem,
+{
+    for command in hook_commands(env_config, hook) {
+        if let Some(reason) =
+            steps::unmet_condition(command.when(), command_runner, file_system, config, sender)
+                .await
+        {
+            progress
+                .next(
+                    sender,
+                    format!("Skipped {hook} command `{}`: {reason}", command.run()),
+                )
+                .await;
+            continue;
+        }
+
+        let reason = match steps::execute_command(
+            command_runner,
+            command.run(),
+            hook.as_str(),
+            CommandKind::Other,
+            env_config,
+            config,
+            sender,
+            progress,
+        )
+        .await
+        {
+            Ok(true) => continue,
+            Ok(false) => "command exited unsuccessfully".to_string(),
+            Err(err) => err.into_owned(),
+        };
+
+        let error = InstallError::HookFailed {
+            package_name: package_name.to_string(),
+            hook,
+            command: command.run().to_string(),
+            reason,
+        };
+        sender
+            .send_error(
+                error.clone(),
+                format!("Package '{package_name}' {hook} hook failed"),
+            )
+            .await;
+        return Err(error);
+    }
+
+    Ok(())
+}
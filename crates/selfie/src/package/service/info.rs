@@ -6,24 +6,32 @@ use crate::{
     commands::runner::CommandRunner,
     config::AppConfig,
     package::{
+        CommandKind,
+        condition::ConditionalCommand,
         event::{
             EnvironmentStatus, EnvironmentStatusData, EventSender, OperationResult, PackageInfoData,
         },
         port::PackageRepository,
+        state::StateStore,
+        template::TemplateContext,
     },
 };
 
-pub(super) async fn handle_info<PR, CR>(
+use super::steps;
+
+pub(super) async fn handle_info<PR, CR, SS>(
     package_name: &str,
     repo: &PR,
     config: &AppConfig,
     command_runner: &CR,
+    state_store: &SS,
     sender: &EventSender,
     progress: &mut crate::package::service::ProgressTracker,
 ) -> OperationResult
 where
     PR: PackageRepository,
     CR: CommandRunner,
+    SS: StateStore,
 {
     // Step 1: Fetch package
     progress.next(sender, "Loading package definition").await;
@@ -45,6 +53,11 @@ where
     // Step 2: Send package information data
     progress.next(sender, "Gathering package information").await;
 
+    let selected_environment = package_blob
+        .package
+        .select_environment(&config.environment_chain())
+        .map(|(name, _)| name.to_string());
+
     let package_info = PackageInfoData {
         name: package_blob.package.name().to_string(),
         version: package_blob.package.version().to_string(),
@@ -63,6 +76,7 @@ where
             .cloned()
             .collect(),
         current_environment: config.environment().to_string(),
+        selected_environment: selected_environment.clone(),
     };
 
     sender.send_package_info(package_info).await;
@@ -72,11 +86,12 @@ where
         .next(sender, "Checking installation status for environments")
         .await;
 
-    // Sort environments to show current environment first
+    // Sort environments to show the one selected for the current environment first
+    let is_selected = |env_name: &str| selected_environment.as_deref() == Some(env_name);
     let mut environments: Vec<_> = package_blob.package.environments().iter().collect();
     environments.sort_by(|a, b| {
-        let a_is_current = a.0 == config.environment();
-        let b_is_current = b.0 == config.environment();
+        let a_is_current = is_selected(a.0);
+        let b_is_current = is_selected(b.0);
 
         match (a_is_current, b_is_current) {
             (true, false) => std::cmp::Ordering::Less, // a comes first
@@ -85,21 +100,80 @@ where
         }
     });
 
+    let template_context = TemplateContext::new(config);
+
     for (env_name, env_config) in environments {
-        let is_current = env_name == config.environment();
-        let status = if is_current {
-            get_installation_status(env_config, command_runner).await
+        let is_current = is_selected(env_name);
+
+        // Show commands as they'd run, but never run one that couldn't be expanded
+        let rendered = match template_context.render(&package_blob.package, env_config) {
+            Ok(rendered) => Some(rendered),
+            Err(err) => {
+                sender
+                    .send_warning(format!("Environment '{env_name}': {err}"))
+                    .await;
+                None
+            }
+        };
+        let env_config = rendered.as_ref().unwrap_or(env_config);
+
+        let status = match (is_current, &rendered) {
+            (true, Some(_)) => get_installation_status(env_config, config, command_runner).await,
+            (true, None) => Some(EnvironmentStatus::Unknown(
+                "commands could not be expanded".to_string(),
+            )),
+            (false, _) => None,
+        };
+
+        // The installed version only means something once the package is installed
+        let installed_version = if matches!(status, Some(EnvironmentStatus::Installed)) {
+            match steps::installed_version(command_runner, env_config, config, sender).await {
+                Some(Ok(version)) => Some(version.to_string()),
+                Some(Err(err)) => {
+                    sender
+                        .send_warning(format!("Unable to detect installed version: {err}"))
+                        .await;
+                    None
+                }
+                None => None,
+            }
         } else {
             None
         };
 
+        // Installs are recorded under the current environment, whichever block ran
+        let record_environment = if is_current {
+            config.environment()
+        } else {
+            env_name
+        };
+        let install_record = match state_store.get_install(package_name, record_environment) {
+            Ok(record) => record,
+            Err(err) => {
+                sender
+                    .send_warning(format!("Unable to read install state: {err}"))
+                    .await;
+                None
+            }
+        };
+
         let environment_status = EnvironmentStatusData {
             environment_name: env_name.clone(),
             is_current,
             install_command: env_config.install().to_string(),
             check_command: env_config.check().map(std::string::ToString::to_string),
+            pre_install_commands: hook_commands(env_config.pre_install()),
+            post_install_commands: hook_commands(env_config.post_install()),
+            uninstall_command: env_config.uninstall().map(std::string::ToString::to_string),
+            update_command: env_config.update().map(std::string::ToString::to_string),
+            rollback_commands: env_config.rollback().to_vec(),
             dependencies: env_config.dependencies().to_vec(),
             status,
+            installed_version,
+            required_version: env_config
+                .required_version()
+                .map(std::string::ToString::to_string),
+            install_record,
         };
 
         sender.send_environment_status(environment_status).await;
@@ -121,12 +195,17 @@ where
 
 async fn get_installation_status(
     env_config: &crate::package::EnvironmentConfig,
+    config: &AppConfig,
     command_runner: &impl CommandRunner,
 ) -> Option<EnvironmentStatus> {
     // Only run check for current environment
     if let Some(check_cmd) = env_config.check() {
         // Run the check command asynchronously
-        if let Ok(output) = command_runner.execute(check_cmd).await {
+        let (timeout, _) = steps::command_timeout(env_config, CommandKind::Check, config);
+        if let Ok(output) = command_runner
+            .run(&env_config.command_request(check_cmd, timeout))
+            .await
+        {
             if output.is_success() {
                 Some(EnvironmentStatus::Installed)
             } else {
@@ -141,3 +220,14 @@ async fn get_installation_status(
         Some(EnvironmentStatus::Unknown("no check command".to_string()))
     }
 }
+
+/// Describe each hook command, with its `when:` clause if it has one
+fn hook_commands(commands: &[ConditionalCommand]) -> Vec<String> {
+    commands
+        .iter()
+        .map(|command| match command.when() {
+            Some(when) => format!("{} (when {})", command.run(), when),
+            None => command.run().to_string(),
+        })
+        .collect()
+}
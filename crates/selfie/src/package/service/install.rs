@@ -2,28 +2,52 @@
 //! Helps break down the pieces of running the `package install` command.
 //!
 
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+};
+
+use futures::stream::{FuturesUnordered, StreamExt};
+
 use crate::{
     commands::runner::CommandRunner,
     config::AppConfig,
+    fs::FileSystem,
     package::{
-        event::{EventSender, OperationResult},
+        CommandKind, EnvironmentConfig,
+        event::{
+            EventSender, InstallPlanAction, InstallPlanData, InstallPlanStep, InstallSummaryData,
+            OperationResult,
+        },
+        graph::{DependencyGraph, DependencyNode},
         port::PackageRepository,
+        state::{InstallReason, StateStore},
     },
 };
 
-use super::steps;
+use super::{InstallError, InstallHook, InstallOptions, ProgressTracker, hooks, rollback, steps};
 
-pub(super) async fn handle_install<PR, CR>(
+/// Number of progress steps every dependency starts with: check + install. Hooks,
+/// further install steps and verification add their own steps once they run.
+const STEPS_PER_DEPENDENCY: u32 = 2;
+
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn handle_install<PR, CR, F, SS>(
     package_name: &str,
+    options: InstallOptions,
     repo: &PR,
     config: &AppConfig,
     command_runner: &CR,
+    file_system: &F,
+    state_store: &SS,
     sender: &EventSender,
-    progress: &mut crate::package::service::ProgressTracker,
+    progress: &mut ProgressTracker,
 ) -> OperationResult
 where
     PR: PackageRepository,
     CR: CommandRunner,
+    F: FileSystem,
+    SS: StateStore,
 {
     // Step 1: Fetch package (reusing shared step)
     let package_blob = match steps::fetch_package(repo, package_name, sender, progress).await {
@@ -35,76 +59,1099 @@ where
     };
 
     // Step 2: Find environment configuration (reusing shared step)
-    let env_config = match steps::find_environment_config(
-        &package_blob.package,
-        config.environment(),
+    let env_config =
+        match steps::find_environment_config(&package_blob.package, config, sender, progress).await
+        {
+            Ok(config) => config,
+            Err(err) => {
+                let error_msg = format!("Environment configuration error: {err}");
+                return OperationResult::Failure(error_msg);
+            }
+        };
+    let env_config = &env_config;
+
+    // Nothing runs for an environment that doesn't apply to this machine
+    if let Some(reason) = steps::unmet_condition(
+        env_config.when(),
+        command_runner,
+        file_system,
+        config,
+        sender,
+    )
+    .await
+    {
+        return OperationResult::Skipped(format!("Package '{package_name}' was skipped: {reason}"));
+    }
+
+    // Step 3: Resolve dependencies before running anything, so a broken dependency
+    // tree fails the whole install up front
+    let graph = match steps::resolve_dependencies(
+        repo,
+        std::slice::from_ref(&package_blob.package),
+        config,
         sender,
         progress,
     )
     .await
     {
-        Ok(config) => config,
-        Err(err) => {
-            let error_msg = format!("Environment configuration error: {err}");
-            return OperationResult::Failure(error_msg);
-        }
+        Ok(graph) => graph,
+        Err(err) => return OperationResult::Failure(err.into_owned()),
     };
+    let dependencies = graph.dependencies();
+    progress.add_steps(dependencies.len() as u32);
+
+    if options.dry_run {
+        return plan_install(
+            &graph,
+            options,
+            config,
+            command_runner,
+            file_system,
+            sender,
+            progress,
+        )
+        .await;
+    }
 
-    // Step 3: Get install command (reusing shared step with custom getter function)
-    let install_cmd = match steps::get_command(
+    // Step 4: Check and install dependencies, running independent ones in parallel.
+    // This happens even if the package itself turns out to be installed, so a
+    // missing dependency is never left behind.
+    let report = install_nodes(
+        dependencies,
+        |_| false,
+        options,
+        config,
+        command_runner,
+        file_system,
+        state_store,
+        sender,
+        progress,
+    )
+    .await;
+    if let Some(err) = report.first_error() {
+        return OperationResult::Failure(err.to_string());
+    }
+
+    // Step 5: Check if the package is already installed (unless forced)
+    if options.force {
+        progress
+            .next(sender, "Skipping installation check (forced)")
+            .await;
+    } else if steps::check_installed(
+        command_runner,
+        package_name,
         env_config,
-        "install",
-        |ec| Some(ec.install()),
+        config,
         sender,
         progress,
     )
     .await
     {
-        Ok(cmd) => cmd,
-        Err(err) => {
-            let error_msg = format!("Install command error: {err}");
-            return OperationResult::Failure(error_msg);
-        }
-    };
+        let message = if report.installed.is_empty() {
+            format!("Package '{package_name}' is already installed")
+        } else {
+            format!(
+                "Package '{package_name}' is already installed; installed missing dependencies: {}",
+                report.installed.join(", ")
+            )
+        };
+        return OperationResult::AlreadyInstalled(message);
+    }
 
-    // Step 4: Execute install command (reusing shared step)
-    let is_success = match steps::execute_command(
+    // Step 6: Run the pre-install hook, if any
+    progress.add_steps(
+        (hooks::hook_commands(env_config, InstallHook::PreInstall).len()
+            + hooks::hook_commands(env_config, InstallHook::PostInstall).len()) as u32,
+    );
+    if let Err(err) = hooks::run_hook(
+        InstallHook::PreInstall,
+        package_name,
+        env_config,
+        config,
         command_runner,
-        install_cmd,
+        file_system,
+        sender,
+        progress,
+    )
+    .await
+    {
+        return OperationResult::Failure(
+            roll_back_if_requested(
+                err.to_string(),
+                package_name,
+                env_config,
+                options,
+                config,
+                command_runner,
+                sender,
+            )
+            .await,
+        );
+    }
+
+    // Step 7: Make sure there's an install command (reusing shared step with custom
+    // getter function)
+    if let Err(err) = steps::get_command(
+        env_config,
         "install",
+        |ec| ec.install().commands().first().map(String::as_str),
+        sender,
+        progress,
+    )
+    .await
+    {
+        return OperationResult::Failure(format!("Install command error: {err}"));
+    }
+
+    // Step 8: Run the install commands, each as its own step
+    progress.add_steps((env_config.install().commands().len() as u32).saturating_sub(1));
+    if let Err(failure) = run_install_commands(
+        package_name,
+        env_config,
         config,
+        command_runner,
         sender,
         progress,
     )
     .await
     {
-        Ok(success) => success,
-        Err(err) => {
-            let error_msg = format!("Command execution error: {err}");
-            return OperationResult::Failure(error_msg);
-        }
-    };
+        let error_msg = match failure {
+            InstallFailure::Exited => {
+                sender
+                    .send_warning(format!(
+                        "Package '{package_name}' installation command failed"
+                    ))
+                    .await;
+                format!(
+                    "Installation failed at step {}/{}",
+                    progress.current_step(),
+                    progress.total_steps()
+                )
+            }
+            InstallFailure::Error(err) => format!("Command execution error: {err}"),
+            InstallFailure::Step(err) => err.to_string(),
+        };
 
-    // Step 5: Process result
-    if is_success {
+        return OperationResult::Failure(
+            roll_back_if_requested(
+                error_msg,
+                package_name,
+                env_config,
+                options,
+                config,
+                command_runner,
+                sender,
+            )
+            .await,
+        );
+    }
+
+    // Step 9: Run the post-install hook, if any
+    if let Err(err) = hooks::run_hook(
+        InstallHook::PostInstall,
+        package_name,
+        env_config,
+        config,
+        command_runner,
+        file_system,
+        sender,
+        progress,
+    )
+    .await
+    {
+        return OperationResult::Failure(
+            roll_back_if_requested(
+                err.to_string(),
+                package_name,
+                env_config,
+                options,
+                config,
+                command_runner,
+                sender,
+            )
+            .await,
+        );
+    }
+
+    // Step 10: Make sure the package can now be detected
+    if let Err(err) = steps::verify_installed(
+        command_runner,
+        package_name,
+        env_config,
+        config,
+        sender,
+        progress,
+    )
+    .await
+    {
+        return OperationResult::Failure(err.to_string());
+    }
+
+    steps::record_install(
+        state_store,
+        &package_blob.package,
+        &env_config.install().to_string(),
+        InstallReason::Direct,
+        config,
+        sender,
+    )
+    .await;
+
+    OperationResult::Success(format!(
+        "Installation completed successfully ({}/{} steps)",
+        progress.current_step(),
+        progress.total_steps()
+    ))
+}
+
+/// Run the package's `rollback` commands after its install command or one of its
+/// hooks failed, if the options ask for it, and say how that went at the end of
+/// `error_msg`
+async fn roll_back_if_requested<CR>(
+    error_msg: String,
+    package_name: &str,
+    env_config: &EnvironmentConfig,
+    options: InstallOptions,
+    config: &AppConfig,
+    command_runner: &CR,
+    sender: &EventSender,
+) -> String
+where
+    CR: CommandRunner,
+{
+    if !options.rollback_on_failure {
+        return error_msg;
+    }
+
+    let outcome =
+        rollback::rollback_install(package_name, env_config, config, command_runner, sender).await;
+    format!("{error_msg} ({})", outcome.describe())
+}
+
+/// Install several packages in one operation
+///
+/// The dependency graphs of all the packages are merged, so a dependency shared
+/// between them is checked and installed only once, and packages that don't depend
+/// on each other can be installed in parallel. A package that can't be loaded, or
+/// whose dependencies can't be resolved, fails on its own; the rest are installed
+/// unless [`AppConfig::stop_on_error`] is set.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn handle_install_many<PR, CR, F, SS>(
+    selection: steps::PackageSelection,
+    options: InstallOptions,
+    repo: &PR,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    state_store: &SS,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> OperationResult
+where
+    PR: PackageRepository,
+    CR: CommandRunner,
+    F: FileSystem,
+    SS: StateStore,
+{
+    // Step 1: Load the requested packages; one that can't be loaded only fails
+    // itself
+    let (packages, mut unresolved) =
+        match steps::load_each_package(&selection, repo, config, sender, progress).await {
+            Ok(loaded) => loaded,
+            Err(err) => return OperationResult::Failure(err),
+        };
+    if packages.is_empty() && unresolved.is_empty() {
+        return OperationResult::Success(format!(
+            "No packages to install for environment '{}'",
+            config.environment()
+        ));
+    }
+
+    // Step 2: Resolve one graph for all of them before running anything, leaving
+    // out the packages whose dependencies can't be resolved
+    let graph =
+        match steps::resolve_each_dependencies(repo, &packages, config, sender, progress).await {
+            Ok((graph, failed)) => {
+                unresolved.extend(failed);
+                graph
+            }
+            Err(err) => return OperationResult::Failure(err.into_owned()),
+        };
+
+    // Step 3: Check which of the requested packages still need installing
+    let mut needed = Vec::with_capacity(graph.roots().len());
+    let mut already_installed = Vec::new();
+    let mut not_applicable = Vec::new();
+
+    if options.force {
+        progress.add_steps(1);
         progress
-            .next(sender, "Package installed successfully")
+            .next(sender, "Skipping installation checks (forced)")
             .await;
-        OperationResult::Success(format!(
-            "Installation completed successfully ({}/{} steps)",
-            progress.current_step(),
-            progress.total_steps()
-        ))
+        needed.extend(graph.roots().iter().map(String::as_str));
+    } else {
+        progress.add_steps(graph.roots().len() as u32);
+
+        for root in graph
+            .install_order()
+            .iter()
+            .filter(|n| graph.is_root(n.name()))
+        {
+            if let Some(reason) = steps::unmet_condition(
+                root.env_config().when(),
+                command_runner,
+                file_system,
+                config,
+                sender,
+            )
+            .await
+            {
+                progress
+                    .next(sender, format!("Skipped `{}`: {reason}", root.name()))
+                    .await;
+                not_applicable.push(root);
+            } else if steps::check_installed(
+                command_runner,
+                root.name(),
+                root.env_config(),
+                config,
+                sender,
+                progress,
+            )
+            .await
+            {
+                already_installed.push(root);
+            } else {
+                needed.push(root.name());
+            }
+        }
+    }
+
+    let plan = graph.subgraph(&needed);
+    let unresolved_names: Vec<String> = unresolved.iter().map(|(name, _)| name.clone()).collect();
+
+    if options.dry_run {
+        // Only the dependencies are left to check
+        progress.add_steps((plan.len() - plan.roots().len()) as u32);
+        return plan_install_many(
+            &plan,
+            &already_installed,
+            &not_applicable,
+            unresolved_names,
+            config,
+            command_runner,
+            file_system,
+            sender,
+            progress,
+        )
+        .await;
+    }
+
+    if plan.is_empty() && unresolved.is_empty() {
+        sender
+            .send_install_summary(InstallSummaryData {
+                environment: config.environment().to_string(),
+                already_installed: node_names(&already_installed),
+                not_applicable: node_names(&not_applicable),
+                ..InstallSummaryData::default()
+            })
+            .await;
+
+        if not_applicable.is_empty() {
+            return OperationResult::AlreadyInstalled(format!(
+                "All {} package(s) are already installed",
+                already_installed.len()
+            ));
+        }
+        let message = format!(
+            "{} package(s) already installed, {} skipped",
+            already_installed.len(),
+            not_applicable.len()
+        );
+        return if already_installed.is_empty() {
+            OperationResult::Skipped(message)
+        } else {
+            OperationResult::AlreadyInstalled(message)
+        };
+    }
+
+    // Step 4: Install everything that's needed, dependencies first. A package that
+    // couldn't be resolved counts as the first failure, so with `stop_on_error` set
+    // nothing else is started.
+    let mut report = if config.stop_on_error() && !unresolved.is_empty() {
+        InstallReport {
+            skipped: plan
+                .install_order()
+                .iter()
+                .map(|node| node.name().to_string())
+                .collect(),
+            ..InstallReport::default()
+        }
     } else {
+        progress.add_steps(plan.len() as u32);
+        install_nodes(
+            plan.install_order(),
+            |name| plan.is_root(name),
+            options,
+            config,
+            command_runner,
+            file_system,
+            state_store,
+            sender,
+            progress,
+        )
+        .await
+    };
+    report.failed.splice(0..0, unresolved);
+
+    // Step 5: Summarize
+    progress.next(sender, "Summarizing installation").await;
+
+    for name in &report.skipped {
         sender
             .send_warning(format!(
-                "Package '{package_name}' installation command failed"
+                "Package '{name}' was not installed because of an earlier failure"
             ))
             .await;
+    }
+
+    let summary = format!(
+        "{} installed, {} already installed, {} failed, {} skipped",
+        report.installed.len(),
+        report.already_installed.len() + already_installed.len(),
+        report.failed.len(),
+        report.skipped.len() + report.not_applicable.len() + not_applicable.len()
+    );
+
+    let failed: Vec<String> = report.failed.into_iter().map(|(name, _)| name).collect();
+    let result = if failed.is_empty() {
+        OperationResult::Success(format!("Installation completed successfully: {summary}"))
+    } else {
         OperationResult::Failure(format!(
-            "Installation failed at step {}/{}",
-            progress.current_step(),
-            progress.total_steps()
+            "Failed to install {} ({summary})",
+            failed.join(", ")
         ))
+    };
+
+    sender
+        .send_install_summary(InstallSummaryData {
+            environment: config.environment().to_string(),
+            installed: report.installed,
+            already_installed: already_installed
+                .iter()
+                .map(|node| node.name().to_string())
+                .chain(report.already_installed)
+                .collect(),
+            failed,
+            skipped: report.skipped,
+            not_applicable: not_applicable
+                .iter()
+                .map(|node| node.name().to_string())
+                .chain(report.not_applicable)
+                .collect(),
+        })
+        .await;
+
+    result
+}
+
+/// Work out what installing several packages would do, without installing anything
+///
+/// The requested packages have already been checked; this checks the dependencies
+/// of the ones that still need installing. `unresolved` are the requested packages
+/// that couldn't be loaded or resolved, which fail the dry run.
+#[allow(clippy::too_many_arguments)]
+async fn plan_install_many<CR, F>(
+    plan: &DependencyGraph,
+    already_installed: &[&DependencyNode],
+    not_applicable: &[&DependencyNode],
+    unresolved: Vec<String>,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> OperationResult
+where
+    CR: CommandRunner,
+    F: FileSystem,
+{
+    let mut steps: Vec<InstallPlanStep> = already_installed
+        .iter()
+        .map(|node| plan_step(node, InstallPlanAction::AlreadyInstalled))
+        .chain(
+            not_applicable
+                .iter()
+                .map(|node| plan_step(node, InstallPlanAction::Skipped)),
+        )
+        .collect();
+
+    for node in plan.install_order() {
+        let action = if plan.is_root(node.name()) {
+            InstallPlanAction::Install
+        } else {
+            planned_action(node, config, command_runner, file_system, sender, progress).await
+        };
+        steps.push(plan_step(node, action));
     }
+
+    progress.next(sender, "Install plan ready").await;
+
+    let mut requested = node_names(already_installed);
+    requested.extend(node_names(not_applicable));
+    requested.extend(plan.roots().iter().cloned());
+    requested.extend(unresolved.iter().cloned());
+
+    let install_plan = InstallPlanData {
+        requested,
+        environment: plan.environment().to_string(),
+        steps,
+        failed: unresolved,
+    };
+    let to_install = install_plan.to_install().count();
+    let total = install_plan.steps.len();
+    let message = format!("Dry run: {to_install} of {total} package(s) would be installed");
+    let failed = install_plan.failed.join(", ");
+    sender.send_install_plan(install_plan).await;
+
+    if failed.is_empty() {
+        OperationResult::Success(message)
+    } else {
+        OperationResult::Failure(format!("{message}; {failed} can't be installed"))
+    }
+}
+
+/// Work out what installing the root of `graph` would do, without installing anything
+///
+/// Only `check` commands are run. Mirrors [`handle_install`]: every dependency is
+/// checked, whether or not the requested package is already installed.
+async fn plan_install<CR, F>(
+    graph: &DependencyGraph,
+    options: InstallOptions,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> OperationResult
+where
+    CR: CommandRunner,
+    F: FileSystem,
+{
+    let Some(root) = graph.root() else {
+        return OperationResult::Failure("Dependency graph is empty".to_string());
+    };
+
+    let mut steps = Vec::with_capacity(graph.len());
+    for dependency in graph.dependencies() {
+        let action = planned_action(
+            dependency,
+            config,
+            command_runner,
+            file_system,
+            sender,
+            progress,
+        )
+        .await;
+        steps.push(plan_step(dependency, action));
+    }
+
+    let root_action = if options.force {
+        progress
+            .next(sender, "Skipping installation check (forced)")
+            .await;
+        InstallPlanAction::Install
+    } else {
+        planned_action(root, config, command_runner, file_system, sender, progress).await
+    };
+    steps.push(plan_step(root, root_action));
+
+    progress.next(sender, "Install plan ready").await;
+
+    let install_plan = InstallPlanData {
+        requested: graph.roots().to_vec(),
+        environment: graph.environment().to_string(),
+        steps,
+        failed: Vec::new(),
+    };
+    let to_install = install_plan.to_install().count();
+    let total = install_plan.steps.len();
+    sender.send_install_plan(install_plan).await;
+
+    OperationResult::Success(format!(
+        "Dry run: {to_install} of {total} package(s) would be installed"
+    ))
+}
+
+async fn planned_action<CR, F>(
+    node: &DependencyNode,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> InstallPlanAction
+where
+    CR: CommandRunner,
+    F: FileSystem,
+{
+    if let Some(reason) = steps::unmet_condition(
+        node.env_config().when(),
+        command_runner,
+        file_system,
+        config,
+        sender,
+    )
+    .await
+    {
+        progress
+            .next(sender, format!("Skipped `{}`: {reason}", node.name()))
+            .await;
+        return InstallPlanAction::Skipped;
+    }
+
+    let installed = steps::check_installed(
+        command_runner,
+        node.name(),
+        node.env_config(),
+        config,
+        sender,
+        progress,
+    )
+    .await;
+
+    if installed {
+        InstallPlanAction::AlreadyInstalled
+    } else {
+        InstallPlanAction::Install
+    }
+}
+
+fn node_names(nodes: &[&DependencyNode]) -> Vec<String> {
+    nodes.iter().map(|node| node.name().to_string()).collect()
+}
+
+fn plan_step(node: &DependencyNode, action: InstallPlanAction) -> InstallPlanStep {
+    InstallPlanStep {
+        package_name: node.name().to_string(),
+        install_command: node.env_config().install().to_string(),
+        action,
+        required_by: node.required_by().map(str::to_string),
+    }
+}
+
+/// What happened to the packages handed to [`install_nodes`]
+#[derive(Debug, Default)]
+struct InstallReport {
+    /// Packages whose install command ran successfully
+    installed: Vec<String>,
+    /// Packages whose check command showed they were already installed
+    already_installed: Vec<String>,
+    /// Packages that failed to install, with the reason
+    failed: Vec<(String, String)>,
+    /// Packages that were never started because something failed first
+    skipped: Vec<String>,
+    /// Packages whose `when:` conditions aren't met
+    not_applicable: Vec<String>,
+}
+
+impl InstallReport {
+    fn first_error(&self) -> Option<&str> {
+        self.failed.first().map(|(_, err)| err.as_str())
+    }
+}
+
+/// Whether a package had to be installed or was already there
+enum NodeOutcome {
+    Installed,
+    AlreadyInstalled,
+    /// The package's `when:` conditions aren't met, so nothing ran
+    NotApplicable,
+}
+
+/// Install `nodes` (given in topological order) as soon as their own dependencies
+/// are in place
+///
+/// Up to [`AppConfig::max_parallel_installations`] packages run at the same time.
+/// Each one reports through a child [`EventSender`] so its events can be told apart
+/// from the others, while `progress` advances once per finished package.
+///
+/// Packages for which `is_requested` returns `true` have already been checked (or
+/// forced) by the caller, so only their install command runs; every other package
+/// is checked first and only installed if it's missing.
+///
+/// A package whose `when:` conditions aren't met is skipped without failing, so
+/// the packages that depend on it still go ahead.
+///
+/// Nothing that depends on a failed package is started. With
+/// [`AppConfig::stop_on_error`] set, no new packages at all are started after the
+/// first failure, but the ones already running are allowed to finish.
+#[allow(clippy::too_many_arguments)]
+async fn install_nodes<CR, F, SS>(
+    nodes: &[DependencyNode],
+    is_requested: impl Fn(&str) -> bool,
+    options: InstallOptions,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    state_store: &SS,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> InstallReport
+where
+    CR: CommandRunner,
+    F: FileSystem,
+    SS: StateStore,
+{
+    let index: HashMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.name(), i))
+        .collect();
+
+    // Number of unfinished dependencies of each node, and the nodes waiting on it
+    let mut pending = vec![0_usize; nodes.len()];
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+
+    for (i, node) in nodes.iter().enumerate() {
+        for dependency in node.env_config().dependencies() {
+            if let Some(&j) = index.get(dependency.as_str()) {
+                pending[i] += 1;
+                dependents[j].push(i);
+            }
+        }
+    }
+
+    let mut ready: VecDeque<usize> = (0..nodes.len()).filter(|&i| pending[i] == 0).collect();
+    let mut started = vec![false; nodes.len()];
+    let mut running = FuturesUnordered::new();
+    let mut report = InstallReport::default();
+    let limit = config.max_parallel_installations().get();
+
+    loop {
+        let stopping = config.stop_on_error() && !report.failed.is_empty();
+
+        while !stopping && running.len() < limit {
+            let Some(i) = ready.pop_front() else {
+                break;
+            };
+            let node = &nodes[i];
+            let requested = is_requested(node.name());
+            let child_sender = sender.child(node.name());
+            started[i] = true;
+
+            running.push(async move {
+                let result = install_node(
+                    node,
+                    requested,
+                    options,
+                    config,
+                    command_runner,
+                    file_system,
+                    state_store,
+                    &child_sender,
+                )
+                .await;
+                (i, result)
+            });
+        }
+
+        let Some((i, result)) = running.next().await else {
+            break;
+        };
+        let name = nodes[i].name().to_string();
+
+        match result {
+            Ok(outcome) => {
+                let label = if is_requested(&name) {
+                    "Package"
+                } else {
+                    "Dependency"
+                };
+                let status = if matches!(outcome, NodeOutcome::NotApplicable) {
+                    "was skipped"
+                } else {
+                    "is ready"
+                };
+                progress
+                    .next(sender, format!("{label} `{name}` {status}"))
+                    .await;
+
+                for &dependent in &dependents[i] {
+                    pending[dependent] -= 1;
+                    if pending[dependent] == 0 {
+                        ready.push_back(dependent);
+                    }
+                }
+
+                match outcome {
+                    NodeOutcome::Installed => report.installed.push(name),
+                    NodeOutcome::AlreadyInstalled => report.already_installed.push(name),
+                    NodeOutcome::NotApplicable => report.not_applicable.push(name),
+                }
+            }
+            Err(err) => report.failed.push((name, err)),
+        }
+    }
+
+    report.skipped = nodes
+        .iter()
+        .zip(&started)
+        .filter(|(_, started)| !**started)
+        .map(|(node, _)| node.name().to_string())
+        .collect();
+
+    report
+}
+
+/// Install a single package from a dependency graph
+///
+/// Reports through the package's own `sender` with its own progress. A package
+/// whose `when:` conditions aren't met is skipped. A dependency is checked first
+/// and left alone if it's already installed; a `requested` package has already
+/// been checked by the caller, so its install command just runs.
+///
+/// The `pre_install` and `post_install` hooks run around the install command.
+/// Once installed, the package is verified with its `check` command and recorded
+/// in the state store.
+#[allow(clippy::too_many_arguments)]
+async fn install_node<CR, F, SS>(
+    node: &DependencyNode,
+    requested: bool,
+    options: InstallOptions,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    state_store: &SS,
+    sender: &EventSender,
+) -> Result<NodeOutcome, String>
+where
+    CR: CommandRunner,
+    F: FileSystem,
+    SS: StateStore,
+{
+    let name = node.name();
+    let (label, total_steps) = if requested {
+        ("package", 1)
+    } else {
+        ("dependency", STEPS_PER_DEPENDENCY)
+    };
+    let env_config = node.env_config();
+    let mut progress = ProgressTracker::new(total_steps);
+    let progress = &mut progress;
+
+    if let Some(reason) = steps::unmet_condition(
+        env_config.when(),
+        command_runner,
+        file_system,
+        config,
+        sender,
+    )
+    .await
+    {
+        progress
+            .next(sender, format!("Skipped {label} `{name}`: {reason}"))
+            .await;
+        return Ok(NodeOutcome::NotApplicable);
+    }
+
+    if !requested
+        && steps::check_installed(command_runner, name, env_config, config, sender, progress).await
+    {
+        progress
+            .next(sender, format!("Dependency `{name}` is already installed"))
+            .await;
+        return Ok(NodeOutcome::AlreadyInstalled);
+    }
+
+    progress.add_steps(hooks::hook_commands(env_config, InstallHook::PreInstall).len() as u32);
+    if let Err(err) = hooks::run_hook(
+        InstallHook::PreInstall,
+        name,
+        env_config,
+        config,
+        command_runner,
+        file_system,
+        sender,
+        progress,
+    )
+    .await
+    {
+        return Err(roll_back_if_requested(
+            err.to_string(),
+            name,
+            env_config,
+            options,
+            config,
+            command_runner,
+            sender,
+        )
+        .await);
+    }
+
+    progress.add_steps((env_config.install().commands().len() as u32).saturating_sub(1));
+    if let Err(failure) =
+        run_install_commands(name, env_config, config, command_runner, sender, progress).await
+    {
+        let error_msg = match failure {
+            InstallFailure::Exited => {
+                sender
+                    .send_warning(format!("Package '{name}' installation command failed"))
+                    .await;
+                format!(
+                    "Failed to install {label} '{name}' at step {}/{}",
+                    progress.current_step(),
+                    progress.total_steps()
+                )
+            }
+            InstallFailure::Error(err) => {
+                format!("Command execution error installing {label} '{name}': {err}")
+            }
+            InstallFailure::Step(err) => err.to_string(),
+        };
+        return Err(roll_back_if_requested(
+            error_msg,
+            name,
+            env_config,
+            options,
+            config,
+            command_runner,
+            sender,
+        )
+        .await);
+    }
+
+    // The post-install hook and verification are only needed once something
+    // was actually installed
+    progress.add_steps(hooks::hook_commands(env_config, InstallHook::PostInstall).len() as u32 + 1);
+    if let Err(err) = hooks::run_hook(
+        InstallHook::PostInstall,
+        name,
+        env_config,
+        config,
+        command_runner,
+        file_system,
+        sender,
+        progress,
+    )
+    .await
+    {
+        return Err(roll_back_if_requested(
+            err.to_string(),
+            name,
+            env_config,
+            options,
+            config,
+            command_runner,
+            sender,
+        )
+        .await);
+    }
+    steps::verify_installed(command_runner, name, env_config, config, sender, progress)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let reason = if requested {
+        InstallReason::Direct
+    } else {
+        InstallReason::Dependency
+    };
+    steps::record_install(
+        state_store,
+        node.package(),
+        &env_config.install().to_string(),
+        reason,
+        config,
+        sender,
+    )
+    .await;
+
+    sender
+        .send_debug(format!("Installed {label} `{name}`"))
+        .await;
+    Ok(NodeOutcome::Installed)
+}
+
+/// How a package's install commands failed
+enum InstallFailure {
+    /// The package's only install command exited unsuccessfully
+    Exited,
+    /// The package's only install command couldn't be run
+    Error(Cow<'static, str>),
+    /// One command of a multi-step install failed; it's already been reported
+    Step(InstallError),
+}
+
+/// Run the package's install commands in order, each as its own progress step
+///
+/// The caller must have added a step per command to `progress`. The first failing
+/// command stops the rest. When there are several commands, the failing one is
+/// reported as an [`InstallError::StepFailed`] error on `sender`, naming the step.
+async fn run_install_commands<CR>(
+    package_name: &str,
+    env_config: &EnvironmentConfig,
+    config: &AppConfig,
+    command_runner: &CR,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> Result<(), InstallFailure>
+where
+    CR: CommandRunner,
+{
+    let commands = env_config.install().commands();
+    if let [command] = commands {
+        return match steps::execute_command(
+            command_runner,
+            command,
+            "install",
+            CommandKind::Install,
+            env_config,
+            config,
+            sender,
+            progress,
+        )
+        .await
+        {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(InstallFailure::Exited),
+            Err(err) => Err(InstallFailure::Error(err)),
+        };
+    }
+
+    let total = commands.len();
+    for (step, command) in (1..).zip(commands) {
+        let reason = match steps::execute_command(
+            command_runner,
+            command,
+            &format!("install step {step}/{total}"),
+            CommandKind::Install,
+            env_config,
+            config,
+            sender,
+            progress,
+        )
+        .await
+        {
+            Ok(true) => continue,
+            Ok(false) => "command exited unsuccessfully".to_string(),
+            Err(err) => err.into_owned(),
+        };
+
+        let error = InstallError::StepFailed {
+            package_name: package_name.to_string(),
+            step,
+            steps: total,
+            command: command.clone(),
+            reason,
+        };
+        sender
+            .send_error(
+                error.clone(),
+                format!("Package '{package_name}' install step {step}/{total} failed"),
+            )
+            .await;
+        return Err(InstallFailure::Step(error));
+    }
+
+    Ok(())
 }
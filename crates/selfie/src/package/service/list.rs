@@ -10,19 +10,22 @@ use crate::{
             EventSender, InvalidPackageInfo, OperationResult, PackageListData, PackageListItem,
         },
         port::PackageRepository,
+        state::StateStore,
     },
 };
 
-pub(super) async fn handle_list<PR, CR>(
+pub(super) async fn handle_list<PR, CR, SS>(
     repo: &PR,
     config: &AppConfig,
     _command_runner: &CR,
+    state_store: &SS,
     sender: &EventSender,
     progress: &mut crate::package::service::ProgressTracker,
 ) -> OperationResult
 where
     PR: PackageRepository,
     CR: CommandRunner,
+    SS: StateStore,
 {
     // Step 1: List all packages
     progress.next(sender, "Loading package list").await;
@@ -48,6 +51,18 @@ where
     let valid_packages: Vec<_> = list_output.valid_packages().collect();
     let invalid_packages: Vec<_> = list_output.invalid_packages().collect();
 
+    // Only installs in the current environment are shown next to each package
+    let mut install_records = match state_store.list_installs() {
+        Ok(records) => records,
+        Err(err) => {
+            sender
+                .send_warning(format!("Unable to read install state: {err}"))
+                .await;
+            Vec::new()
+        }
+    };
+    install_records.retain(|record| record.environment == config.environment());
+
     // Convert to structured data and sort by name
     let mut valid_package_items: Vec<PackageListItem> = valid_packages
         .iter()
@@ -55,6 +70,13 @@ where
             name: package.name().to_string(),
             version: package.version().to_string(),
             environments: package.environments().keys().cloned().collect(),
+            selected_environment: package
+                .select_environment(&config.environment_chain())
+                .map(|(name, _)| name.to_string()),
+            install_record: install_records
+                .iter()
+                .find(|record| record.package_name == package.name())
+                .cloned(),
         })
         .collect();
 
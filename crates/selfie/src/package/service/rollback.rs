@@ -0,0 +1,130 @@
+//! Helps break down the pieces of rolling back a failed install.
+
+use crate::{
+    commands::runner::CommandRunner,
+    config::AppConfig,
+    package::{
+        CommandKind, EnvironmentConfig,
+        event::{
+            EventSender, RollbackData, RollbackStep, RollbackStepResult, metadata::OperationType,
+        },
+    },
+};
+
+use super::{InstallError, ProgressTracker, steps};
+
+/// How rolling back a failed install went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum RollbackOutcome {
+    /// Every `rollback` command ran successfully
+    RolledBack,
+    /// The package has no `rollback` commands for the environment
+    NothingToRollBack,
+    /// A `rollback` command failed, so the rest were skipped
+    Failed,
+}
+
+impl RollbackOutcome {
+    /// Describe the outcome for the end of an install failure message
+    pub(super) fn describe(self) -> &'static str {
+        match self {
+            Self::RolledBack => "rolled back",
+            Self::NothingToRollBack => "no rollback commands to run",
+            Self::Failed => "rollback failed",
+        }
+    }
+}
+
+/// Run a package's `rollback` commands after its install command or one of its
+/// hooks failed
+///
+/// The commands run in order through their own
+/// [`OperationType::PackageRollback`] sub-operation, which ends with a
+/// [`RollbackData`] describing each command. The first failing command stops the
+/// rollback; it's reported as an [`InstallError::RollbackFailed`] error on
+/// `sender`, since the machine may now be left partly configured.
+pub(super) async fn rollback_install<CR>(
+    package_name: &str,
+    env_config: &EnvironmentConfig,
+    config: &AppConfig,
+    command_runner: &CR,
+    sender: &EventSender,
+) -> RollbackOutcome
+where
+    CR: CommandRunner,
+{
+    let commands = env_config.rollback();
+    if commands.is_empty() {
+        sender
+            .send_warning(format!(
+                "Package '{package_name}' has no `rollback` commands; nothing was rolled back"
+            ))
+            .await;
+        return RollbackOutcome::NothingToRollBack;
+    }
+
+    let rollback_sender = sender.child_operation(OperationType::PackageRollback, package_name);
+    let mut progress = ProgressTracker::new(commands.len() as u32);
+    let mut rollback_steps = Vec::with_capacity(commands.len());
+    let mut failure: Option<(&str, String)> = None;
+
+    for command in commands {
+        let result = if failure.is_some() {
+            RollbackStepResult::Skipped
+        } else {
+            match steps::execute_command(
+                command_runner,
+                command,
+                "rollback",
+                CommandKind::Other,
+                env_config,
+                config,
+                &rollback_sender,
+                &mut progress,
+            )
+            .await
+            {
+                Ok(true) => RollbackStepResult::Succeeded,
+                Ok(false) => {
+                    RollbackStepResult::Failed("command exited unsuccessfully".to_string())
+                }
+                Err(err) => RollbackStepResult::Failed(err.into_owned()),
+            }
+        };
+
+        if let RollbackStepResult::Failed(reason) = &result {
+            failure = Some((command, reason.clone()));
+        }
+        rollback_steps.push(RollbackStep {
+            command: command.clone(),
+            result,
+        });
+    }
+
+    rollback_sender
+        .send_rollback(RollbackData {
+            package_name: package_name.to_string(),
+            environment: config.environment().to_string(),
+            steps: rollback_steps,
+        })
+        .await;
+
+    if let Some((command, reason)) = failure {
+        sender
+            .send_error(
+                InstallError::RollbackFailed {
+                    package_name: package_name.to_string(),
+                    command: command.to_string(),
+                    reason,
+                },
+                format!("Rollback of package '{package_name}' failed"),
+            )
+            .await;
+        RollbackOutcome::Failed
+    } else {
+        sender
+            .send_debug(format!("Rolled back package `{package_name}`"))
+            .await;
+        RollbackOutcome::RolledBack
+    }
+}
@@ -1,10 +1,19 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, time::Duration};
 
 use crate::{
-    commands::runner::CommandRunner,
+    commands::runner::{CommandError, CommandOutput, CommandRunner},
     config::AppConfig,
+    fs::FileSystem,
     package::{
-        EnvironmentConfig, GetPackage, Package, event::EventSender, port::PackageRepository,
+        CommandKind, EnvironmentConfig, GetPackage, Package,
+        condition::Condition,
+        event::{EventSender, error::StreamedError},
+        graph::DependencyGraph,
+        port::{PackageRepoError, PackageRepository},
+        service::InstallError,
+        state::{InstallReason, InstallRecord, StateStore},
+        template::TemplateContext,
+        version::{self, Version},
     },
 };
 
@@ -36,13 +45,152 @@ where
     }
 }
 
+/// Load every valid package that supports the current environment, sorted by name
+///
+/// Invalid package files are reported as warnings and skipped, so one broken file
+/// doesn't stop an operation over all packages.
+pub async fn load_environment_packages<PR>(
+    repo: &PR,
+    config: &AppConfig,
+    sender: &EventSender,
+) -> Result<Vec<Package>, String>
+where
+    PR: PackageRepository,
+{
+    let list_output = match repo.list_packages() {
+        Ok(output) => output,
+        Err(err) => {
+            let error_msg = format!("Failed to list packages: {err}");
+            sender
+                .send_error(PackageRepoError::PackageListError(err), &error_msg)
+                .await;
+            return Err(error_msg);
+        }
+    };
+
+    for invalid_package in list_output.invalid_packages() {
+        sender
+            .send_warning(format!("Skipping invalid package file: {invalid_package}"))
+            .await;
+    }
+
+    let mut packages: Vec<Package> = list_output
+        .valid_packages()
+        .filter(|package| package.supports_environment(&config.environment_chain()))
+        .cloned()
+        .collect();
+    packages.sort_by(|a, b| a.name().cmp(b.name()));
+
+    sender
+        .send_debug(format!(
+            "Found {} package(s) for environment '{}'",
+            packages.len(),
+            config.environment()
+        ))
+        .await;
+
+    Ok(packages)
+}
+
+/// Which packages an operation over several packages should work on
+#[derive(Debug, Clone)]
+pub enum PackageSelection {
+    /// The named packages
+    Named(Vec<String>),
+    /// Every package that supports the current environment
+    All,
+}
+
+/// Step to load the packages picked by `selection`
+///
+/// Named packages that can't be loaded fail the whole operation, since nothing has
+/// run yet. When working on everything, only valid packages that support the
+/// current environment are picked.
+pub async fn load_packages<PR>(
+    selection: &PackageSelection,
+    repo: &PR,
+    config: &AppConfig,
+    sender: &EventSender,
+    progress: &mut crate::package::service::ProgressTracker,
+) -> Result<Vec<Package>, String>
+where
+    PR: PackageRepository,
+{
+    progress.next(sender, "Loading package definitions").await;
+
+    match selection {
+        PackageSelection::Named(names) => {
+            let mut packages = Vec::with_capacity(names.len());
+
+            for name in names {
+                match repo.get_package(name) {
+                    Ok(blob) => packages.push(blob.package),
+                    Err(err) => {
+                        let error_msg = format!("Failed to fetch package '{name}': {err}");
+                        sender.send_error(err, &error_msg).await;
+                        return Err(error_msg);
+                    }
+                }
+            }
+
+            Ok(packages)
+        }
+        PackageSelection::All => load_environment_packages(repo, config, sender).await,
+    }
+}
+
+/// Step to load the packages picked by `selection`, without letting one that can't
+/// be loaded stop the others
+///
+/// Like [`load_packages`], except that a named package that can't be loaded is
+/// reported on `sender` and returned with the reason, next to the packages that
+/// could be loaded.
+pub async fn load_each_package<PR>(
+    selection: &PackageSelection,
+    repo: &PR,
+    config: &AppConfig,
+    sender: &EventSender,
+    progress: &mut crate::package::service::ProgressTracker,
+) -> Result<(Vec<Package>, Vec<(String, String)>), String>
+where
+    PR: PackageRepository,
+{
+    let PackageSelection::Named(names) = selection else {
+        return load_packages(selection, repo, config, sender, progress)
+            .await
+            .map(|packages| (packages, Vec::new()));
+    };
+
+    progress.next(sender, "Loading package definitions").await;
+
+    let mut packages = Vec::with_capacity(names.len());
+    let mut failed = Vec::new();
+    for name in names {
+        match repo.get_package(name) {
+            Ok(blob) => packages.push(blob.package),
+            Err(err) => {
+                let error_msg = format!("Failed to fetch package '{name}': {err}");
+                sender.send_error(err, &error_msg).await;
+                failed.push((name.clone(), error_msg));
+            }
+        }
+    }
+
+    Ok((packages, failed))
+}
+
 /// Step to find environment configuration for a package
-pub async fn find_environment_config<'a>(
-    package: &'a Package,
-    environment: &str,
+///
+/// The package's block for the current environment is used, or else the block of
+/// the first of its fallbacks the package has. The block's command templates are
+/// expanded before it's returned.
+pub async fn find_environment_config(
+    package: &Package,
+    config: &AppConfig,
     sender: &EventSender,
     progress: &mut crate::package::service::ProgressTracker,
-) -> Result<&'a EnvironmentConfig, Cow<'static, str>> {
+) -> Result<EnvironmentConfig, Cow<'static, str>> {
+    let environment = config.environment();
     progress
         .next(
             sender,
@@ -50,21 +198,377 @@ pub async fn find_environment_config<'a>(
         )
         .await;
 
-    if let Some(env_config) = package.environments().get(environment) {
+    match package.environment_for(config) {
+        Ok(Some((selected, env_config))) => {
+            if selected == environment {
+                sender
+                    .send_trace("Current environment supported by package")
+                    .await;
+            } else {
+                sender
+                    .send_debug(format!(
+                        "Package '{}' has no '{environment}' environment, using '{selected}'",
+                        package.name()
+                    ))
+                    .await;
+            }
+            Ok(env_config)
+        }
+        Err(err) => {
+            let message = format!("Command template error: {err}");
+            sender
+                .send_error(err, "Unable to expand the package's commands")
+                .await;
+            Err(message.into())
+        }
+        Ok(None) => {
+            if let Some(err) = package.inheritance_errors().get(environment) {
+                sender
+                    .send_warning(format!(
+                        "Package '{}' can't resolve environment '{}': {err}",
+                        package.name(),
+                        environment
+                    ))
+                    .await;
+                Err(format!("Environment could not be resolved: {err}").into())
+            } else {
+                sender
+                    .send_warning(format!(
+                        "Package '{}' does not support environment '{}'",
+                        package.name(),
+                        config.environment_chain().join("', '")
+                    ))
+                    .await;
+                Err("Environment not supported".into())
+            }
+        }
+    }
+}
+
+/// Step to resolve the full dependency graph of one or more packages for the current
+/// environment and its fallbacks, with every package's command templates expanded
+pub async fn resolve_dependencies<PR>(
+    repo: &PR,
+    packages: &[Package],
+    config: &AppConfig,
+    sender: &EventSender,
+    progress: &mut crate::package::service::ProgressTracker,
+) -> Result<DependencyGraph, Cow<'static, str>>
+where
+    PR: PackageRepository,
+{
+    let names = package_names(packages);
+    progress
+        .next(sender, format!("Resolving dependencies of {names}"))
+        .await;
+
+    match resolve_graph(repo, packages, config) {
+        Ok(graph) => {
+            sender
+                .send_debug(format!(
+                    "Resolved {} package(s) in total for {names}",
+                    graph.len()
+                ))
+                .await;
+            Ok(graph)
+        }
+        Err(e) => {
+            let message = format!("Dependency resolution failed: {e}");
+            sender
+                .send_error(e, "Unable to resolve package dependencies")
+                .await;
+            Err(message.into())
+        }
+    }
+}
+
+/// Step to resolve one dependency graph for several packages, leaving out the
+/// ones whose dependencies can't be resolved
+///
+/// Each package is resolved on its own first, so a missing dependency or a cycle
+/// only fails the package it belongs to. Those packages are reported on `sender`
+/// and returned with the reason, next to the merged graph of the rest.
+pub async fn resolve_each_dependencies<PR>(
+    repo: &PR,
+    packages: &[Package],
+    config: &AppConfig,
+    sender: &EventSender,
+    progress: &mut crate::package::service::ProgressTracker,
+) -> Result<(DependencyGraph, Vec<(String, String)>), Cow<'static, str>>
+where
+    PR: PackageRepository,
+{
+    progress
+        .next(
+            sender,
+            format!("Resolving dependencies of {}", package_names(packages)),
+        )
+        .await;
+
+    let mut resolvable = Vec::with_capacity(packages.len());
+    let mut failed = Vec::new();
+    for package in packages {
+        match resolve_graph(repo, std::slice::from_ref(package), config) {
+            Ok(_) => resolvable.push(package.clone()),
+            Err(e) => {
+                let message = format!("Dependency resolution failed for '{}': {e}", package.name());
+                sender.send_error(e, &message).await;
+                failed.push((package.name().to_string(), message));
+            }
+        }
+    }
+
+    // Packages that resolve on their own also resolve together
+    match resolve_graph(repo, &resolvable, config) {
+        Ok(graph) => {
+            sender
+                .send_debug(format!(
+                    "Resolved {} package(s) in total, {} requested package(s) could not be resolved",
+                    graph.len(),
+                    failed.len()
+                ))
+                .await;
+            Ok((graph, failed))
+        }
+        Err(e) => {
+            let message = format!("Dependency resolution failed: {e}");
+            sender
+                .send_error(e, "Unable to resolve package dependencies")
+                .await;
+            Err(message.into())
+        }
+    }
+}
+
+/// Resolve and render the dependency graph of `packages`
+fn resolve_graph<PR>(
+    repo: &PR,
+    packages: &[Package],
+    config: &AppConfig,
+) -> Result<DependencyGraph, StreamedError>
+where
+    PR: PackageRepository,
+{
+    DependencyGraph::resolve_many(repo, packages, &config.environment_chain())
+        .map_err(StreamedError::from)?
+        .render_commands(&TemplateContext::new(config))
+        .map_err(StreamedError::from)
+}
+
+/// The names of `packages` for progress messages, e.g. `` `git`, `curl` ``
+fn package_names(packages: &[Package]) -> String {
+    packages
+        .iter()
+        .map(|package| format!("`{}`", package.name()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Evaluate a `when:` clause for the current machine
+///
+/// Returns why the conditions aren't met, or `None` when there are no conditions
+/// or they all hold.
+pub async fn unmet_condition<CR, F>(
+    when: Option<&Condition>,
+    command_runner: &CR,
+    file_system: &F,
+    config: &AppConfig,
+    sender: &EventSender,
+) -> Option<String>
+where
+    CR: CommandRunner,
+    F: FileSystem,
+{
+    let when = when?;
+    let unmet = when.unmet(command_runner, file_system, config.arch()).await;
+
+    match &unmet {
+        Some(reason) => {
+            sender
+                .send_debug(format!("Conditions `{when}` not met: {reason}"))
+                .await;
+        }
+        None => sender.send_trace(format!("Conditions `{when}` met")).await,
+    }
+
+    unmet
+}
+
+/// Step to run a package's `check` command to see if it's already installed
+///
+/// Returns `true` only when the check command exists and succeeds. A missing check
+/// command, a failing check, or a check that can't be run all mean the package
+/// should be (re)installed.
+pub async fn check_installed<CR>(
+    command_runner: &CR,
+    package_name: &str,
+    env_config: &EnvironmentConfig,
+    config: &AppConfig,
+    sender: &EventSender,
+    progress: &mut crate::package::service::ProgressTracker,
+) -> bool
+where
+    CR: CommandRunner,
+{
+    progress
+        .next(
+            sender,
+            format!("Checking if `{package_name}` is already installed"),
+        )
+        .await;
+
+    let Some(check_cmd) = env_config.check() else {
         sender
-            .send_trace("Current environment supported by package")
+            .send_debug("Package has no `check` command; assuming it is not installed")
             .await;
-        Ok(env_config)
+        return false;
+    };
+
+    let (timeout, timeout_setting) = command_timeout(env_config, CommandKind::Check, config);
+    match command_runner
+        .run(&env_config.command_request(check_cmd, timeout))
+        .await
+    {
+        Ok(output) if output.is_success() => {
+            sender
+                .send_debug(format!("Check command succeeded: `{check_cmd}`"))
+                .await;
+            true
+        }
+        Ok(output) => {
+            sender
+                .send_debug(format!(
+                    "Check command exited with code {}; package is not installed",
+                    output.exit_code()
+                ))
+                .await;
+            false
+        }
+        Err(error) => {
+            let timeout_note = timeout_note(&error, timeout_setting);
+            sender
+                .send_warning(format!(
+                    "Unable to run check command `{check_cmd}` ({error}{timeout_note}); continuing with installation"
+                ))
+                .await;
+            false
+        }
+    }
+}
+
+/// Step to run a package's `version_command` and pick the installed version out of
+/// its output
+///
+/// Returns `None` when the environment has no `version_command`.
+pub async fn installed_version<CR>(
+    command_runner: &CR,
+    env_config: &EnvironmentConfig,
+    config: &AppConfig,
+    sender: &EventSender,
+) -> Option<Result<Version, Cow<'static, str>>>
+where
+    CR: CommandRunner,
+{
+    let version_cmd = env_config.version_command()?;
+
+    let (timeout, timeout_setting) = command_timeout(env_config, CommandKind::Check, config);
+    let output = match command_runner
+        .run(&env_config.command_request(version_cmd, timeout))
+        .await
+    {
+        Ok(output) if output.is_success() => output,
+        Ok(output) => {
+            return Some(Err(format!(
+                "Version command `{version_cmd}` exited with code {}",
+                output.exit_code()
+            )
+            .into()));
+        }
+        Err(err) => {
+            return Some(Err(format!(
+                "Unable to run version command `{version_cmd}`: {err}{}",
+                timeout_note(&err, timeout_setting)
+            )
+            .into()));
+        }
+    };
+
+    // Plenty of tools print their version to stderr
+    let text = if output.stdout_str().trim().is_empty() {
+        output.stderr_str()
     } else {
+        output.stdout_str()
+    };
+
+    let result = version::extract_version(&text, env_config.version_regex())
+        .map_err(|err| Cow::Owned(err.to_string()));
+    if let Ok(version) = &result {
         sender
-            .send_warning(format!(
-                "Package '{}' does not support environment '{}'",
-                package.name(),
-                environment
-            ))
+            .send_debug(format!("Detected installed version {version}"))
             .await;
-        Err("Environment not supported".into())
     }
+    Some(result)
+}
+
+/// Step to re-run a package's `check` command after its install command succeeded
+///
+/// Catches install recipes that exit successfully without actually installing the
+/// package. Packages without a `check` command can't be verified, so they pass.
+pub async fn verify_installed<CR>(
+    command_runner: &CR,
+    package_name: &str,
+    env_config: &EnvironmentConfig,
+    config: &AppConfig,
+    sender: &EventSender,
+    progress: &mut crate::package::service::ProgressTracker,
+) -> Result<(), InstallError>
+where
+    CR: CommandRunner,
+{
+    progress
+        .next(sender, format!("Verifying `{package_name}` is installed"))
+        .await;
+
+    let Some(check_cmd) = env_config.check() else {
+        sender
+            .send_debug("Package has no `check` command; skipping verification")
+            .await;
+        return Ok(());
+    };
+
+    let (timeout, timeout_setting) = command_timeout(env_config, CommandKind::Check, config);
+    let error = match command_runner
+        .run(&env_config.command_request(check_cmd, timeout))
+        .await
+    {
+        Ok(output) if output.is_success() => {
+            sender
+                .send_debug(format!("Verified `{package_name}` with `{check_cmd}`"))
+                .await;
+            return Ok(());
+        }
+        Ok(output) => InstallError::NotDetected {
+            package_name: package_name.to_string(),
+            check_command: check_cmd.to_string(),
+            exit_code: output.exit_code(),
+        },
+        Err(source) => InstallError::VerificationFailed {
+            package_name: package_name.to_string(),
+            source: Box::new(source),
+        },
+    };
+
+    let timeout_note = match &error {
+        InstallError::VerificationFailed { source, .. } => timeout_note(source, timeout_setting),
+        _ => String::new(),
+    };
+    sender
+        .send_error(
+            error.clone(),
+            format!("Installation could not be verified{timeout_note}"),
+        )
+        .await;
+    Err(error)
 }
 
 /// Step to get a specific command from environment config
@@ -99,10 +603,21 @@ pub async fn get_command<'a>(
 }
 
 /// Step to execute a command
+///
+/// A command that exits unsuccessfully or times out is retried as many times as
+/// the environment's `retries` setting allows (falling back to the global one).
+/// The first retry waits `retry_delay`, and each further retry waits twice as
+/// long as the one before. Every attempt that's retried is reported as a warning.
+///
+/// The command may run as long as [`command_timeout`] allows for `kind`; a timeout
+/// is reported along with the setting that decided it.
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_command<CR>(
     command_runner: &CR,
     cmd: &str,
     command_type: &str,
+    kind: CommandKind,
+    env_config: &EnvironmentConfig,
     config: &AppConfig,
     sender: &EventSender,
     progress: &mut crate::package::service::ProgressTracker,
@@ -119,27 +634,52 @@ where
 
     progress.next(sender, step_message).await;
 
-    match command_runner
-        .execute_with_timeout(cmd, config.command_timeout())
-        .await
-    {
-        Ok(output) => {
-            if config.verbose() {
-                if !output.stdout_str().trim().is_empty() {
-                    sender
-                        .send_info(crate::package::event::ConsoleOutput::Stdout(
-                            output.stdout_str().to_string(),
-                        ))
-                        .await;
-                }
-                if !output.stderr_str().trim().is_empty() {
-                    sender
-                        .send_info(crate::package::event::ConsoleOutput::Stderr(
-                            output.stderr_str().to_string(),
-                        ))
-                        .await;
+    let attempts = env_config
+        .retries()
+        .unwrap_or(config.retries())
+        .saturating_add(1);
+    let mut delay = env_config.retry_delay().unwrap_or(config.retry_delay());
+    let (timeout, timeout_setting) = command_timeout(env_config, kind, config);
+    let mut attempt = 1;
+
+    let result = loop {
+        let result = command_runner
+            .run(&env_config.command_request(cmd, timeout))
+            .await;
+
+        let retry_reason = match &result {
+            Ok(output) if !output.is_success() => Some(format!("exit code {}", output.exit_code())),
+            Err(error @ CommandError::Timeout { .. }) => Some(error.to_string()),
+            _ => None,
+        };
+
+        match retry_reason {
+            Some(reason) if attempt < attempts => {
+                if let Ok(output) = &result {
+                    send_command_output(output, config, sender).await;
                 }
+                sender
+                    .send_warning(format!(
+                        "Attempt {attempt}/{attempts} of `{command_type}` command failed ({reason}); retrying in {delay:?}"
+                    ))
+                    .await;
+                tokio::time::sleep(delay).await;
+                delay = delay.saturating_mul(2);
+                attempt += 1;
             }
+            _ => break result,
+        }
+    };
+
+    let attempt_note = if attempts > 1 {
+        format!(" (attempt {attempt}/{attempts})")
+    } else {
+        String::new()
+    };
+
+    match result {
+        Ok(output) => {
+            send_command_output(&output, config, sender).await;
 
             if output.is_success() {
                 if is_final_execution {
@@ -155,7 +695,7 @@ where
             } else {
                 sender
                     .send_warning(format!(
-                        "Command failed at step {}/{}: exit code {}",
+                        "Command failed at step {}/{}: exit code {}{attempt_note}",
                         progress.current_step(),
                         progress.total_steps(),
                         output.exit_code()
@@ -165,18 +705,19 @@ where
             }
         }
         Err(error) => {
+            let timeout_note = timeout_note(&error, timeout_setting);
             sender
                 .send_error(
                     error,
                     format!(
-                        "Failed to execute {command_type} command at step {}/{}",
+                        "Failed to execute {command_type} command at step {}/{}{attempt_note}{timeout_note}",
                         progress.current_step(),
                         progress.total_steps()
                     ),
                 )
                 .await;
             Err(format!(
-                "Command execution failed: {} (step {}/{})",
+                "Command execution failed: {} (step {}/{}){timeout_note}",
                 command_type,
                 progress.current_step(),
                 progress.total_steps()
@@ -185,3 +726,124 @@ where
         }
     }
 }
+
+/// How long a `kind` command of the environment may run, with the name of the
+/// setting that says so
+///
+/// The environment's own settings win over the global `command_timeout`.
+pub fn command_timeout(
+    env_config: &EnvironmentConfig,
+    kind: CommandKind,
+    config: &AppConfig,
+) -> (Duration, &'static str) {
+    env_config
+        .timeout(kind)
+        .unwrap_or((config.command_timeout(), "command_timeout"))
+}
+
+/// Name the setting behind a timeout, so it's clear which one to raise; empty
+/// for any other error
+pub fn timeout_note(error: &CommandError, timeout_setting: &str) -> String {
+    if matches!(error, CommandError::Timeout { .. }) {
+        format!(" (limit set by `{timeout_setting}`)")
+    } else {
+        String::new()
+    }
+}
+
+/// Forward a command's output as info events when running verbosely
+async fn send_command_output(output: &CommandOutput, config: &AppConfig, sender: &EventSender) {
+    if !config.verbose() {
+        return;
+    }
+
+    if !output.stdout_str().trim().is_empty() {
+        sender
+            .send_info(crate::package::event::ConsoleOutput::Stdout(
+                output.stdout_str().to_string(),
+            ))
+            .await;
+    }
+    if !output.stderr_str().trim().is_empty() {
+        sender
+            .send_info(crate::package::event::ConsoleOutput::Stderr(
+                output.stderr_str().to_string(),
+            ))
+            .await;
+    }
+}
+
+/// Record that selfie installed a package in the current environment
+///
+/// A package that was already recorded as installed directly keeps that reason
+/// when it's reinstalled as a dependency. The install itself already succeeded, so
+/// failing to save the record only produces a warning.
+pub async fn record_install<SS>(
+    state_store: &SS,
+    package: &Package,
+    install_command: &str,
+    reason: InstallReason,
+    config: &AppConfig,
+    sender: &EventSender,
+) where
+    SS: StateStore,
+{
+    let environment = config.environment();
+    let reason = match state_store.get_install(package.name(), environment) {
+        Ok(Some(existing)) if existing.reason == InstallReason::Direct => InstallReason::Direct,
+        _ => reason,
+    };
+
+    let record = InstallRecord {
+        package_name: package.name().to_string(),
+        environment: environment.to_string(),
+        installed_at: chrono::Utc::now(),
+        version: package.version().to_string(),
+        install_command: install_command.to_string(),
+        reason,
+    };
+
+    match state_store.record_install(record) {
+        Ok(()) => {
+            sender
+                .send_trace(format!("Recorded install of `{}`", package.name()))
+                .await;
+        }
+        Err(err) => {
+            sender
+                .send_warning(format!(
+                    "Installed package '{}' but failed to record it: {err}",
+                    package.name()
+                ))
+                .await;
+        }
+    }
+}
+
+/// Forget that selfie installed a package in the current environment
+///
+/// Like [`record_install`], failing to update the state only produces a warning.
+pub async fn remove_install_record<SS>(
+    state_store: &SS,
+    package_name: &str,
+    config: &AppConfig,
+    sender: &EventSender,
+) where
+    SS: StateStore,
+{
+    match state_store.remove_install(package_name, config.environment()) {
+        Ok(Some(_)) => {
+            sender
+                .send_trace(format!("Removed install record of `{package_name}`"))
+                .await;
+        }
+        Ok(None) => {}
+        Err(err) => {
+            sender
+                .send_warning(format!(
+                    "Uninstalled package '{package_name}' but failed to update its install record: {err}"
+                ))
+                .await;
+        }
+    }
+}
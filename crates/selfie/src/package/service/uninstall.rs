@@ -0,0 +1,344 @@
+//! Helps break down the pieces of running the `package uninstall` command.
+
+use crate::{
+    commands::runner::CommandRunner,
+    config::AppConfig,
+    fs::FileSystem,
+    package::{
+        CommandKind, EnvironmentConfig,
+        event::{EventSender, OperationResult},
+        port::PackageRepository,
+        state::StateStore,
+    },
+};
+
+use super::{ProgressTracker, UninstallError, steps};
+
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn handle_uninstall<PR, CR, F, SS>(
+    package_name: &str,
+    repo: &PR,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    state_store: &SS,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> OperationResult
+where
+    PR: PackageRepository,
+    CR: CommandRunner,
+    F: FileSystem,
+    SS: StateStore,
+{
+    // Step 1: Fetch package (reusing shared step)
+    let package_blob = match steps::fetch_package(repo, package_name, sender, progress).await {
+        Ok(pkg) => pkg,
+        Err(err) => {
+            let error_msg = format!("Failed to fetch package '{package_name}': {err}");
+            return OperationResult::Failure(error_msg);
+        }
+    };
+
+    // Step 2: Find environment configuration (reusing shared step)
+    let env_config =
+        match steps::find_environment_config(&package_blob.package, config, sender, progress).await
+        {
+            Ok(config) => config,
+            Err(err) => {
+                let error_msg = format!("Environment configuration error: {err}");
+                return OperationResult::Failure(error_msg);
+            }
+        };
+    let env_config = &env_config;
+
+    // Nothing runs for an environment that doesn't apply to this machine
+    if let Some(reason) = steps::unmet_condition(
+        env_config.when(),
+        command_runner,
+        file_system,
+        config,
+        sender,
+    )
+    .await
+    {
+        return OperationResult::Skipped(format!("Package '{package_name}' was skipped: {reason}"));
+    }
+
+    // Step 3: Warn about installed packages that will lose a dependency
+    warn_installed_dependents(
+        repo,
+        package_name,
+        config,
+        command_runner,
+        file_system,
+        sender,
+        progress,
+    )
+    .await;
+
+    // Step 4: Check the package is installed; without a `check` command there's
+    // no way to tell, so go ahead with the uninstall
+    if env_config.check().is_none() {
+        progress
+            .next(
+                sender,
+                "Skipping installation check (no `check` command defined)",
+            )
+            .await;
+    } else if !steps::check_installed(
+        command_runner,
+        package_name,
+        env_config,
+        config,
+        sender,
+        progress,
+    )
+    .await
+    {
+        return OperationResult::NotInstalled(format!("Package '{package_name}' is not installed"));
+    }
+
+    // Step 5: Get uninstall command (reusing shared step with custom getter function)
+    let uninstall_cmd = match steps::get_command(
+        env_config,
+        "uninstall",
+        EnvironmentConfig::uninstall,
+        sender,
+        progress,
+    )
+    .await
+    {
+        Ok(cmd) => cmd,
+        Err(err) => {
+            let error_msg = format!("Uninstall command error: {err}");
+            return OperationResult::Failure(error_msg);
+        }
+    };
+
+    // Step 6: Execute uninstall command (reusing shared step)
+    let is_success = match steps::execute_command(
+        command_runner,
+        uninstall_cmd,
+        "uninstall",
+        CommandKind::Other,
+        env_config,
+        config,
+        sender,
+        progress,
+    )
+    .await
+    {
+        Ok(success) => success,
+        Err(err) => {
+            let error_msg = format!("Command execution error: {err}");
+            return OperationResult::Failure(error_msg);
+        }
+    };
+
+    if !is_success {
+        sender
+            .send_warning(format!("Package '{package_name}' uninstall command failed"))
+            .await;
+        return OperationResult::Failure(format!(
+            "Uninstall failed at step {}/{}",
+            progress.current_step(),
+            progress.total_steps()
+        ));
+    }
+
+    // Step 7: Make sure the package can no longer be detected
+    if let Err(err) = verify_uninstalled(
+        command_runner,
+        package_name,
+        env_config,
+        config,
+        sender,
+        progress,
+    )
+    .await
+    {
+        return OperationResult::Failure(err.to_string());
+    }
+
+    steps::remove_install_record(state_store, package_name, config, sender).await;
+
+    OperationResult::Success(format!(
+        "Uninstall completed successfully ({}/{} steps)",
+        progress.current_step(),
+        progress.total_steps()
+    ))
+}
+
+/// Warn when packages that depend on `package_name` in the current environment
+/// are installed, since uninstalling it will probably break them
+///
+/// This never stops the uninstall; it only tells the user what might break.
+/// Dependents whose `when:` clause doesn't hold on this machine are skipped,
+/// since they can't be installed here.
+async fn warn_installed_dependents<PR, CR, F>(
+    repo: &PR,
+    package_name: &str,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) where
+    PR: PackageRepository,
+    CR: CommandRunner,
+    F: FileSystem,
+{
+    progress
+        .next(
+            sender,
+            format!("Checking for installed packages that depend on `{package_name}`"),
+        )
+        .await;
+
+    let dependents = match repo.find_dependent_packages(package_name) {
+        Ok(dependents) => dependents,
+        Err(err) => {
+            sender
+                .send_warning(format!(
+                    "Unable to look for packages that depend on '{package_name}': {err}"
+                ))
+                .await;
+            return;
+        }
+    };
+
+    let mut installed_dependents = Vec::new();
+
+    for dependent in &dependents {
+        let Some(env_config) = dependent
+            .environment_for(config)
+            .ok()
+            .flatten()
+            .map(|(_, env_config)| env_config)
+            .filter(|env_config| {
+                env_config
+                    .dependencies()
+                    .iter()
+                    .any(|dependency| dependency == package_name)
+            })
+        else {
+            continue;
+        };
+        let Some(check_cmd) = env_config.check() else {
+            continue;
+        };
+        if steps::unmet_condition(
+            env_config.when(),
+            command_runner,
+            file_system,
+            config,
+            sender,
+        )
+        .await
+        .is_some()
+        {
+            continue;
+        }
+
+        let (timeout, timeout_setting) =
+            steps::command_timeout(&env_config, CommandKind::Check, config);
+        match command_runner
+            .run(&env_config.command_request(check_cmd, timeout))
+            .await
+        {
+            Ok(output) if output.is_success() => {
+                installed_dependents.push(dependent.name().to_string());
+            }
+            Ok(_) => {}
+            Err(error) => {
+                sender
+                    .send_debug(format!(
+                        "Unable to run check command for dependent '{}': {error}{}",
+                        dependent.name(),
+                        steps::timeout_note(&error, timeout_setting)
+                    ))
+                    .await;
+            }
+        }
+    }
+
+    if installed_dependents.is_empty() {
+        sender
+            .send_debug(format!("No installed packages depend on '{package_name}'"))
+            .await;
+    } else {
+        installed_dependents.sort();
+        sender
+            .send_warning(format!(
+                "Package '{package_name}' is a dependency of installed package(s): {}",
+                installed_dependents.join(", ")
+            ))
+            .await;
+    }
+}
+
+/// Re-run a package's `check` command after its uninstall command succeeded
+///
+/// The check should now fail. Packages without a `check` command can't be
+/// verified, so they pass.
+async fn verify_uninstalled<CR>(
+    command_runner: &CR,
+    package_name: &str,
+    env_config: &EnvironmentConfig,
+    config: &AppConfig,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> Result<(), UninstallError>
+where
+    CR: CommandRunner,
+{
+    progress
+        .next(sender, format!("Verifying `{package_name}` was removed"))
+        .await;
+
+    let Some(check_cmd) = env_config.check() else {
+        sender
+            .send_debug("Package has no `check` command; skipping verification")
+            .await;
+        return Ok(());
+    };
+
+    let (timeout, timeout_setting) = steps::command_timeout(env_config, CommandKind::Check, config);
+    let error = match command_runner
+        .run(&env_config.command_request(check_cmd, timeout))
+        .await
+    {
+        Ok(output) if !output.is_success() => {
+            sender
+                .send_debug(format!(
+                    "Check command `{check_cmd}` exited with code {}; package was removed",
+                    output.exit_code()
+                ))
+                .await;
+            return Ok(());
+        }
+        Ok(_) => UninstallError::StillDetected {
+            package_name: package_name.to_string(),
+            check_command: check_cmd.to_string(),
+        },
+        Err(source) => UninstallError::VerificationFailed {
+            package_name: package_name.to_string(),
+            source: Box::new(source),
+        },
+    };
+
+    let timeout_note = match &error {
+        UninstallError::VerificationFailed { source, .. } => {
+            steps::timeout_note(source, timeout_setting)
+        }
+        UninstallError::StillDetected { .. } => String::new(),
+    };
+    sender
+        .send_error(
+            error.clone(),
+            format!("Uninstall could not be verified{timeout_note}"),
+        )
+        .await;
+    Err(error)
+}
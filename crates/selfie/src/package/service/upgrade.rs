@@ -0,0 +1,323 @@
+//! Helps break down the pieces of running the `package upgrade` command.
+
+use crate::{
+    commands::runner::CommandRunner,
+    config::AppConfig,
+    fs::FileSystem,
+    package::{
+        CommandKind, EnvironmentConfig,
+        event::{
+            EventSender, OperationResult, UpgradeResult, UpgradeResultData, UpgradeSummaryData,
+        },
+        port::PackageRepository,
+    },
+};
+
+use super::{ProgressTracker, steps};
+
+/// Number of progress steps used to upgrade one package: find the update command,
+/// check, update, and verify
+pub(super) const STEPS_PER_UPGRADE: u32 = 4;
+
+pub(super) async fn handle_upgrade<PR, CR, F>(
+    package_name: &str,
+    repo: &PR,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> OperationResult
+where
+    PR: PackageRepository,
+    CR: CommandRunner,
+    F: FileSystem,
+{
+    // Step 1: Fetch package (reusing shared step)
+    let package_blob = match steps::fetch_package(repo, package_name, sender, progress).await {
+        Ok(pkg) => pkg,
+        Err(err) => {
+            let error_msg = format!("Failed to fetch package '{package_name}': {err}");
+            return OperationResult::Failure(error_msg);
+        }
+    };
+
+    // Step 2: Find environment configuration (reusing shared step)
+    let env_config =
+        match steps::find_environment_config(&package_blob.package, config, sender, progress).await
+        {
+            Ok(config) => config,
+            Err(err) => {
+                let error_msg = format!("Environment configuration error: {err}");
+                return OperationResult::Failure(error_msg);
+            }
+        };
+    let env_config = &env_config;
+
+    // Steps 3-6: Check, update, and verify the package
+    let result = upgrade_package(
+        package_name,
+        env_config,
+        config,
+        command_runner,
+        file_system,
+        sender,
+        progress,
+    )
+    .await;
+
+    sender
+        .send_upgrade_result(upgrade_result_data(
+            package_name,
+            env_config,
+            config,
+            result.clone(),
+        ))
+        .await;
+
+    match result {
+        UpgradeResult::Upgraded => OperationResult::Success(format!(
+            "Upgrade completed successfully ({}/{} steps)",
+            progress.current_step(),
+            progress.total_steps()
+        )),
+        UpgradeResult::NotUpgradable => OperationResult::Failure(format!(
+            "Package '{package_name}' is not upgradable: no `update` command for environment '{}'",
+            config.environment()
+        )),
+        UpgradeResult::NotInstalled => OperationResult::NotInstalled(format!(
+            "Package '{package_name}' is not installed; install it instead of upgrading it"
+        )),
+        UpgradeResult::Failed(message) => OperationResult::Failure(message),
+        UpgradeResult::Skipped(reason) => {
+            OperationResult::Skipped(format!("Package '{package_name}' was skipped: {reason}"))
+        }
+    }
+}
+
+/// Upgrade every package that supports the current environment
+///
+/// Sends an [`UpgradeResultData`] for each package as it's upgraded and an
+/// [`UpgradeSummaryData`] at the end. Packages without an `update` command,
+/// packages that aren't installed, and packages whose `when:` conditions aren't
+/// met are left alone; only failed upgrades fail the
+/// operation. With [`AppConfig::stop_on_error`] set, the first failure stops the
+/// upgrades and the remaining packages are reported as skipped.
+pub(super) async fn handle_upgrade_all<PR, CR, F>(
+    repo: &PR,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> OperationResult
+where
+    PR: PackageRepository,
+    CR: CommandRunner,
+    F: FileSystem,
+{
+    progress.next(sender, "Loading package definitions").await;
+
+    // Step 1: Find every package that supports the current environment
+    let packages = match steps::load_environment_packages(repo, config, sender).await {
+        Ok(packages) => packages,
+        Err(error_msg) => return OperationResult::Failure(error_msg),
+    };
+    progress.add_steps(packages.len() as u32);
+
+    // Step 2: Upgrade each package, reporting each one's own steps separately
+    let current_env = config.environment();
+    let mut summary = UpgradeSummaryData {
+        environment: current_env.to_string(),
+        ..UpgradeSummaryData::default()
+    };
+
+    for (i, package) in packages.iter().enumerate() {
+        let package_name = package.name();
+        progress
+            .next(sender, format!("Upgrading package `{package_name}`"))
+            .await;
+
+        let env_config = match package.environment_for(config) {
+            Ok(Some((_, env_config))) => env_config,
+            Ok(None) => continue,
+            Err(err) => {
+                let error_msg =
+                    format!("Could not expand commands of package '{package_name}': {err}");
+                sender.send_error(err, &error_msg).await;
+                summary.failed.push(package_name.to_string());
+                continue;
+            }
+        };
+
+        let package_sender = sender.child(package_name);
+        let mut package_progress = ProgressTracker::new(STEPS_PER_UPGRADE);
+        let result = upgrade_package(
+            package_name,
+            &env_config,
+            config,
+            command_runner,
+            file_system,
+            &package_sender,
+            &mut package_progress,
+        )
+        .await;
+
+        let failed = matches!(result, UpgradeResult::Failed(_));
+        let upgrade_result = upgrade_result_data(package_name, &env_config, config, result);
+        summary.record(&upgrade_result);
+        sender.send_upgrade_result(upgrade_result).await;
+
+        if failed && config.stop_on_error() {
+            sender
+                .send_warning(format!(
+                    "Stopping after package '{package_name}' failed to upgrade"
+                ))
+                .await;
+            summary.skipped = packages[i + 1..]
+                .iter()
+                .map(|package| package.name().to_string())
+                .collect();
+            break;
+        }
+    }
+
+    // Step 3: Summarize
+    progress.next(sender, "Summarizing upgrades").await;
+    let message = format!(
+        "Upgrade finished: {} upgraded, {} not upgradable, {} not installed, {} failed, {} skipped",
+        summary.upgraded.len(),
+        summary.not_upgradable.len(),
+        summary.not_installed.len(),
+        summary.failed.len(),
+        summary.skipped.len() + summary.not_applicable.len()
+    );
+    let succeeded = summary.failed.is_empty() && summary.skipped.is_empty();
+    sender.send_upgrade_summary(summary).await;
+
+    if succeeded {
+        OperationResult::Success(message)
+    } else {
+        OperationResult::Failure(message)
+    }
+}
+
+/// Run a package's `update` command, if it has one and is installed
+///
+/// Packages without an `update` command are never reinstalled in its place; they're
+/// reported as [`UpgradeResult::NotUpgradable`]. After a successful update, the
+/// `check` command is run again to make sure the update didn't break the package.
+/// Nothing runs when the environment's `when:` conditions aren't met.
+async fn upgrade_package<CR, F>(
+    package_name: &str,
+    env_config: &EnvironmentConfig,
+    config: &AppConfig,
+    command_runner: &CR,
+    file_system: &F,
+    sender: &EventSender,
+    progress: &mut ProgressTracker,
+) -> UpgradeResult
+where
+    CR: CommandRunner,
+    F: FileSystem,
+{
+    if let Some(reason) = steps::unmet_condition(
+        env_config.when(),
+        command_runner,
+        file_system,
+        config,
+        sender,
+    )
+    .await
+    {
+        return UpgradeResult::Skipped(reason);
+    }
+
+    let Ok(update_cmd) = steps::get_command(
+        env_config,
+        "update",
+        EnvironmentConfig::update,
+        sender,
+        progress,
+    )
+    .await
+    else {
+        sender
+            .send_warning(format!(
+                "Package '{package_name}' is not upgradable: no `update` command defined"
+            ))
+            .await;
+        return UpgradeResult::NotUpgradable;
+    };
+
+    // Without a `check` command there's no way to tell if the package is
+    // installed, so go ahead with the update
+    if env_config.check().is_none() {
+        progress
+            .next(
+                sender,
+                "Skipping installation check (no `check` command defined)",
+            )
+            .await;
+    } else if !steps::check_installed(
+        command_runner,
+        package_name,
+        env_config,
+        config,
+        sender,
+        progress,
+    )
+    .await
+    {
+        return UpgradeResult::NotInstalled;
+    }
+
+    match steps::execute_command(
+        command_runner,
+        update_cmd,
+        "update",
+        CommandKind::Other,
+        env_config,
+        config,
+        sender,
+        progress,
+    )
+    .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return UpgradeResult::Failed(format!(
+                "Package '{package_name}' update command failed"
+            ));
+        }
+        Err(err) => return UpgradeResult::Failed(format!("Command execution error: {err}")),
+    }
+
+    match steps::verify_installed(
+        command_runner,
+        package_name,
+        env_config,
+        config,
+        sender,
+        progress,
+    )
+    .await
+    {
+        Ok(()) => UpgradeResult::Upgraded,
+        Err(err) => UpgradeResult::Failed(err.to_string()),
+    }
+}
+
+fn upgrade_result_data(
+    package_name: &str,
+    env_config: &EnvironmentConfig,
+    config: &AppConfig,
+    result: UpgradeResult,
+) -> UpgradeResultData {
+    UpgradeResultData {
+        package_name: package_name.to_string(),
+        environment: config.environment().to_string(),
+        update_command: env_config.update().map(ToString::to_string),
+        result,
+    }
+}
@@ -46,7 +46,13 @@ where
     // Step 2: Validate the package for the current environment
     progress.next(sender, "Validating package definition").await;
 
-    let validation_result = package_blob.package.validate(config.environment());
+    let mut validation_result = package_blob
+        .package
+        .validate_with_repository(&config.environment_chain(), repo);
+
+    let mut all_issues = validation_result.issues.all_issues().to_vec();
+    all_issues.extend(package_blob.package.validate_templates(config.vars()));
+    validation_result.issues = all_issues.into();
     let issues = validation_result.issues();
 
     // Step 3: Process validation results
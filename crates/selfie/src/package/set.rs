@@ -0,0 +1,199 @@
+//! Named sets of packages
+//!
+//! Different machines need different groups of packages: a work laptop, a
+//! personal machine, a CI image. The config's `sets` map gives those groups names
+//! (e.g. `dev: [git, ripgrep, neovim]`) so they can be installed or checked
+//! together. This module looks sets up and merges their members, so a package
+//! that belongs to several sets is only worked on once.
+
+use thiserror::Error;
+
+use crate::config::AppConfig;
+
+/// A named group of packages from the config's `sets`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageSet {
+    pub name: String,
+    /// Names of the packages in the set, in the order the config lists them
+    pub members: Vec<String>,
+}
+
+/// Errors looking up package sets
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SetError {
+    /// No set with the given name is defined
+    #[error("Package set '{name}' is not defined; available sets: {}", available.join(", "))]
+    NotFound {
+        name: String,
+        available: Vec<String>,
+    },
+
+    /// The config doesn't define any sets
+    #[error("No package sets are defined; add a `sets` map to your config file")]
+    NoSets,
+}
+
+impl PackageSet {
+    /// Look up the named sets, in the order given
+    ///
+    /// A set named more than once is only returned once.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SetError::NoSets`] if the config defines no sets, or
+    /// [`SetError::NotFound`] for the first name that isn't defined.
+    pub fn resolve(config: &AppConfig, names: &[String]) -> Result<Vec<Self>, SetError> {
+        if config.sets().is_empty() {
+            return Err(SetError::NoSets);
+        }
+
+        let mut sets: Vec<Self> = Vec::with_capacity(names.len());
+
+        for name in names {
+            if sets.iter().any(|set| &set.name == name) {
+                continue;
+            }
+
+            let members = config.sets().get(name).ok_or_else(|| SetError::NotFound {
+                name: name.clone(),
+                available: config.sets().keys().cloned().collect(),
+            })?;
+            sets.push(Self {
+                name: name.clone(),
+                members: members.clone(),
+            });
+        }
+
+        Ok(sets)
+    }
+
+    /// Every set defined in the config, sorted by name
+    #[must_use]
+    pub fn all(config: &AppConfig) -> Vec<Self> {
+        config
+            .sets()
+            .iter()
+            .map(|(name, members)| Self {
+                name: name.clone(),
+                members: members.clone(),
+            })
+            .collect()
+    }
+
+    /// The members of several sets, each package listed once, in the order
+    /// they're first seen
+    #[must_use]
+    pub fn unique_members(sets: &[Self]) -> Vec<String> {
+        let mut members: Vec<String> = Vec::new();
+
+        for member in sets.iter().flat_map(|set| &set.members) {
+            if !members.contains(member) {
+                members.push(member.clone());
+            }
+        }
+
+        members
+    }
+
+    /// Members of this set that appear in `package_names`, in set order
+    #[must_use]
+    pub fn members_in(&self, package_names: &[String]) -> Vec<&str> {
+        self.members
+            .iter()
+            .filter(|member| package_names.contains(member))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfigBuilder;
+
+    fn config_with_sets() -> AppConfig {
+        AppConfigBuilder::default()
+            .environment("test-env")
+            .package_directory("/test/path")
+            .set("dev", vec!["git", "ripgrep", "neovim"])
+            .set("ci", vec!["git", "curl"])
+            .build()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_resolve_sets_in_requested_order() {
+        let config = config_with_sets();
+
+        let sets = PackageSet::resolve(&config, &names(&["dev", "ci", "dev"])).unwrap();
+
+        let set_names: Vec<_> = sets.iter().map(|set| set.name.as_str()).collect();
+        assert_eq!(set_names, ["dev", "ci"]);
+        assert_eq!(sets[1].members, ["git", "curl"]);
+    }
+
+    #[test]
+    fn test_resolve_unknown_set() {
+        let config = config_with_sets();
+
+        let err = PackageSet::resolve(&config, &names(&["dev", "work"])).unwrap_err();
+
+        assert_eq!(
+            err,
+            SetError::NotFound {
+                name: "work".to_string(),
+                available: names(&["ci", "dev"]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_without_sets() {
+        let config = AppConfigBuilder::default()
+            .environment("test-env")
+            .package_directory("/test/path")
+            .build();
+
+        assert_eq!(
+            PackageSet::resolve(&config, &names(&["dev"])),
+            Err(SetError::NoSets)
+        );
+    }
+
+    #[test]
+    fn test_unique_members_shared_between_sets() {
+        let config = config_with_sets();
+        let sets = PackageSet::resolve(&config, &names(&["dev", "ci"])).unwrap();
+
+        assert_eq!(
+            PackageSet::unique_members(&sets),
+            ["git", "ripgrep", "neovim", "curl"]
+        );
+    }
+
+    #[test]
+    fn test_members_in() {
+        let config = config_with_sets();
+        let dev = &PackageSet::resolve(&config, &names(&["dev"])).unwrap()[0];
+
+        assert_eq!(
+            dev.members_in(&names(&["curl", "neovim", "git"])),
+            ["git", "neovim"]
+        );
+        assert!(dev.members_in(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_all_sets_sorted_by_name() {
+        let config = config_with_sets();
+
+        let set_names: Vec<_> = PackageSet::all(&config)
+            .into_iter()
+            .map(|set| set.name)
+            .collect();
+        assert_eq!(set_names, ["ci", "dev"]);
+    }
+}
@@ -0,0 +1,111 @@
+//! Install state port and types
+//!
+//! Selfie package files describe how to install things, but not what has been
+//! installed. This module defines the `StateStore` port, which keeps a record of
+//! every package selfie installed, per environment, so later runs (and other
+//! commands) can tell what selfie put on the machine and why.
+
+pub mod json;
+
+pub use self::json::JsonStateStore;
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::fs::filesystem::FileSystemError;
+
+/// A record of selfie installing a package in an environment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallRecord {
+    pub package_name: String,
+    pub environment: String,
+    /// When the install command finished
+    pub installed_at: DateTime<Utc>,
+    /// The `version` of the package file used for the install
+    pub version: String,
+    /// The exact command that installed the package
+    pub install_command: String,
+    pub reason: InstallReason,
+}
+
+/// Why selfie installed a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallReason {
+    /// The package was asked for by name, or as part of installing everything
+    Direct,
+    /// The package was installed because another package depends on it
+    Dependency,
+}
+
+/// Port for storing install state (Hexagonal Architecture)
+///
+/// This trait abstracts where and how install records are kept, so the service
+/// layer can record and query installs without caring about the storage format.
+/// There's at most one record per package and environment.
+///
+/// Implementations might include:
+/// - JSON files in the user data directory
+/// - In-memory stores for testing
+#[cfg_attr(test, mockall::automock)]
+pub trait StateStore: Send + Sync {
+    /// Get the install record of a package in an environment, if selfie installed it
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StateStoreError`] if the stored state can't be read or parsed
+    fn get_install(
+        &self,
+        package_name: &str,
+        environment: &str,
+    ) -> Result<Option<InstallRecord>, StateStoreError>;
+
+    /// List every install record, sorted by package name and then environment
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StateStoreError`] if the stored state can't be read or parsed
+    fn list_installs(&self) -> Result<Vec<InstallRecord>, StateStoreError>;
+
+    /// Save an install record, replacing any existing record for the same package
+    /// and environment
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StateStoreError`] if the stored state can't be read or written
+    fn record_install(&self, record: InstallRecord) -> Result<(), StateStoreError>;
+
+    /// Remove the install record of a package in an environment
+    ///
+    /// # Returns
+    ///
+    /// The removed record, or `None` if there wasn't one
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StateStoreError`] if the stored state can't be read or written
+    fn remove_install(
+        &self,
+        package_name: &str,
+        environment: &str,
+    ) -> Result<Option<InstallRecord>, StateStoreError>;
+}
+
+/// Errors that can occur while reading or writing install state
+#[derive(Error, Debug, Clone)]
+pub enum StateStoreError {
+    /// The state file couldn't be read or written
+    #[error(transparent)]
+    FileSystemError(#[from] FileSystemError),
+
+    /// The state file exists but isn't valid
+    #[error("Invalid state file: {0}")]
+    ParseError(Arc<serde_json::Error>),
+
+    /// The state couldn't be turned into the storage format
+    #[error("Unable to serialize state: {0}")]
+    SerializeError(Arc<serde_json::Error>),
+}
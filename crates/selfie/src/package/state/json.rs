@@ -0,0 +1,277 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fs::FileSystem,
+    package::state::{InstallRecord, StateStore, StateStoreError},
+};
+
+/// Name of the file, inside the state directory, that holds install records
+pub const STATE_FILE_NAME: &str = "state.json";
+
+/// Version of the state file format written by this store
+const STATE_FORMAT_VERSION: u32 = 1;
+
+/// Stores install records in a single JSON file
+///
+/// Every change reads the whole file, updates it, and writes it back. Changes
+/// made through clones of the same store are serialized, so concurrent installs
+/// don't lose each other's records.
+#[derive(Debug, Clone)]
+pub struct JsonStateStore<F: FileSystem> {
+    fs: F,
+    path: PathBuf,
+    write_lock: Arc<Mutex<()>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StateFile {
+    version: u32,
+    #[serde(default)]
+    installs: Vec<InstallRecord>,
+}
+
+impl Default for StateFile {
+    fn default() -> Self {
+        Self {
+            version: STATE_FORMAT_VERSION,
+            installs: Vec::new(),
+        }
+    }
+}
+
+impl<F: FileSystem> JsonStateStore<F> {
+    /// Create a store that keeps its state file in `state_directory`
+    ///
+    /// Nothing is created until the first install is recorded.
+    pub fn new(fs: F, state_directory: &Path) -> Self {
+        Self {
+            fs,
+            path: state_directory.join(STATE_FILE_NAME),
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Path of the JSON file holding the install records
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<StateFile, StateStoreError> {
+        if !self.fs.path_exists(&self.path) {
+            return Ok(StateFile::default());
+        }
+
+        let content = self.fs.read_file(&self.path)?;
+        serde_json::from_str(&content).map_err(|e| StateStoreError::ParseError(Arc::new(e)))
+    }
+
+    fn save(&self, mut state: StateFile) -> Result<(), StateStoreError> {
+        state.version = STATE_FORMAT_VERSION;
+        state.installs.sort_by(|a, b| {
+            (&a.package_name, &a.environment).cmp(&(&b.package_name, &b.environment))
+        });
+
+        let data = serde_json::to_vec_pretty(&state)
+            .map_err(|e| StateStoreError::SerializeError(Arc::new(e)))?;
+        self.fs.write_file(&self.path, &data)?;
+        Ok(())
+    }
+
+    /// Run a read-modify-write cycle while holding the store's write lock
+    fn update<T>(&self, f: impl FnOnce(&mut StateFile) -> T) -> Result<T, StateStoreError> {
+        let _guard = self
+            .write_lock
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        let mut state = self.load()?;
+        let output = f(&mut state);
+        self.save(state)?;
+        Ok(output)
+    }
+}
+
+impl<F: FileSystem> StateStore for JsonStateStore<F> {
+    fn get_install(
+        &self,
+        package_name: &str,
+        environment: &str,
+    ) -> Result<Option<InstallRecord>, StateStoreError> {
+        Ok(self
+            .load()?
+            .installs
+            .into_iter()
+            .find(|r| r.package_name == package_name && r.environment == environment))
+    }
+
+    fn list_installs(&self) -> Result<Vec<InstallRecord>, StateStoreError> {
+        let mut installs = self.load()?.installs;
+        installs.sort_by(|a, b| {
+            (&a.package_name, &a.environment).cmp(&(&b.package_name, &b.environment))
+        });
+        Ok(installs)
+    }
+
+    fn record_install(&self, record: InstallRecord) -> Result<(), StateStoreError> {
+        self.update(|state| {
+            state.installs.retain(|r| {
+                r.package_name != record.package_name || r.environment != record.environment
+            });
+            state.installs.push(record);
+        })
+    }
+
+    fn remove_install(
+        &self,
+        package_name: &str,
+        environment: &str,
+    ) -> Result<Option<InstallRecord>, StateStoreError> {
+        self.update(|state| {
+            let index = state
+                .installs
+                .iter()
+                .position(|r| r.package_name == package_name && r.environment == environment)?;
+            Some(state.installs.remove(index))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{fs::real::RealFileSystem, package::state::InstallReason};
+
+    fn record(package_name: &str, environment: &str, reason: InstallReason) -> InstallRecord {
+        InstallRecord {
+            package_name: package_name.to_string(),
+            environment: environment.to_string(),
+            installed_at: Utc.with_ymd_and_hms(2025, 3, 14, 9, 26, 53).unwrap(),
+            version: "1.0.0".to_string(),
+            install_command: format!("brew install {package_name}"),
+            reason,
+        }
+    }
+
+    #[test]
+    fn test_empty_store_without_state_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = JsonStateStore::new(RealFileSystem, temp_dir.path());
+
+        assert!(store.list_installs().unwrap().is_empty());
+        assert!(store.get_install("ripgrep", "macos").unwrap().is_none());
+        assert!(!store.path().exists());
+    }
+
+    #[test]
+    fn test_record_and_get_install() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = JsonStateStore::new(RealFileSystem, &temp_dir.path().join("state"));
+
+        let ripgrep = record("ripgrep", "macos", InstallReason::Direct);
+        store.record_install(ripgrep.clone()).unwrap();
+
+        assert_eq!(
+            store.get_install("ripgrep", "macos").unwrap(),
+            Some(ripgrep)
+        );
+        assert!(store.get_install("ripgrep", "linux").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_record_install_replaces_existing_record() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = JsonStateStore::new(RealFileSystem, temp_dir.path());
+
+        store
+            .record_install(record("ripgrep", "macos", InstallReason::Dependency))
+            .unwrap();
+        let mut updated = record("ripgrep", "macos", InstallReason::Direct);
+        updated.version = "2.0.0".to_string();
+        store.record_install(updated.clone()).unwrap();
+
+        assert_eq!(store.list_installs().unwrap(), vec![updated]);
+    }
+
+    #[test]
+    fn test_list_installs_sorted() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = JsonStateStore::new(RealFileSystem, temp_dir.path());
+
+        store
+            .record_install(record("zsh", "macos", InstallReason::Direct))
+            .unwrap();
+        store
+            .record_install(record("fd", "macos", InstallReason::Dependency))
+            .unwrap();
+        store
+            .record_install(record("fd", "linux", InstallReason::Direct))
+            .unwrap();
+
+        let installs: Vec<_> = store
+            .list_installs()
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.package_name, r.environment))
+            .collect();
+        assert_eq!(
+            installs,
+            vec![
+                ("fd".to_string(), "linux".to_string()),
+                ("fd".to_string(), "macos".to_string()),
+                ("zsh".to_string(), "macos".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remove_install() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = JsonStateStore::new(RealFileSystem, temp_dir.path());
+        let ripgrep = record("ripgrep", "macos", InstallReason::Direct);
+        store.record_install(ripgrep.clone()).unwrap();
+
+        assert_eq!(
+            store.remove_install("ripgrep", "macos").unwrap(),
+            Some(ripgrep)
+        );
+        assert!(store.remove_install("ripgrep", "macos").unwrap().is_none());
+        assert!(store.list_installs().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_state_file_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = JsonStateStore::new(RealFileSystem, temp_dir.path());
+        store
+            .record_install(record("ripgrep", "macos", InstallReason::Dependency))
+            .unwrap();
+
+        let content = std::fs::read_to_string(store.path()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(json["version"], 1);
+        assert_eq!(json["installs"][0]["package_name"], "ripgrep");
+        assert_eq!(json["installs"][0]["reason"], "dependency");
+        assert_eq!(json["installs"][0]["installed_at"], "2025-03-14T09:26:53Z");
+    }
+
+    #[test]
+    fn test_invalid_state_file() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join(STATE_FILE_NAME), "not json").unwrap();
+        let store = JsonStateStore::new(RealFileSystem, temp_dir.path());
+
+        assert!(matches!(
+            store.list_installs(),
+            Err(StateStoreError::ParseError(_))
+        ));
+    }
+}
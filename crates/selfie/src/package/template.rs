@@ -0,0 +1,521 @@
+//! Command templating
+//!
+//! Package commands can use `{{placeholders}}` instead of spelling out values
+//! that depend on the machine or the package, e.g. `cargo install --root
+//! {{home}}/.local {{name}}`. The built-in variables are:
+//!
+//! - `name`: the package name
+//! - `environment`: the current environment
+//! - `arch`: the machine architecture, e.g. `x86_64` or `aarch64`
+//! - `home`: the user's home directory
+//! - `package_dir`: the package directory from the config file
+//!
+//! Anything else comes from `vars:` in the package file or the config file, with
+//! the package's own value winning. Every substituted value is shell-quoted, so
+//! a value with spaces or quotes stays a single word.
+//!
+//! Templates are expanded when a package's environment block is picked for the
+//! current environment, so every command that runs has already been expanded.
+//! That's also when the block's `cwd` is resolved and the `SELFIE_*` variables
+//! every command gets are filled in.
+
+use std::{collections::BTreeMap, path::Path};
+
+use thiserror::Error;
+
+use super::{EnvironmentConfig, Package};
+use crate::{
+    config::AppConfig,
+    validation::{ValidationErrorCategory, ValidationIssue},
+};
+
+/// Names of the variables selfie always provides
+pub const BUILTIN_VARIABLES: [&str; 5] = ["name", "environment", "arch", "home", "package_dir"];
+
+/// Warn about each of the `vars` names that's a built-in variable
+///
+/// Built-in variables always win, so a `vars` entry with a built-in's name is
+/// never used, whether it's in a package file or the config file.
+pub(crate) fn validate_var_names<'a>(
+    names: impl IntoIterator<Item = &'a String>,
+) -> Vec<ValidationIssue> {
+    names
+        .into_iter()
+        .filter(|name| BUILTIN_VARIABLES.contains(&name.as_str()))
+        .map(|name| {
+            ValidationIssue::warning(
+                ValidationErrorCategory::InvalidValue,
+                &format!("vars.{name}"),
+                &format!("`{name}` is a built-in variable, so this value is never used"),
+                Some("Rename the variable."),
+            )
+        })
+        .collect()
+}
+
+/// Environment variables selfie sets for every package command
+pub const CONTEXT_VARIABLES: [&str; 3] =
+    ["SELFIE_PACKAGE", "SELFIE_ENVIRONMENT", "SELFIE_PACKAGE_DIR"];
+
+/// Errors expanding the placeholders in a command
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// The command uses a variable that isn't built in or defined in `vars:`
+    #[error("Unknown variable `{{{{{variable}}}}}` in command `{command}`")]
+    UnknownVariable { variable: String, command: String },
+
+    /// A `{{` isn't closed by a matching `}}`
+    #[error("Unclosed `{{{{` in command `{command}`")]
+    Unclosed { command: String },
+}
+
+/// The values placeholders are replaced with, apart from the package's own
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    environment: String,
+    arch: String,
+    home: String,
+    package_dir: String,
+    vars: BTreeMap<String, String>,
+}
+
+impl TemplateContext {
+    /// Build the context for running commands with `config`
+    #[must_use]
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            environment: config.environment().to_string(),
+            arch: config.arch().to_string(),
+            home: config
+                .home_directory()
+                .map(|home| home.display().to_string())
+                .unwrap_or_default(),
+            package_dir: config.package_directory().display().to_string(),
+            vars: config.vars().clone(),
+        }
+    }
+
+    /// Look up the value of `variable` for `package`
+    fn lookup<'a>(&'a self, package: &'a Package, variable: &str) -> Option<&'a str> {
+        match variable {
+            "name" => Some(package.name()),
+            "environment" => Some(&self.environment),
+            "arch" => Some(&self.arch),
+            "home" => Some(&self.home),
+            "package_dir" => Some(&self.package_dir),
+            _ => package
+                .vars()
+                .get(variable)
+                .or_else(|| self.vars.get(variable))
+                .map(String::as_str),
+        }
+    }
+
+    /// The [`CONTEXT_VARIABLES`] for `package`'s commands
+    fn context_variables(&self, package: &Package) -> BTreeMap<String, String> {
+        let values = [package.name(), &self.environment, &self.package_dir];
+
+        CONTEXT_VARIABLES
+            .into_iter()
+            .zip(values)
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Resolve a `cwd` setting: `~` is the home directory, and a relative path is
+    /// taken from the package directory
+    fn resolve_cwd(&self, cwd: &str) -> String {
+        let path = match cwd.strip_prefix('~') {
+            Some("") => Path::new(&self.home).to_path_buf(),
+            Some(rest) if rest.starts_with('/') => Path::new(&self.home).join(&rest[1..]),
+            _ => Path::new(&self.package_dir).join(cwd),
+        };
+
+        path.display().to_string()
+    }
+
+    /// Expand the placeholders in one of `package`'s commands
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TemplateError`] if the command uses an unknown variable or leaves
+    /// a `{{` unclosed.
+    pub fn expand(&self, package: &Package, command: &str) -> Result<String, TemplateError> {
+        let mut expanded = String::with_capacity(command.len());
+
+        for part in parse(command)? {
+            match part {
+                Part::Text(text) => expanded.push_str(text),
+                Part::Variable(variable) => {
+                    let value = self.lookup(package, variable).ok_or_else(|| {
+                        TemplateError::UnknownVariable {
+                            variable: variable.to_string(),
+                            command: command.to_string(),
+                        }
+                    })?;
+                    expanded.push_str(&shell_quote(value));
+                }
+            }
+        }
+
+        Ok(expanded)
+    }
+
+    /// Expand every command of one of `package`'s environment blocks, resolve its
+    /// `cwd` and fill in the `SELFIE_*` variables for its commands
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`TemplateError`] found in any of the commands.
+    pub fn render(
+        &self,
+        package: &Package,
+        env_config: &EnvironmentConfig,
+    ) -> Result<EnvironmentConfig, TemplateError> {
+        let expand = |command: &String| self.expand(package, command);
+        let expand_all = |commands: &[String]| -> Result<Vec<String>, TemplateError> {
+            commands.iter().map(expand).collect()
+        };
+
+        let mut rendered = env_config.clone();
+        rendered.install = expand_all(env_config.install.commands())?.into();
+        rendered.check = env_config.check.as_ref().map(expand).transpose()?;
+        rendered.version_command = env_config
+            .version_command
+            .as_ref()
+            .map(expand)
+            .transpose()?;
+        rendered.uninstall = env_config.uninstall.as_ref().map(expand).transpose()?;
+        rendered.update = env_config.update.as_ref().map(expand).transpose()?;
+        for command in rendered
+            .pre_install
+            .iter_mut()
+            .chain(&mut rendered.post_install)
+        {
+            command.run = expand(&command.run)?;
+        }
+        rendered.rollback = expand_all(&env_config.rollback)?;
+        rendered.cwd = env_config.cwd.as_deref().map(|cwd| self.resolve_cwd(cwd));
+        rendered.context = self.context_variables(package);
+
+        Ok(rendered)
+    }
+}
+
+impl Package {
+    /// Pick the environment block for `config`'s environment and its fallbacks,
+    /// with its commands' placeholders expanded
+    ///
+    /// Returns `Ok(None)` when the package supports none of the environments.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TemplateError`] if one of the block's commands can't be expanded.
+    pub fn environment_for(
+        &self,
+        config: &AppConfig,
+    ) -> Result<Option<(&str, EnvironmentConfig)>, TemplateError> {
+        let Some((name, env_config)) = self.select_environment(&config.environment_chain()) else {
+            return Ok(None);
+        };
+
+        let rendered = TemplateContext::new(config).render(self, env_config)?;
+        Ok(Some((name, rendered)))
+    }
+
+    /// Check every command's placeholders against the variables that will be
+    /// available, including `config_vars` from the config file
+    pub(crate) fn validate_templates(
+        &self,
+        config_vars: &BTreeMap<String, String>,
+    ) -> Vec<ValidationIssue> {
+        let mut issues = validate_var_names(self.vars.keys());
+
+        let mut env_names: Vec<&String> = self.environments.keys().collect();
+        env_names.sort();
+
+        for env_name in env_names {
+            let env_config = &self.environments[env_name];
+            for (field, command) in env_config.commands() {
+                let field = format!("environments.{env_name}.{field}");
+                let variables = match parse(command) {
+                    Ok(parts) => parts,
+                    Err(err) => {
+                        issues.push(ValidationIssue::error(
+                            ValidationErrorCategory::CommandSyntax,
+                            &field,
+                            &err.to_string(),
+                            Some("Close the placeholder with `}}`."),
+                        ));
+                        continue;
+                    }
+                };
+
+                for variable in variables.into_iter().filter_map(|part| match part {
+                    Part::Variable(variable) => Some(variable),
+                    Part::Text(_) => None,
+                }) {
+                    let known = BUILTIN_VARIABLES.contains(&variable)
+                        || self.vars.contains_key(variable)
+                        || config_vars.contains_key(variable);
+                    if !known {
+                        issues.push(ValidationIssue::error(
+                            ValidationErrorCategory::InvalidValue,
+                            &field,
+                            &format!("Unknown variable `{{{{{variable}}}}}`"),
+                            Some(&format!(
+                                "Define `{variable}` under `vars:` in the package or config file, or use one of: {}.",
+                                BUILTIN_VARIABLES.join(", ")
+                            )),
+                        ));
+                    }
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+impl EnvironmentConfig {
+    /// Every command of this block, with the name of the field it's in
+    fn commands(&self) -> Vec<(String, &str)> {
+        let mut commands: Vec<(String, &str)> = self
+            .install
+            .commands()
+            .iter()
+            .enumerate()
+            .map(|(i, command)| (self.install.field(i), command.as_str()))
+            .collect();
+
+        let optional = [
+            ("check", &self.check),
+            ("version_command", &self.version_command),
+            ("uninstall", &self.uninstall),
+            ("update", &self.update),
+        ];
+        for (field, command) in optional {
+            if let Some(command) = command {
+                commands.push((field.to_string(), command.as_str()));
+            }
+        }
+
+        let hooks = [
+            ("pre_install", &self.pre_install),
+            ("post_install", &self.post_install),
+        ];
+        for (field, list) in hooks {
+            for (i, command) in list.iter().enumerate() {
+                commands.push((format!("{field}[{i}]"), command.run()));
+            }
+        }
+        for (i, command) in self.rollback.iter().enumerate() {
+            commands.push((format!("rollback[{i}]"), command.as_str()));
+        }
+
+        commands
+    }
+}
+
+/// A piece of a command: literal text or a placeholder
+#[derive(Debug, PartialEq, Eq)]
+enum Part<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+}
+
+/// Split a command into text and `{{variable}}` placeholders
+fn parse(command: &str) -> Result<Vec<Part<'_>>, TemplateError> {
+    let mut parts = Vec::new();
+    let mut rest = command;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            parts.push(Part::Text(&rest[..start]));
+        }
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            return Err(TemplateError::Unclosed {
+                command: command.to_string(),
+            });
+        };
+        parts.push(Part::Variable(after[..end].trim()));
+        rest = &after[end + 2..];
+    }
+
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+
+    Ok(parts)
+}
+
+/// Quote `value` for a POSIX shell, leaving it alone when that's not needed
+fn shell_quote(value: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c);
+
+    if !value.is_empty() && value.chars().all(is_safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::AppConfigBuilder, package::PackageBuilder};
+
+    fn context() -> TemplateContext {
+        TemplateContext {
+            environment: "ubuntu".to_string(),
+            arch: "x86_64".to_string(),
+            home: "/home/me".to_string(),
+            package_dir: "/home/me/my packages".to_string(),
+            vars: BTreeMap::from([
+                ("channel".to_string(), "stable".to_string()),
+                ("prefix".to_string(), "/usr/local".to_string()),
+            ]),
+        }
+    }
+
+    fn package() -> Package {
+        PackageBuilder::default()
+            .name("ripgrep")
+            .version("1.0.0")
+            .var("prefix", "/opt/it's mine")
+            .environment("ubuntu", |b| {
+                b.install("cargo install --root {{ prefix }} {{name}}")
+                    .check_some("test -x {{home}}/.cargo/bin/rg")
+                    .pre_install(vec!["echo {{environment}}-{{arch}} {{channel}}"])
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_expand_builtins_and_vars() {
+        let ctx = context();
+        let package = package();
+
+        assert_eq!(
+            ctx.expand(&package, "cargo install --root {{ prefix }} {{name}}")
+                .unwrap(),
+            r"cargo install --root '/opt/it'\''s mine' ripgrep"
+        );
+        assert_eq!(
+            ctx.expand(&package, "echo {{environment}}-{{arch}} {{channel}}")
+                .unwrap(),
+            "echo ubuntu-x86_64 stable"
+        );
+        assert_eq!(
+            ctx.expand(&package, "ls {{package_dir}}").unwrap(),
+            "ls '/home/me/my packages'"
+        );
+        assert_eq!(
+            ctx.expand(&package, "echo no placeholders").unwrap(),
+            "echo no placeholders"
+        );
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let ctx = context();
+        let package = package();
+
+        assert_eq!(
+            ctx.expand(&package, "echo {{missing}}"),
+            Err(TemplateError::UnknownVariable {
+                variable: "missing".to_string(),
+                command: "echo {{missing}}".to_string(),
+            })
+        );
+        assert!(matches!(
+            ctx.expand(&package, "echo {{name"),
+            Err(TemplateError::Unclosed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_render_environment() {
+        let ctx = context();
+        let package = package();
+
+        let rendered = ctx
+            .render(&package, &package.environments()["ubuntu"])
+            .unwrap();
+        assert_eq!(rendered.check(), Some("test -x /home/me/.cargo/bin/rg"));
+        assert_eq!(rendered.pre_install()[0].run(), "echo ubuntu-x86_64 stable");
+    }
+
+    #[test]
+    fn test_render_cwd_and_context() {
+        let ctx = context();
+        let package = package();
+
+        assert_eq!(ctx.resolve_cwd("~"), "/home/me");
+        assert_eq!(ctx.resolve_cwd("~/src/rg"), "/home/me/src/rg");
+        assert_eq!(ctx.resolve_cwd("/tmp"), "/tmp");
+        assert_eq!(ctx.resolve_cwd("ripgrep"), "/home/me/my packages/ripgrep");
+
+        let rendered = ctx
+            .render(&package, &package.environments()["ubuntu"])
+            .unwrap();
+        let request = rendered.command_request("true", std::time::Duration::from_secs(1));
+        assert_eq!(request.working_dir(), None);
+        assert_eq!(request.env_vars()["SELFIE_PACKAGE"], "ripgrep");
+        assert_eq!(request.env_vars()["SELFIE_ENVIRONMENT"], "ubuntu");
+        assert_eq!(
+            request.env_vars()["SELFIE_PACKAGE_DIR"],
+            "/home/me/my packages"
+        );
+    }
+
+    #[test]
+    fn test_environment_for() {
+        let config = AppConfigBuilder::default()
+            .environment("ubuntu")
+            .package_directory("/packages")
+            .home_directory("/home/me")
+            .var("channel", "beta")
+            .build();
+        let package = package();
+
+        let (name, env_config) = package.environment_for(&config).unwrap().unwrap();
+        assert_eq!(name, "ubuntu");
+        assert!(env_config.pre_install()[0].run().ends_with(" beta"));
+        assert_eq!(env_config.check(), Some("test -x /home/me/.cargo/bin/rg"));
+
+        let other = AppConfigBuilder::default()
+            .environment("macos")
+            .package_directory("/packages")
+            .build();
+        assert!(package.environment_for(&other).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_validate_templates() {
+        let package = PackageBuilder::default()
+            .name("ripgrep")
+            .version("1.0.0")
+            .var("name", "shadowed")
+            .environment("ubuntu", |b| {
+                b.install("install {{name}} {{channel}}")
+                    .check_some("check {{missing}}")
+                    .rollback(vec!["undo {{name"])
+            })
+            .build();
+        let config_vars = BTreeMap::from([("channel".to_string(), "stable".to_string())]);
+
+        let issues = package.validate_templates(&config_vars);
+
+        let fields: Vec<&str> = issues.iter().map(ValidationIssue::field).collect();
+        assert_eq!(
+            fields,
+            [
+                "vars.name",
+                "environments.ubuntu.check",
+                "environments.ubuntu.rollback[0]"
+            ]
+        );
+    }
+}
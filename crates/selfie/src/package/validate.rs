@@ -2,7 +2,16 @@ use std::path::PathBuf;
 
 use crate::validation::{ValidationErrorCategory, ValidationIssue, ValidationIssues};
 
-use super::Package;
+use super::{
+    EnvironmentConfig, Package,
+    graph::DependencyGraph,
+    port::PackageRepository,
+    template::CONTEXT_VARIABLES,
+    version::{self, VersionRequirement},
+};
+
+/// Longest environment timeout, in seconds, that isn't flagged as a likely mistake
+const MAX_REASONABLE_TIMEOUT_SECS: u64 = 24 * 60 * 60;
 
 /// Results of a package validation
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -39,13 +48,15 @@ impl ValidationResult {
 
 impl Package {
     /// Perform all basic domain validations
+    ///
+    /// `environments` is the current environment followed by its fallbacks.
     #[must_use]
-    pub fn validate(&self, current_env: &str) -> ValidationResult {
+    pub fn validate(&self, environments: &[&str]) -> ValidationResult {
         let mut issues = Vec::new();
 
         issues.extend(self.validate_required_fields());
         issues.extend(self.validate_urls());
-        issues.extend(self.validate_environments_contents(current_env));
+        issues.extend(self.validate_environments_contents(environments));
         issues.extend(self.validate_command_syntax());
 
         ValidationResult {
@@ -55,6 +66,69 @@ impl Package {
         }
     }
 
+    /// Perform all domain validations, plus the ones that need the rest of the repository
+    ///
+    /// In addition to [`Package::validate`], this follows the package's dependencies
+    /// through `repo`, with the fallbacks in `environments`, to find dependency
+    /// cycles.
+    #[must_use]
+    pub fn validate_with_repository<R>(&self, environments: &[&str], repo: &R) -> ValidationResult
+    where
+        R: PackageRepository + ?Sized,
+    {
+        let mut result = self.validate(environments);
+
+        let mut issues = result.issues.all_issues().to_vec();
+        issues.extend(self.validate_dependency_cycles(environments, repo));
+        result.issues = issues.into();
+
+        result
+    }
+
+    /// Check each environment's dependencies for cycles back to a package already
+    /// being resolved
+    ///
+    /// Dependencies are looked up through the same fallback chain install uses.
+    /// The block picked for the current environment is checked with `environments`
+    /// as they are; every other block as if its own environment were current, with
+    /// the same fallbacks.
+    pub(crate) fn validate_dependency_cycles<R>(
+        &self,
+        environments: &[&str],
+        repo: &R,
+    ) -> Vec<ValidationIssue>
+    where
+        R: PackageRepository + ?Sized,
+    {
+        let fallbacks = environments.get(1..).unwrap_or_default();
+        let selected = self.select_environment(environments).map(|(name, _)| name);
+
+        let mut env_names: Vec<&String> = self.environments.keys().collect();
+        env_names.sort();
+
+        env_names
+            .into_iter()
+            .filter_map(|env_name| {
+                let chain: Vec<&str> = if selected == Some(env_name.as_str()) {
+                    environments.to_vec()
+                } else {
+                    std::iter::once(env_name.as_str())
+                        .chain(fallbacks.iter().copied().filter(|f| f != env_name))
+                        .collect()
+                };
+
+                DependencyGraph::find_cycle(repo, self, &chain).map(|path| {
+                    ValidationIssue::error(
+                        ValidationErrorCategory::DependencyCycle,
+                        &format!("environments.{env_name}.dependencies"),
+                        &format!("Dependency cycle detected: {}", path.join(" -> ")),
+                        Some("Remove one of the dependencies in the cycle."),
+                    )
+                })
+            })
+            .collect()
+    }
+
     pub(crate) fn validate_required_fields(&self) -> Vec<ValidationIssue> {
         let mut issues = Vec::new();
 
@@ -144,11 +218,25 @@ impl Package {
         }
     }
 
-    fn validate_environments_contents(&self, current_env: &str) -> Vec<ValidationIssue> {
+    fn validate_environments_contents(&self, environments: &[&str]) -> Vec<ValidationIssue> {
         let mut issues = Vec::new();
 
-        // Check if current environment is configured
-        if !current_env.is_empty() && !self.environments.contains_key(current_env) {
+        // Environments left out because their `use:` chain is broken
+        for (env_name, err) in &self.inheritance_errors {
+            issues.push(ValidationIssue::error(
+                ValidationErrorCategory::Environment,
+                &format!("environments.{env_name}.use"),
+                &err.to_string(),
+                Some("Point `use:` at another environment of this package, without looping back."),
+            ));
+        }
+
+        // Check if current environment, or one of its fallbacks, is configured
+        let current_env = environments.first().copied().unwrap_or_default();
+        if !current_env.is_empty()
+            && !self.supports_environment(environments)
+            && !self.inheritance_errors.contains_key(current_env)
+        {
             issues.push(ValidationIssue::warning(
                 ValidationErrorCategory::Environment,
                 "environments",
@@ -168,10 +256,21 @@ impl Package {
                     "Install command is required",
                     Some("Add an install command like 'brew install package-name'."),
                 ));
+            } else if env_config.install.is_multi_step() {
+                for (i, command) in env_config.install.commands().iter().enumerate() {
+                    if command.trim().is_empty() {
+                        issues.push(ValidationIssue::error(
+                            ValidationErrorCategory::InvalidValue,
+                            &format!("environments.{env_name}.install[{i}]"),
+                            "Install step cannot be empty",
+                            Some("Remove the empty step or give it a command."),
+                        ));
+                    }
+                }
             }
 
             // Validate dependencies (check for empty names)
-            for (i, dep) in env_config.dependencies.iter().enumerate() {
+            for (i, dep) in env_config.dependencies().iter().enumerate() {
                 if dep.is_empty() {
                     issues.push(ValidationIssue::error(
                         ValidationErrorCategory::InvalidValue,
@@ -181,6 +280,186 @@ impl Package {
                     ));
                 }
             }
+
+            issues.extend(Self::validate_version_check(env_name, env_config));
+            issues.extend(Self::validate_conditions(env_name, env_config));
+            issues.extend(Self::validate_timeouts(env_name, env_config));
+            issues.extend(Self::validate_command_env(env_name, env_config));
+        }
+
+        issues
+    }
+
+    /// Validate an environment's `version_command`, `version_regex`, and
+    /// `required_version`
+    fn validate_version_check(
+        env_name: &str,
+        env_config: &EnvironmentConfig,
+    ) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        if env_config.version_command.is_none() {
+            for (field, value) in [
+                ("version_regex", &env_config.version_regex),
+                ("required_version", &env_config.required_version),
+            ] {
+                if value.is_some() {
+                    issues.push(ValidationIssue::error(
+                        ValidationErrorCategory::RequiredField,
+                        &format!("environments.{env_name}.{field}"),
+                        &format!(
+                            "`{field}` needs a `version_command` to get the installed version"
+                        ),
+                        Some("Add a command that prints the version, like 'rg --version'."),
+                    ));
+                }
+            }
+        }
+
+        if let Some(pattern) = env_config.version_regex.as_deref()
+            && let Err(err) = version::version_pattern(Some(pattern))
+        {
+            issues.push(ValidationIssue::error(
+                ValidationErrorCategory::InvalidValue,
+                &format!("environments.{env_name}.version_regex"),
+                &err.to_string(),
+                Some(
+                    "Use a regex whose first capture group is the version, like 'ripgrep (\\S+)'.",
+                ),
+            ));
+        }
+
+        if let Some(requirement) = env_config.required_version.as_deref()
+            && let Err(err) = requirement.parse::<VersionRequirement>()
+        {
+            issues.push(ValidationIssue::error(
+                ValidationErrorCategory::InvalidValue,
+                &format!("environments.{env_name}.required_version"),
+                &err.to_string(),
+                Some("Use comparisons like '>=14.0' or '>=1.2, <2'."),
+            ));
+        }
+
+        issues
+    }
+
+    /// Validate the `when:` clauses of an environment and of its hook commands
+    fn validate_conditions(env_name: &str, env_config: &EnvironmentConfig) -> Vec<ValidationIssue> {
+        let hook_conditions = [
+            ("pre_install", &env_config.pre_install),
+            ("post_install", &env_config.post_install),
+        ]
+        .into_iter()
+        .flat_map(|(hook, commands)| {
+            commands.iter().enumerate().filter_map(move |(i, command)| {
+                command
+                    .when()
+                    .map(|when| (format!("environments.{env_name}.{hook}[{i}].when"), when))
+            })
+        });
+        let conditions = env_config
+            .when()
+            .map(|when| (format!("environments.{env_name}.when"), when))
+            .into_iter()
+            .chain(hook_conditions);
+
+        let mut issues = Vec::new();
+        for (field, when) in conditions {
+            if when.is_empty() {
+                issues.push(ValidationIssue::warning(
+                    ValidationErrorCategory::InvalidValue,
+                    &field,
+                    "`when:` has no conditions, so it always holds",
+                    Some("Add a condition like 'arch: arm64', or remove `when:`."),
+                ));
+            }
+
+            if let Some(env_var) = when.env_var.as_deref()
+                && env_var.split('=').next().is_none_or(str::is_empty)
+            {
+                issues.push(ValidationIssue::error(
+                    ValidationErrorCategory::InvalidValue,
+                    &format!("{field}.env_var"),
+                    "Environment variable name cannot be empty",
+                    Some("Use a variable name like 'CI', or 'CI=true' to match its value."),
+                ));
+            }
+        }
+
+        issues
+    }
+
+    /// Warn about timeouts that can't be what the package author meant
+    fn validate_timeouts(env_name: &str, env_config: &EnvironmentConfig) -> Vec<ValidationIssue> {
+        let timeouts = [
+            ("timeout", env_config.timeout),
+            ("install_timeout", env_config.install_timeout),
+            ("check_timeout", env_config.check_timeout),
+        ];
+
+        timeouts
+            .into_iter()
+            .filter_map(|(field, seconds)| {
+                let message = match seconds? {
+                    0 => "A timeout of 0 seconds stops every command straight away",
+                    seconds if seconds > MAX_REASONABLE_TIMEOUT_SECS => {
+                        "A timeout of more than a day lets a stuck command hang for too long"
+                    }
+                    _ => return None,
+                };
+                Some(ValidationIssue::warning(
+                    ValidationErrorCategory::InvalidValue,
+                    &format!("environments.{env_name}.{field}"),
+                    message,
+                    Some("Use a number of seconds like 600, or remove it to use the global `command_timeout`."),
+                ))
+            })
+            .collect()
+    }
+
+    /// Validate an environment's `cwd` and `env` settings
+    fn validate_command_env(
+        env_name: &str,
+        env_config: &EnvironmentConfig,
+    ) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        if env_config
+            .cwd
+            .as_deref()
+            .is_some_and(|cwd| cwd.trim().is_empty())
+        {
+            issues.push(ValidationIssue::error(
+                ValidationErrorCategory::InvalidValue,
+                &format!("environments.{env_name}.cwd"),
+                "Working directory cannot be empty",
+                Some("Give a directory like `~/src`, or remove `cwd` to run in the current directory."),
+            ));
+        }
+
+        for name in env_config.env.keys() {
+            let field = format!("environments.{env_name}.env.{name}");
+            let is_valid = name
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+            if !is_valid {
+                issues.push(ValidationIssue::error(
+                    ValidationErrorCategory::InvalidValue,
+                    &field,
+                    &format!("`{name}` is not a valid environment variable name"),
+                    Some("Use letters, digits and underscores, not starting with a digit."),
+                ));
+            } else if CONTEXT_VARIABLES.contains(&name.as_str()) {
+                issues.push(ValidationIssue::warning(
+                    ValidationErrorCategory::InvalidValue,
+                    &field,
+                    &format!("selfie sets `{name}` itself, so this value is never used"),
+                    Some("Rename the variable."),
+                ));
+            }
         }
 
         issues
@@ -225,11 +504,13 @@ impl Package {
         let mut issues = Vec::new();
 
         for (env_name, env_config) in &self.environments {
-            // Check install command syntax
-            issues.extend(Self::validate_single_command(
-                &env_config.install,
-                &format!("environments.{env_name}.install"),
-            ));
+            // Check each install command's syntax
+            for (i, install_cmd) in env_config.install.commands().iter().enumerate() {
+                issues.extend(Self::validate_single_command(
+                    install_cmd,
+                    &format!("environments.{env_name}.{}", env_config.install.field(i)),
+                ));
+            }
 
             // Check check command syntax if present
             if let Some(check_cmd) = &env_config.check {
@@ -238,6 +519,51 @@ impl Package {
                     &format!("environments.{env_name}.check"),
                 ));
             }
+
+            // Check uninstall command syntax if present
+            if let Some(uninstall_cmd) = &env_config.uninstall {
+                issues.extend(Self::validate_single_command(
+                    uninstall_cmd,
+                    &format!("environments.{env_name}.uninstall"),
+                ));
+            }
+
+            // Check update command syntax if present
+            if let Some(update_cmd) = &env_config.update {
+                issues.extend(Self::validate_single_command(
+                    update_cmd,
+                    &format!("environments.{env_name}.update"),
+                ));
+            }
+
+            // Check version command syntax if present
+            if let Some(version_cmd) = &env_config.version_command {
+                issues.extend(Self::validate_single_command(
+                    version_cmd,
+                    &format!("environments.{env_name}.version_command"),
+                ));
+            }
+
+            // Check each hook command's syntax
+            for (hook, commands) in [
+                ("pre_install", &env_config.pre_install),
+                ("post_install", &env_config.post_install),
+            ] {
+                for (i, hook_cmd) in commands.iter().enumerate() {
+                    issues.extend(Self::validate_single_command(
+                        hook_cmd.run(),
+                        &format!("environments.{env_name}.{hook}[{i}]"),
+                    ));
+                }
+            }
+
+            // Check each rollback command's syntax
+            for (i, rollback_cmd) in env_config.rollback.iter().enumerate() {
+                issues.extend(Self::validate_single_command(
+                    rollback_cmd,
+                    &format!("environments.{env_name}.rollback[{i}]"),
+                ));
+            }
         }
 
         issues
@@ -319,7 +645,11 @@ impl Package {
 #[cfg(test)]
 mod tests {
     use crate::{
-        package::{EnvironmentConfig, builder::PackageBuilder},
+        package::{
+            EnvironmentConfig,
+            builder::PackageBuilder,
+            condition::{Condition, ConditionalCommand},
+        },
         validation::ValidationLevel,
     };
 
@@ -333,7 +663,7 @@ mod tests {
             .environment("test-env", |b| b.install("test install"))
             .build();
 
-        assert!(package.validate("test-env").issues().is_valid());
+        assert!(package.validate(&["test-env"]).issues().is_valid());
     }
 
     #[test]
@@ -384,11 +714,15 @@ mod tests {
             .environment("other-env", |b| b.install("test install"))
             .build();
 
-        let issues = package.validate_environments_contents("test-env");
+        let issues = package.validate_environments_contents(&["test-env"]);
         assert_eq!(issues.len(), 1);
         assert_eq!(issues[0].level(), ValidationLevel::Warning);
         assert!(issues[0].message.contains("not configured"));
 
+        // A fallback environment counts as configured
+        let issues = package.validate_environments_contents(&["test-env", "other-env"]);
+        assert!(issues.is_empty());
+
         // Test empty install command
         let mut package = PackageBuilder::default()
             .name("test-package")
@@ -396,21 +730,231 @@ mod tests {
             .build();
 
         let env_config = EnvironmentConfig {
-            install: String::new(),
+            uses: None,
+            when: None,
+            install: String::new().into(),
             check: None,
-            dependencies: vec![],
+            version_command: None,
+            version_regex: None,
+            required_version: None,
+            pre_install: vec![],
+            post_install: vec![],
+            dependencies: None,
+            uninstall: None,
+            update: None,
+            rollback: vec![],
+            retries: None,
+            retry_delay: None,
+            timeout: None,
+            install_timeout: None,
+            check_timeout: None,
+            cwd: None,
+            env: Default::default(),
+            context: Default::default(),
         };
 
         package
             .environments
             .insert("test-env".to_string(), env_config);
 
-        let issues = package.validate_environments_contents("test-env");
+        let issues = package.validate_environments_contents(&["test-env"]);
         assert_eq!(issues.len(), 1);
         assert_eq!(issues[0].level(), ValidationLevel::Error);
         assert!(issues[0].message.contains("required"));
     }
 
+    #[test]
+    fn test_validate_use_chains() {
+        let package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("ubuntu", |b| b.install("apt install test"))
+            .environment("debian", |b| b.uses("ubunt"))
+            .environment("a", |b| b.uses("b"))
+            .environment("b", |b| b.uses("a"))
+            .build();
+
+        let issues = package.validate_environments_contents(&["debian"]);
+        assert_eq!(issues.len(), 3);
+        assert!(
+            issues
+                .iter()
+                .all(|issue| issue.level() == ValidationLevel::Error)
+        );
+
+        let debian = issues
+            .iter()
+            .find(|issue| issue.field == "environments.debian.use")
+            .unwrap();
+        assert!(debian.message.contains("ubunt"));
+        let loop_issue = issues
+            .iter()
+            .find(|issue| issue.field == "environments.a.use")
+            .unwrap();
+        assert!(loop_issue.message.contains("a -> b -> a"));
+    }
+
+    #[test]
+    fn test_validate_version_check() {
+        let package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("test-env", |b| {
+                b.install("echo test")
+                    .version_command_some("test-package --version")
+                    .version_regex_some("(")
+                    .required_version_some(">=latest")
+            })
+            .environment("other-env", |b| {
+                b.install("echo test").required_version_some(">=1.0")
+            })
+            .build();
+
+        let issues = package.validate_environments_contents(&["test-env"]);
+        let mut fields: Vec<_> = issues.iter().map(|issue| issue.field.as_str()).collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            [
+                "environments.other-env.required_version",
+                "environments.test-env.required_version",
+                "environments.test-env.version_regex",
+            ]
+        );
+        assert!(
+            issues
+                .iter()
+                .all(|issue| issue.level() == ValidationLevel::Error)
+        );
+    }
+
+    #[test]
+    fn test_validate_conditions() {
+        let mut package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("test-env", |b| {
+                b.install("echo test").when(Condition::default())
+            })
+            .build();
+        package
+            .environments
+            .get_mut("test-env")
+            .unwrap()
+            .post_install
+            .push(ConditionalCommand {
+                run: "echo done".to_string(),
+                when: Some(Condition {
+                    env_var: Some("=true".to_string()),
+                    ..Condition::default()
+                }),
+            });
+
+        let issues = package.validate_environments_contents(&["test-env"]);
+        assert_eq!(issues.len(), 2);
+
+        let empty = issues
+            .iter()
+            .find(|issue| issue.field == "environments.test-env.when")
+            .unwrap();
+        assert_eq!(empty.level(), ValidationLevel::Warning);
+        let unnamed = issues
+            .iter()
+            .find(|issue| issue.field == "environments.test-env.post_install[0].when.env_var")
+            .unwrap();
+        assert_eq!(unnamed.level(), ValidationLevel::Error);
+    }
+
+    #[test]
+    fn test_validate_install_steps() {
+        let package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("test-env", |b| {
+                b.install_script(vec!["make", "", "echo 'unterminated"])
+            })
+            .build();
+
+        let mut fields: Vec<_> = package
+            .validate(&["test-env"])
+            .issues()
+            .errors()
+            .iter()
+            .map(|issue| issue.field.clone())
+            .collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            [
+                "environments.test-env.install[1]",
+                "environments.test-env.install[2]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_timeouts() {
+        let package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("test-env", |b| {
+                b.install("echo test")
+                    .timeout(0)
+                    .install_timeout(7 * 24 * 60 * 60)
+                    .check_timeout(30)
+            })
+            .build();
+
+        let issues = package.validate_environments_contents(&["test-env"]);
+        let mut fields: Vec<_> = issues.iter().map(|issue| issue.field.as_str()).collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            [
+                "environments.test-env.install_timeout",
+                "environments.test-env.timeout",
+            ]
+        );
+        assert!(
+            issues
+                .iter()
+                .all(|issue| issue.level() == ValidationLevel::Warning)
+        );
+    }
+
+    #[test]
+    fn test_validate_command_env() {
+        let package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("test-env", |b| {
+                b.install("make install")
+                    .cwd(" ")
+                    .env("PREFIX", "/usr/local")
+                    .env("2FAST", "yes")
+                    .env("SELFIE_PACKAGE", "other")
+            })
+            .build();
+
+        let issues = package.validate_environments_contents(&["test-env"]);
+        let mut found: Vec<_> = issues
+            .iter()
+            .map(|issue| (issue.field.as_str(), issue.level()))
+            .collect();
+        found.sort_unstable_by_key(|(field, _)| *field);
+        assert_eq!(
+            found,
+            [
+                ("environments.test-env.cwd", ValidationLevel::Error),
+                ("environments.test-env.env.2FAST", ValidationLevel::Error),
+                (
+                    "environments.test-env.env.SELFIE_PACKAGE",
+                    ValidationLevel::Warning
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_validate_command_syntax() {
         // Test unmatched quote
@@ -448,6 +992,62 @@ mod tests {
         assert_eq!(issues.len(), 1);
         assert_eq!(issues[0].level(), ValidationLevel::Warning);
         assert!(issues[0].message.contains("backticks"));
+
+        // Test uninstall command is checked too
+        let package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("test-env", |b| {
+                b.install("echo test").uninstall_some("echo 'unmatched")
+            })
+            .build();
+
+        let issues = package.validate_command_syntax();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "environments.test-env.uninstall");
+
+        // Test version command is checked too
+        let package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("test-env", |b| {
+                b.install("echo test")
+                    .version_command_some("test-package --version | | head -1")
+            })
+            .build();
+
+        let issues = package.validate_command_syntax();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "environments.test-env.version_command");
+
+        // Test rollback commands are checked too
+        let package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("test-env", |b| {
+                b.install("echo test")
+                    .rollback(vec!["echo ok", "echo 'unmatched"])
+            })
+            .build();
+
+        let issues = package.validate_command_syntax();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "environments.test-env.rollback[1]");
+
+        // Test hook commands are checked too
+        let package = PackageBuilder::default()
+            .name("test-package")
+            .version("1.0.0")
+            .environment("test-env", |b| {
+                b.install("echo test")
+                    .pre_install(vec!["echo 'unmatched"])
+                    .post_install(vec!["echo ok"])
+            })
+            .build();
+
+        let issues = package.validate_command_syntax();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "environments.test-env.pre_install[0]");
     }
 
     #[test]
@@ -461,7 +1061,7 @@ mod tests {
             .environment("test-env", |b| b.install("echo test"))
             .build();
 
-        let result = package.validate("test-env");
+        let result = package.validate(&["test-env"]);
         assert!(!result.issues().has_issues());
 
         // Test an invalid package with multiple issues
@@ -472,7 +1072,104 @@ mod tests {
             .environment("other-env", |b| b.install("echo `test`"))
             .build();
 
-        let result = package.validate("test-env");
+        let result = package.validate(&["test-env"]);
         assert!(result.issues().all_issues().len() >= 4); // At least 4 issues should be found
     }
+
+    fn mock_repo(packages: Vec<Package>) -> crate::package::port::MockPackageRepository {
+        use crate::package::{
+            GetPackage,
+            port::{MockPackageRepository, PackageError, PackageRepoError},
+        };
+
+        let mut repo = MockPackageRepository::new();
+        repo.expect_get_package().returning(move |name| {
+            packages
+                .iter()
+                .find(|p| p.name() == name)
+                .map(|p| GetPackage::from_existing(p.clone(), PathBuf::from(name)))
+                .ok_or_else(|| {
+                    PackageRepoError::PackageError(Box::new(PackageError::PackageNotFound {
+                        name: name.to_string(),
+                        packages_path: PathBuf::from("/packages"),
+                        files_examined: 0,
+                        search_patterns: Vec::new(),
+                    }))
+                })
+        });
+        repo
+    }
+
+    #[test]
+    fn test_validate_dependency_cycles() {
+        let package_a = PackageBuilder::default()
+            .name("a")
+            .version("1.0.0")
+            .environment("test-env", |b| b.install("echo a").dependencies(vec!["b"]))
+            .environment("other-env", |b| b.install("echo a"))
+            .build();
+        let package_b = PackageBuilder::default()
+            .name("b")
+            .version("1.0.0")
+            .environment("test-env", |b| b.install("echo b").dependencies(vec!["c"]))
+            .build();
+        let package_c = PackageBuilder::default()
+            .name("c")
+            .version("1.0.0")
+            .environment("test-env", |b| b.install("echo c").dependencies(vec!["a"]))
+            .build();
+
+        let repo = mock_repo(vec![package_a.clone(), package_b, package_c]);
+
+        let result = package_a.validate_with_repository(&["test-env"], &repo);
+        let cycles = result
+            .issues()
+            .issues_by_category(&ValidationErrorCategory::DependencyCycle);
+
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].level(), ValidationLevel::Error);
+        assert_eq!(cycles[0].field(), "environments.test-env.dependencies");
+        assert_eq!(
+            cycles[0].message(),
+            "Dependency cycle detected: a -> b -> c -> a"
+        );
+    }
+
+    #[test]
+    fn test_validate_dependency_cycles_through_fallbacks() {
+        // `b` only has a `linux` block, so the cycle only exists when `ubuntu`
+        // falls back to `linux`, the way install resolves it
+        let package_a = PackageBuilder::default()
+            .name("a")
+            .version("1.0.0")
+            .environment("ubuntu", |b| b.install("echo a").dependencies(vec!["b"]))
+            .build();
+        let package_b = PackageBuilder::default()
+            .name("b")
+            .version("1.0.0")
+            .environment("linux", |b| b.install("echo b").dependencies(vec!["a"]))
+            .build();
+
+        let repo = mock_repo(vec![package_a.clone(), package_b]);
+
+        let result = package_a.validate_with_repository(&["ubuntu", "linux"], &repo);
+        let cycles = result
+            .issues()
+            .issues_by_category(&ValidationErrorCategory::DependencyCycle);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].field(), "environments.ubuntu.dependencies");
+        assert_eq!(
+            cycles[0].message(),
+            "Dependency cycle detected: a -> b -> a"
+        );
+
+        // Without the fallback, `b` can't be resolved and there's no cycle
+        let result = package_a.validate_with_repository(&["ubuntu"], &repo);
+        assert!(
+            result
+                .issues()
+                .issues_by_category(&ValidationErrorCategory::DependencyCycle)
+                .is_empty()
+        );
+    }
 }
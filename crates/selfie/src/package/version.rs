@@ -0,0 +1,315 @@
+//! Installed-version detection and comparison
+//!
+//! A package file's `version` describes the file, not the software it installs.
+//! To tell whether the installed software is new enough, an environment can give a
+//! `version_command` whose output holds the installed version, a `version_regex`
+//! to pick the version out of that output, and a `required_version` such as
+//! `>=14.0` or `>=1.2, <2`.
+//!
+//! Versions are compared by their dotted numeric parts, with missing parts counting
+//! as zero, so `14` and `14.0.0` are the same version. Anything after the numeric
+//! parts (e.g. `-beta` in `1.2.3-beta`) is ignored.
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use regex::Regex;
+use thiserror::Error;
+
+/// Finds a version in command output when the environment has no `version_regex`
+const DEFAULT_VERSION_PATTERN: &str = r"\d+(?:\.\d+)*";
+
+/// A software version, e.g. `14.1.0`
+#[derive(Debug, Clone)]
+pub struct Version {
+    parts: Vec<u64>,
+    raw: String,
+}
+
+/// Errors working out or comparing versions
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum VersionError {
+    /// The text doesn't start with a number
+    #[error("'{0}' is not a version")]
+    InvalidVersion(String),
+
+    /// A `required_version` couldn't be understood
+    #[error("Invalid version requirement '{requirement}': {reason}")]
+    InvalidRequirement { requirement: String, reason: String },
+
+    /// A `version_regex` isn't a valid regular expression
+    #[error("Invalid version regex '{pattern}': {reason}")]
+    InvalidPattern { pattern: String, reason: String },
+
+    /// No version could be found in the `version_command` output
+    #[error("No version found in output '{output}'")]
+    NotFound { output: String },
+}
+
+impl Version {
+    /// The version as it was written
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl FromStr for Version {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw = s.trim();
+        let numeric = raw.strip_prefix('v').unwrap_or(raw);
+
+        let mut parts = Vec::new();
+        for part in numeric.split('.') {
+            let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+            let Ok(number) = digits.parse() else {
+                break;
+            };
+            parts.push(number);
+            if digits.len() < part.len() {
+                break;
+            }
+        }
+
+        if parts.is_empty() {
+            return Err(VersionError::InvalidVersion(raw.to_string()));
+        }
+
+        Ok(Self {
+            parts,
+            raw: raw.to_string(),
+        })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.parts.len().max(other.parts.len());
+        let part = |parts: &[u64], i: usize| parts.get(i).copied().unwrap_or(0);
+
+        (0..len)
+            .map(|i| part(&self.parts, i).cmp(&part(&other.parts, i)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Version {}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// How a [`VersionRequirement`] compares against a version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+}
+
+/// A constraint on a version, e.g. `>=14.0` or `>=1.2, <2`
+///
+/// Comma-separated comparisons must all hold. A version without an operator must
+/// match exactly.
+#[derive(Debug, Clone)]
+pub struct VersionRequirement {
+    comparisons: Vec<(Comparison, Version)>,
+    raw: String,
+}
+
+impl VersionRequirement {
+    /// Whether `version` meets every comparison in the requirement
+    #[must_use]
+    pub fn matches(&self, version: &Version) -> bool {
+        self.comparisons
+            .iter()
+            .all(|(comparison, required)| match comparison {
+                Comparison::Greater => version > required,
+                Comparison::GreaterOrEqual => version >= required,
+                Comparison::Less => version < required,
+                Comparison::LessOrEqual => version <= required,
+                Comparison::Equal => version == required,
+            })
+    }
+}
+
+impl FromStr for VersionRequirement {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| VersionError::InvalidRequirement {
+            requirement: s.to_string(),
+            reason,
+        };
+
+        let comparisons = s
+            .split(',')
+            .map(|part| {
+                let part = part.trim();
+                let (comparison, version) = [
+                    (">=", Comparison::GreaterOrEqual),
+                    ("<=", Comparison::LessOrEqual),
+                    ("==", Comparison::Equal),
+                    (">", Comparison::Greater),
+                    ("<", Comparison::Less),
+                    ("=", Comparison::Equal),
+                ]
+                .into_iter()
+                .find_map(|(op, comparison)| {
+                    part.strip_prefix(op).map(|version| (comparison, version))
+                })
+                .unwrap_or((Comparison::Equal, part));
+
+                version
+                    .parse()
+                    .map(|version| (comparison, version))
+                    .map_err(|err: VersionError| invalid(err.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            comparisons,
+            raw: s.trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for VersionRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// Compile a `version_regex`, or the default pattern when there isn't one
+///
+/// # Errors
+///
+/// Returns [`VersionError::InvalidPattern`] if `pattern` isn't a valid regex.
+pub fn version_pattern(pattern: Option<&str>) -> Result<Regex, VersionError> {
+    let pattern = pattern.unwrap_or(DEFAULT_VERSION_PATTERN);
+
+    Regex::new(pattern).map_err(|err| VersionError::InvalidPattern {
+        pattern: pattern.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+/// Pick the installed version out of a `version_command`'s output
+///
+/// With a `pattern`, its first capture group is the version, or the whole match
+/// if it has no groups. Without one, the first dotted number in the output is.
+///
+/// # Errors
+///
+/// Returns [`VersionError::InvalidPattern`] if `pattern` isn't a valid regex, and
+/// [`VersionError::NotFound`] or [`VersionError::InvalidVersion`] if the output
+/// holds no version.
+pub fn extract_version(output: &str, pattern: Option<&str>) -> Result<Version, VersionError> {
+    let regex = version_pattern(pattern)?;
+    let captures = regex
+        .captures(output)
+        .ok_or_else(|| VersionError::NotFound {
+            output: output.trim().to_string(),
+        })?;
+    let version = captures
+        .get(1)
+        .or_else(|| captures.get(0))
+        .map_or("", |m| m.as_str());
+
+    version.parse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    fn requirement(s: &str) -> VersionRequirement {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(version("v14.1.0").as_str(), "v14.1.0");
+        assert_eq!(version("1.2.3-beta"), version("1.2.3"));
+        assert_eq!(version("14"), version("14.0.0"));
+        assert_eq!(
+            "beta".parse::<Version>().unwrap_err(),
+            VersionError::InvalidVersion("beta".to_string())
+        );
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert!(version("14.1") > version("14.0.9"));
+        assert!(version("2.10") > version("2.9"));
+        assert!(version("0.9.9") < version("1"));
+    }
+
+    #[test]
+    fn test_requirement_matches() {
+        assert!(requirement(">=14.0").matches(&version("14.0.0")));
+        assert!(!requirement(">=14.0").matches(&version("13.9")));
+        assert!(requirement(">=1.2, <2").matches(&version("1.9.3")));
+        assert!(!requirement(">=1.2, <2").matches(&version("2.0")));
+        assert!(requirement("1.2").matches(&version("1.2.0")));
+        assert!(requirement("==1.2").matches(&version("1.2")));
+        assert!(!requirement("> 1.2").matches(&version("1.2")));
+        assert!(requirement("<=1.2").matches(&version("1.1")));
+    }
+
+    #[test]
+    fn test_invalid_requirement() {
+        assert!(matches!(
+            ">=latest".parse::<VersionRequirement>(),
+            Err(VersionError::InvalidRequirement { .. })
+        ));
+        assert!(matches!(
+            ">=1.0,".parse::<VersionRequirement>(),
+            Err(VersionError::InvalidRequirement { .. })
+        ));
+    }
+
+    #[test]
+    fn test_extract_version() {
+        assert_eq!(
+            extract_version("ripgrep 14.1.0\n-SIMD -AVX", None).unwrap(),
+            version("14.1.0")
+        );
+        assert_eq!(
+            extract_version("Python 3.12.1 (built 2024.01)", Some(r"Python (\S+)")).unwrap(),
+            version("3.12.1")
+        );
+        assert_eq!(
+            extract_version("no digits here", None).unwrap_err(),
+            VersionError::NotFound {
+                output: "no digits here".to_string()
+            }
+        );
+        assert!(matches!(
+            extract_version("1.0", Some("(")),
+            Err(VersionError::InvalidPattern { .. })
+        ));
+    }
+}
@@ -193,6 +193,10 @@ pub enum ValidationErrorCategory {
     /// Path format errors
     ///
     PathFormat,
+
+    /// Packages that depend on each other in a loop
+    ///
+    DependencyCycle,
 }
 
 impl fmt::Display for ValidationErrorCategory {
@@ -204,6 +208,7 @@ impl fmt::Display for ValidationErrorCategory {
             Self::CommandSyntax => f.write_str("command_syntax"),
             Self::UrlFormat => f.write_str("url_format"),
             Self::PathFormat => f.write_str("path_format"),
+            Self::DependencyCycle => f.write_str("dependency_cycle"),
         }
     }
 }
@@ -12,16 +12,29 @@
 //! These tests complement the unit tests by testing the full service layer
 //! integration with real file system and command runner implementations.
 
+use std::{collections::HashMap, path::PathBuf};
+
 use tempfile::TempDir;
 use test_common::{
     assert_failed_operation, assert_successful_operation, collect_events,
-    create_service_invalid_package_file, create_service_test_package_file,
-    create_service_test_service, get_operation_result,
+    config::service_test_config_with_dir, create_package_file, create_service_invalid_package_file,
+    create_service_test_package_file, create_service_test_service, create_test_service_with_config,
+    get_operation_result, read_log, service::create_test_state_store,
 };
 
-use selfie::package::{
-    event::{OperationResult, PackageEvent},
-    service::PackageService,
+use selfie::{
+    config::AppConfigBuilder,
+    fs::real::RealFileSystem,
+    package::{
+        PackageBuilder,
+        event::{
+            CheckResult, CheckSummaryData, InstallPlanAction, InstallPlanData, InstallSummaryData,
+            OperationInfo, OperationResult, PackageEvent, RollbackData, RollbackStepResult,
+            UpgradeResult, UpgradeSummaryData, error::StreamedError, metadata::OperationType,
+        },
+        service::{InstallError, InstallHook, InstallOptions, PackageService, UninstallError},
+        state::{InstallReason, JsonStateStore, StateStore},
+    },
 };
 
 fn create_test_package_file(dir: &TempDir, name: &str, has_check: bool) -> std::path::PathBuf {
@@ -104,32 +117,190 @@ async fn test_service_check_no_check_command() {
     );
 }
 
+/// Set up packages for `check_all`: one installed, one missing, and one that can't be
+/// checked because it has no check command
+fn create_check_all_packages(dir: &TempDir) {
+    create_package_with_dependencies(dir, "a-installed", "true", "true", &[]);
+    create_test_package_file(dir, "b-unchecked", false);
+    create_package_with_dependencies(dir, "c-missing", "true", "exit 1", &[]);
+}
+
+fn check_summary(events: &[PackageEvent]) -> &CheckSummaryData {
+    events
+        .iter()
+        .find_map(|e| match e {
+            PackageEvent::CheckSummaryCompleted { check_summary, .. } => Some(check_summary),
+            _ => None,
+        })
+        .expect("Should have a check summary")
+}
+
+/// Test that `check_all` checks every package and collects failures
+#[tokio::test]
+async fn test_service_check_all_collects_results() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_check_all_packages(&temp_dir);
+    let config = AppConfigBuilder::default()
+        .environment("test")
+        .package_directory(temp_dir.path())
+        .use_colors(false)
+        .stop_on_error(false)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act
+    let stream = service.check_all().await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    let check_results = events
+        .iter()
+        .filter(|e| matches!(e, PackageEvent::CheckResultCompleted { .. }))
+        .count();
+    assert_eq!(check_results, 3);
+
+    let summary = check_summary(&events);
+    assert_eq!(summary.installed, ["a-installed"]);
+    assert_eq!(summary.failed, ["b-unchecked"]);
+    assert_eq!(summary.missing, ["c-missing"]);
+    assert!(summary.skipped.is_empty());
+    assert!(matches!(
+        get_operation_result(&events),
+        Some(OperationResult::Failure(_))
+    ));
+}
+
+/// Test that `check_all` stops at the first failure when `stop_on_error` is set
+#[tokio::test]
+async fn test_service_check_all_stop_on_error() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_check_all_packages(&temp_dir);
+    let config = AppConfigBuilder::default()
+        .environment("test")
+        .package_directory(temp_dir.path())
+        .use_colors(false)
+        .stop_on_error(true)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act
+    let stream = service.check_all().await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    let check_results = events
+        .iter()
+        .filter(|e| matches!(e, PackageEvent::CheckResultCompleted { .. }))
+        .count();
+    assert_eq!(check_results, 2);
+
+    let summary = check_summary(&events);
+    assert_eq!(summary.installed, ["a-installed"]);
+    assert_eq!(summary.failed, ["b-unchecked"]);
+    assert!(summary.missing.is_empty());
+    assert_eq!(summary.skipped, ["c-missing"]);
+}
+
+/// Test that `check_all` succeeds when every package is installed
+#[tokio::test]
+async fn test_service_check_all_installed() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_test_package_file(&temp_dir, "first", true);
+    create_test_package_file(&temp_dir, "second", true);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.check_all().await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    assert_eq!(check_summary(&events).installed, ["first", "second"]);
+}
+
 #[tokio::test]
 async fn test_service_install_success() {
     // Arrange
     let temp_dir = TempDir::new().unwrap();
-    create_test_package_file(&temp_dir, "install-package", true);
+    create_test_package_file(&temp_dir, "install-package", false);
     let service = create_service_test_service(&temp_dir);
 
     // Act
-    let stream = service.install("install-package").await;
+    let stream = service
+        .install("install-package", InstallOptions::default())
+        .await;
     let events = collect_events(stream).await;
 
     // Assert
     assert_successful_operation(&events);
 
-    // Verify we have progress events for install (should be 5 steps)
+    // Verify we have progress events for install (should be 7 steps)
+    let progress_events: Vec<_> = events
+        .iter()
+        .filter(|e| matches!(e, PackageEvent::Progress { .. }))
+        .collect();
+    assert_eq!(
+        progress_events.len(),
+        7,
+        "Should have 7 progress events for install operation"
+    );
+}
+
+#[tokio::test]
+async fn test_service_install_already_installed() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_test_package_file(&temp_dir, "installed-package", true);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .install("installed-package", InstallOptions::default())
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    let result = get_operation_result(&events);
+    assert!(matches!(result, Some(OperationResult::AlreadyInstalled(_))));
+
+    // Should stop after the check step, before running the install command
     let progress_events: Vec<_> = events
         .iter()
         .filter(|e| matches!(e, PackageEvent::Progress { .. }))
         .collect();
     assert_eq!(
         progress_events.len(),
-        5,
-        "Should have 5 progress events for install operation"
+        4,
+        "Should stop after the check step when already installed"
     );
 }
 
+#[tokio::test]
+async fn test_service_install_force_skips_check() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_test_package_file(&temp_dir, "installed-package", true);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .install(
+            "installed-package",
+            InstallOptions {
+                force: true,
+                ..InstallOptions::default()
+            },
+        )
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+}
+
 #[tokio::test]
 async fn test_service_list_packages() {
     // Arrange
@@ -226,6 +397,341 @@ async fn test_service_info_package() {
     );
 }
 
+#[tokio::test]
+async fn test_service_uses_fallback_environment() {
+    // Arrange: the package only has a `default` block
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join("fallback-package.yaml"),
+        r#"
+name: fallback-package
+version: 1.0.0
+environments:
+  default:
+    install: "true"
+    check: "true"
+"#,
+    )
+    .unwrap();
+    let config = AppConfigBuilder::default()
+        .environment("ubuntu")
+        .environment_fallbacks(vec!["debian", "default"])
+        .package_directory(temp_dir.path())
+        .state_directory(temp_dir.path().join("state"))
+        .use_colors(false)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act & Assert: check picks the fallback block
+    let events = collect_events(service.check("fallback-package").await).await;
+    assert_successful_operation(&events);
+
+    // Act & Assert: info reports which block was selected
+    let events = collect_events(service.info("fallback-package").await.unwrap()).await;
+    assert_successful_operation(&events);
+    let package_info = events
+        .iter()
+        .find_map(|e| match e {
+            PackageEvent::PackageInfoLoaded { package_info, .. } => Some(package_info),
+            _ => None,
+        })
+        .expect("Expected PackageInfoLoaded event");
+    assert_eq!(package_info.current_environment, "ubuntu");
+    assert_eq!(
+        package_info.selected_environment.as_deref(),
+        Some("default")
+    );
+    assert!(events.iter().any(|e| matches!(
+        e,
+        PackageEvent::EnvironmentStatusChecked { environment_status, .. }
+            if environment_status.environment_name == "default" && environment_status.is_current
+    )));
+}
+
+#[tokio::test]
+async fn test_service_expands_command_templates() {
+    // Arrange: the check command only passes once its placeholders are expanded
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join("templated-package.yaml"),
+        r#"
+name: templated-package
+version: 1.0.0
+vars:
+  greeting: hello world
+environments:
+  ubuntu:
+    install: "true"
+    check: test {{ greeting }} = "hello world" -a {{name}} = templated-package -a {{target}} = "/opt/my tools"
+  broken:
+    install: echo {{missing}}
+"#,
+    )
+    .unwrap();
+    let config = AppConfigBuilder::default()
+        .environment("ubuntu")
+        .var("target", "/opt/my tools")
+        .package_directory(temp_dir.path())
+        .state_directory(temp_dir.path().join("state"))
+        .use_colors(false)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act & Assert: the expanded, shell-quoted check command passes
+    let events = collect_events(service.check("templated-package").await).await;
+    assert_successful_operation(&events);
+
+    // Act & Assert: validation flags the unknown variable
+    let events = collect_events(service.validate("templated-package", None).await.unwrap()).await;
+    let validation_result = events
+        .iter()
+        .find_map(|e| match e {
+            PackageEvent::ValidationResultCompleted {
+                validation_result, ..
+            } => Some(validation_result),
+            _ => None,
+        })
+        .expect("Expected ValidationResultCompleted event");
+    assert!(validation_result.issues.iter().any(|issue| {
+        issue.field == "environments.broken.install" && issue.message.contains("{{missing}}")
+    }));
+}
+
+/// Test that a list of install commands runs in order, one step each, and that a
+/// failing step stops the rest and is named in the error
+#[tokio::test]
+async fn test_service_install_runs_install_steps() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("steps.log").display().to_string();
+    for (name, second_step) in [("stepped", "echo two"), ("broken-step", "false")] {
+        std::fs::write(
+            temp_dir.path().join(format!("{name}.yaml")),
+            format!(
+                r"
+name: {name}
+version: 1.0.0
+environments:
+  ubuntu:
+    install:
+      - echo one >> {log}
+      - {second_step} >> {log}
+      - echo three >> {log}
+"
+            ),
+        )
+        .unwrap();
+    }
+    let config = AppConfigBuilder::default()
+        .environment("ubuntu")
+        .package_directory(temp_dir.path())
+        .state_directory(temp_dir.path().join("state"))
+        .use_colors(false)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act & Assert: every step runs, in order, each with its own progress step
+    let events = collect_events(service.install("stepped", InstallOptions::default()).await).await;
+    assert_successful_operation(&events);
+    assert_eq!(
+        std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .collect::<Vec<_>>(),
+        ["one", "two", "three"]
+    );
+    let step_messages = events
+        .iter()
+        .filter(|e| match e {
+            PackageEvent::Progress { message, .. } => message.contains("`install step"),
+            _ => false,
+        })
+        .count();
+    assert_eq!(step_messages, 3);
+
+    // Act & Assert: the failing step is named and the step after it never runs
+    std::fs::remove_file(&log).unwrap();
+    let events = collect_events(
+        service
+            .install("broken-step", InstallOptions::default())
+            .await,
+    )
+    .await;
+    assert_failed_operation(&events);
+    assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), 1);
+    let failure = match get_operation_result(&events) {
+        Some(OperationResult::Failure(message)) => message,
+        other => panic!("Expected a failure, got {other:?}"),
+    };
+    assert!(failure.contains("install step 2/3"), "{failure}");
+    assert!(events.iter().any(|e| matches!(
+        e,
+        PackageEvent::Error {
+            error: StreamedError::InstallError(InstallError::StepFailed { step: 2, .. }),
+            ..
+        }
+    )));
+}
+
+/// Test that an environment's `install_timeout` overrides the global timeout, and
+/// that the timeout failure names it
+#[tokio::test]
+async fn test_service_install_uses_environment_timeout() {
+    // Arrange: the global timeout is generous, but the environment's isn't
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join("slow-package.yaml"),
+        r"
+name: slow-package
+version: 1.0.0
+environments:
+  ubuntu:
+    install: sleep 5
+    install_timeout: 1
+",
+    )
+    .unwrap();
+    let config = AppConfigBuilder::default()
+        .environment("ubuntu")
+        .command_timeout_unchecked(60)
+        .package_directory(temp_dir.path())
+        .state_directory(temp_dir.path().join("state"))
+        .use_colors(false)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act
+    let started = std::time::Instant::now();
+    let events = collect_events(
+        service
+            .install("slow-package", InstallOptions::default())
+            .await,
+    )
+    .await;
+
+    // Assert
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    assert_failed_operation(&events);
+    let failure = match get_operation_result(&events) {
+        Some(OperationResult::Failure(message)) => message,
+        other => panic!("Expected a failure, got {other:?}"),
+    };
+    assert!(failure.contains("`install_timeout`"), "{failure}");
+}
+
+/// Test that commands run in the environment's `cwd` with its `env` variables and
+/// the `SELFIE_*` context
+#[tokio::test]
+async fn test_service_install_uses_cwd_and_env() {
+    // Arrange: a relative `cwd` is taken from the package directory
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::create_dir(temp_dir.path().join("build")).unwrap();
+    std::fs::write(
+        temp_dir.path().join("env-package.yaml"),
+        r#"
+name: env-package
+version: 1.0.0
+environments:
+  ubuntu:
+    cwd: build
+    env:
+      GREETING: hello there
+    install: echo "$GREETING $SELFIE_PACKAGE $SELFIE_ENVIRONMENT $SELFIE_PACKAGE_DIR" > installed.txt
+"#,
+    )
+    .unwrap();
+    let config = AppConfigBuilder::default()
+        .environment("ubuntu")
+        .package_directory(temp_dir.path())
+        .state_directory(temp_dir.path().join("state"))
+        .use_colors(false)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act
+    let events = collect_events(
+        service
+            .install("env-package", InstallOptions::default())
+            .await,
+    )
+    .await;
+
+    // Assert
+    assert_successful_operation(&events);
+    let written = std::fs::read_to_string(temp_dir.path().join("build/installed.txt")).unwrap();
+    assert_eq!(
+        written.trim(),
+        format!(
+            "hello there env-package ubuntu {}",
+            temp_dir.path().display()
+        )
+    );
+}
+
+/// Test that `when:` conditions skip whole environments and single hook commands
+#[tokio::test]
+async fn test_service_skips_unmet_when_conditions() {
+    // Arrange: one package only applies when an unset variable is set, the other
+    // has a post_install command that only applies when a missing file exists
+    let temp_dir = TempDir::new().unwrap();
+    let marker = |name: &str| temp_dir.path().join(name).display().to_string();
+    std::fs::write(
+        temp_dir.path().join("ci-only.yaml"),
+        r"
+name: ci-only
+version: 1.0.0
+environments:
+  ubuntu:
+    when: { env_var: SELFIE_TEST_UNSET_VARIABLE }
+    install: 'false'
+",
+    )
+    .unwrap();
+    std::fs::write(
+        temp_dir.path().join("hooked.yaml"),
+        format!(
+            r"
+name: hooked
+version: 1.0.0
+environments:
+  ubuntu:
+    install: 'true'
+    post_install:
+      - run: touch {skipped}
+        when: {{ file_exists: {missing} }}
+      - touch {ran}
+",
+            skipped = marker("skipped"),
+            missing = marker("missing"),
+            ran = marker("ran"),
+        ),
+    )
+    .unwrap();
+    let config = AppConfigBuilder::default()
+        .environment("ubuntu")
+        .package_directory(temp_dir.path())
+        .state_directory(temp_dir.path().join("state"))
+        .use_colors(false)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act & Assert: the package that doesn't apply is skipped, not failed
+    let events = collect_events(service.install("ci-only", InstallOptions::default()).await).await;
+    assert!(events.iter().any(|e| matches!(
+        e,
+        PackageEvent::Completed {
+            result: OperationResult::Skipped(_),
+            ..
+        }
+    )));
+
+    // Act & Assert: only the hook command without unmet conditions runs
+    let events = collect_events(service.install("hooked", InstallOptions::default()).await).await;
+    assert_successful_operation(&events);
+    assert!(temp_dir.path().join("ran").exists());
+    assert!(!temp_dir.path().join("skipped").exists());
+}
+
 /// Test the validate service with a well-formed package
 /// This verifies that validation logic works correctly for valid packages
 #[tokio::test]
@@ -334,3 +840,2063 @@ async fn test_service_error_handling() {
         "Should have completed event even for failures"
     );
 }
+
+fn create_package_with_dependencies(
+    dir: &TempDir,
+    name: &str,
+    install: &str,
+    check: &str,
+    dependencies: &[&str],
+) {
+    let package = PackageBuilder::default()
+        .name(name)
+        .version("1.0.0")
+        .environment("test", |b| {
+            b.install(install)
+                .check_some(check)
+                .dependencies(dependencies.to_vec())
+        })
+        .build();
+    create_package_file(dir, &package);
+}
+
+/// Test that dependencies are installed, in dependency order, before the package
+#[tokio::test]
+async fn test_service_install_dependencies_in_order() {
+    // Arrange: app -> (lib-a, lib-b), lib-a -> lib-b
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("install.log");
+    let log = log.display();
+    create_package_with_dependencies(
+        &temp_dir,
+        "app",
+        &format!("echo app >> {log}"),
+        &format!("grep -qx app {log}"),
+        &["lib-a", "lib-b"],
+    );
+    create_package_with_dependencies(
+        &temp_dir,
+        "lib-a",
+        &format!("echo lib-a >> {log}"),
+        &format!("grep -qx lib-a {log}"),
+        &["lib-b"],
+    );
+    create_package_with_dependencies(
+        &temp_dir,
+        "lib-b",
+        &format!("echo lib-b >> {log}"),
+        &format!("grep -qx lib-b {log}"),
+        &[],
+    );
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    let installed = std::fs::read_to_string(temp_dir.path().join("install.log")).unwrap();
+    assert_eq!(
+        installed.lines().collect::<Vec<_>>(),
+        ["lib-b", "lib-a", "app"]
+    );
+
+    // 7 steps for the package plus one per dependency; each dependency reports its
+    // own check + install + verify steps under a child operation
+    let root_id = started_operation_id(&events);
+    let (root_progress, child_progress): (Vec<_>, Vec<_>) = events
+        .iter()
+        .filter_map(|e| match e {
+            PackageEvent::Progress { operation_info, .. } => Some(operation_info),
+            _ => None,
+        })
+        .partition(|info| info.id == root_id);
+    assert_eq!(root_progress.len(), 9);
+    assert_eq!(child_progress.len(), 6);
+}
+
+/// The id of the operation announced by the stream's `Started` event
+fn started_operation_id(events: &[PackageEvent]) -> uuid::Uuid {
+    events
+        .iter()
+        .find_map(|e| match e {
+            PackageEvent::Started { operation_info } => Some(operation_info.id),
+            _ => None,
+        })
+        .expect("Should have a started event")
+}
+
+/// Test that independent dependencies are installed at the same time
+#[tokio::test]
+async fn test_service_install_independent_dependencies_in_parallel() {
+    // Arrange: each dependency's install waits for the other's to have started,
+    // which only finishes if they run concurrently
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path().display();
+    let wait_for = |marker: &str, other: &str| {
+        format!(
+            "touch {dir}/{marker}; for i in $(seq 1 100); do [ -f {dir}/{other} ] && exit 0; sleep 0.05; done; exit 1"
+        )
+    };
+    create_package_with_dependencies(
+        &temp_dir,
+        "app",
+        &format!("touch {dir}/app.done"),
+        &format!("test -f {dir}/app.done"),
+        &["lib-a", "lib-b"],
+    );
+    create_package_with_dependencies(
+        &temp_dir,
+        "lib-a",
+        &wait_for("a.started", "b.started"),
+        &format!("test -f {dir}/a.started"),
+        &[],
+    );
+    create_package_with_dependencies(
+        &temp_dir,
+        "lib-b",
+        &wait_for("b.started", "a.started"),
+        &format!("test -f {dir}/b.started"),
+        &[],
+    );
+    let config = AppConfigBuilder::default()
+        .environment("test")
+        .package_directory(temp_dir.path())
+        .use_colors(false)
+        .max_parallel_unchecked(2)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+
+    // Each dependency's events carry their own operation id, pointing back to the
+    // install of `app`
+    let root_id = started_operation_id(&events);
+    let mut child_ids: HashMap<String, uuid::Uuid> = HashMap::new();
+    for event in &events {
+        let info = event.operation_info();
+        if info.id == root_id {
+            assert_eq!(info.package_name, "app");
+            assert_eq!(info.parent_id, None);
+            continue;
+        }
+
+        assert_eq!(info.parent_id, Some(root_id));
+        let id = child_ids
+            .entry(info.package_name.clone())
+            .or_insert(info.id);
+        assert_eq!(
+            *id, info.id,
+            "{} should use one operation id",
+            info.package_name
+        );
+    }
+    assert_eq!(child_ids.len(), 2);
+    assert_ne!(child_ids["lib-a"], child_ids["lib-b"]);
+}
+
+/// Test that `max_parallel_installations` limits how many dependencies run at once
+#[tokio::test]
+async fn test_service_install_dependencies_respects_parallel_limit() {
+    // Arrange: each install fails if another one is running at the same time
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path().display();
+    let exclusive = |name: &str| {
+        format!(
+            "[ -f {dir}/running ] && exit 1; touch {dir}/running; sleep 0.2; rm {dir}/running; touch {dir}/{name}.done"
+        )
+    };
+    create_package_with_dependencies(
+        &temp_dir,
+        "app",
+        &format!("touch {dir}/app.done"),
+        &format!("test -f {dir}/app.done"),
+        &["lib-a", "lib-b", "lib-c"],
+    );
+    for name in ["lib-a", "lib-b", "lib-c"] {
+        create_package_with_dependencies(
+            &temp_dir,
+            name,
+            &exclusive(name),
+            &format!("test -f {dir}/{name}.done"),
+            &[],
+        );
+    }
+    let config = AppConfigBuilder::default()
+        .environment("test")
+        .package_directory(temp_dir.path())
+        .use_colors(false)
+        .max_parallel_unchecked(1)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+}
+
+fn install_plan(events: &[PackageEvent]) -> &InstallPlanData {
+    events
+        .iter()
+        .find_map(|e| match e {
+            PackageEvent::InstallPlanCompleted { install_plan, .. } => Some(install_plan),
+            _ => None,
+        })
+        .expect("Should have an install plan")
+}
+
+/// Test that a dry run reports the install plan without installing anything
+#[tokio::test]
+async fn test_service_install_dry_run_plan() {
+    // Arrange: app -> (lib-a, lib-b), lib-a -> lib-b, and lib-b is already installed
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("install.log");
+    let log = log.display();
+    create_package_with_dependencies(
+        &temp_dir,
+        "app",
+        &format!("echo app >> {log}"),
+        &format!("grep -qx app {log}"),
+        &["lib-a", "lib-b"],
+    );
+    create_package_with_dependencies(
+        &temp_dir,
+        "lib-a",
+        &format!("echo lib-a >> {log}"),
+        &format!("grep -qx lib-a {log}"),
+        &["lib-b"],
+    );
+    create_package_with_dependencies(
+        &temp_dir,
+        "lib-b",
+        &format!("echo lib-b >> {log}"),
+        "true",
+        &[],
+    );
+    let service = create_service_test_service(&temp_dir);
+    let options = InstallOptions {
+        dry_run: true,
+        ..InstallOptions::default()
+    };
+
+    // Act
+    let stream = service.install("app", options).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    assert!(
+        !temp_dir.path().join("install.log").exists(),
+        "A dry run shouldn't run any install commands"
+    );
+
+    let plan = install_plan(&events);
+    assert_eq!(plan.requested, ["app"]);
+    assert_eq!(plan.environment, "test");
+    let steps: Vec<_> = plan
+        .steps
+        .iter()
+        .map(|step| (step.package_name.as_str(), step.action))
+        .collect();
+    assert_eq!(
+        steps,
+        [
+            ("lib-b", InstallPlanAction::AlreadyInstalled),
+            ("lib-a", InstallPlanAction::Install),
+            ("app", InstallPlanAction::Install),
+        ]
+    );
+    assert_eq!(
+        plan.steps[1].install_command,
+        format!("echo lib-a >> {log}")
+    );
+    assert_eq!(plan.steps[1].required_by.as_deref(), Some("app"));
+    assert_eq!(plan.steps[2].required_by, None);
+
+    // Every step of the dry run is accounted for
+    let last_progress = events.iter().rev().find_map(|e| match e {
+        PackageEvent::Progress {
+            step, total_steps, ..
+        } => Some((*step, *total_steps)),
+        _ => None,
+    });
+    assert_eq!(last_progress, Some((7, 7)));
+}
+
+/// Test that a dry run for an installed package still checks its dependencies
+#[tokio::test]
+async fn test_service_install_dry_run_already_installed() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_package_with_dependencies(&temp_dir, "app", "true", "true", &["lib"]);
+    create_package_with_dependencies(&temp_dir, "lib", "true", "exit 1", &[]);
+    let service = create_service_test_service(&temp_dir);
+    let options = InstallOptions {
+        dry_run: true,
+        ..InstallOptions::default()
+    };
+
+    // Act
+    let stream = service.install("app", options).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    let plan = install_plan(&events);
+    let actions: Vec<_> = plan
+        .steps
+        .iter()
+        .map(|step| (step.package_name.as_str(), step.action))
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            ("lib", InstallPlanAction::Install),
+            ("app", InstallPlanAction::AlreadyInstalled),
+        ]
+    );
+}
+
+/// Test that a missing dependency is installed even when the package itself is
+/// already installed
+#[tokio::test]
+async fn test_service_install_already_installed_installs_missing_dependency() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let marker = temp_dir.path().join("lib-installed");
+    create_package_with_dependencies(&temp_dir, "app", "exit 1", "true", &["lib"]);
+    create_package_with_dependencies(
+        &temp_dir,
+        "lib",
+        &format!("touch {}", marker.display()),
+        &format!("test -f {}", marker.display()),
+        &[],
+    );
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::AlreadyInstalled(msg)) => assert!(msg.ends_with(": lib"), "{msg}"),
+        other => panic!("Expected already installed, got: {other:?}"),
+    }
+    assert!(marker.exists());
+}
+
+/// Test that installing several packages runs a shared dependency once
+#[tokio::test]
+async fn test_service_install_packages_shares_dependencies() {
+    // Arrange: app -> lib, tool -> lib
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("install.log");
+    let log = log.display();
+    for (name, dependencies) in [("app", &["lib"][..]), ("tool", &["lib"]), ("lib", &[])] {
+        create_package_with_dependencies(
+            &temp_dir,
+            name,
+            &format!("echo {name} >> {log}"),
+            &format!("grep -qx {name} {log}"),
+            dependencies,
+        );
+    }
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .install_packages(
+            &["app".to_string(), "tool".to_string()],
+            InstallOptions::default(),
+        )
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    let mut installed = read_log(temp_dir.path().join("install.log"));
+    assert_eq!(installed[0], "lib");
+    installed.sort();
+    assert_eq!(installed, ["app", "lib", "tool"]);
+}
+
+/// Test that requested packages that are already installed are left alone
+#[tokio::test]
+async fn test_service_install_packages_skips_installed() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("install.log");
+    let log = log.display();
+    create_package_with_dependencies(
+        &temp_dir,
+        "app",
+        &format!("echo app >> {log}"),
+        "true",
+        &["lib"],
+    );
+    create_package_with_dependencies(
+        &temp_dir,
+        "lib",
+        &format!("echo lib >> {log}"),
+        &format!("grep -qx lib {log}"),
+        &[],
+    );
+    create_package_with_dependencies(
+        &temp_dir,
+        "tool",
+        &format!("echo tool >> {log}"),
+        &format!("grep -qx tool {log}"),
+        &[],
+    );
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .install_packages(
+            &["app".to_string(), "tool".to_string()],
+            InstallOptions::default(),
+        )
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert: app is installed, so its missing dependency isn't touched either
+    assert_successful_operation(&events);
+    assert_eq!(read_log(temp_dir.path().join("install.log")), ["tool"]);
+}
+
+/// Test that installing everything only picks packages for the current environment
+#[tokio::test]
+async fn test_service_install_all() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("install.log");
+    let log = log.display();
+    create_package_with_dependencies(
+        &temp_dir,
+        "app",
+        &format!("echo app >> {log}"),
+        &format!("grep -qx app {log}"),
+        &[],
+    );
+    create_package_with_dependencies(
+        &temp_dir,
+        "tool",
+        &format!("echo tool >> {log}"),
+        &format!("grep -qx tool {log}"),
+        &[],
+    );
+    std::fs::write(
+        temp_dir.path().join("elsewhere.yml"),
+        format!(
+            "name: elsewhere\nversion: \"1.0.0\"\nenvironments:\n  other:\n    install: \"echo elsewhere >> {log}\"\n"
+        ),
+    )
+    .unwrap();
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install_all(InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    let mut installed = read_log(temp_dir.path().join("install.log"));
+    installed.sort();
+    assert_eq!(installed, ["app", "tool"]);
+}
+
+fn failing_and_independent_packages(dir: &TempDir, stop_on_error: bool) -> impl PackageService {
+    let log = dir.path().join("install.log");
+    let log = log.display();
+    create_package_with_dependencies(dir, "a-broken", "exit 1", "exit 1", &[]);
+    create_package_with_dependencies(
+        dir,
+        "b-fine",
+        &format!("echo b-fine >> {log}"),
+        &format!("grep -qx b-fine {log}"),
+        &[],
+    );
+    let config = AppConfigBuilder::default()
+        .environment("test")
+        .package_directory(dir.path())
+        .use_colors(false)
+        .max_parallel_unchecked(1)
+        .stop_on_error(stop_on_error)
+        .build();
+    create_test_service_with_config(config)
+}
+
+/// Test that a failure stops the remaining installs when `stop_on_error` is set
+#[tokio::test]
+async fn test_service_install_all_stop_on_error() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let service = failing_and_independent_packages(&temp_dir, true);
+
+    // Act
+    let stream = service.install_all(InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => {
+            assert!(msg.contains("a-broken"), "{msg}");
+            assert!(msg.contains("1 skipped"), "{msg}");
+        }
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert!(read_log(temp_dir.path().join("install.log")).is_empty());
+}
+
+/// Test that independent packages are still installed when `stop_on_error` is off
+#[tokio::test]
+async fn test_service_install_all_keeps_going() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let service = failing_and_independent_packages(&temp_dir, false);
+
+    // Act
+    let stream = service.install_all(InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert!(matches!(
+        get_operation_result(&events),
+        Some(OperationResult::Failure(_))
+    ));
+    assert_eq!(read_log(temp_dir.path().join("install.log")), ["b-fine"]);
+}
+
+fn install_summary(events: &[PackageEvent]) -> &InstallSummaryData {
+    events
+        .iter()
+        .find_map(|event| match event {
+            PackageEvent::InstallSummaryCompleted {
+                install_summary, ..
+            } => Some(install_summary),
+            _ => None,
+        })
+        .expect("Expected an install summary")
+}
+
+/// Write a package whose dependency doesn't exist next to one that installs fine
+fn unresolvable_and_independent_packages(
+    dir: &TempDir,
+    stop_on_error: bool,
+) -> impl PackageService {
+    let log = dir.path().join("install.log");
+    let log = log.display();
+    create_package_with_dependencies(dir, "a-broken", "true", "exit 1", &["missing"]);
+    create_package_with_dependencies(
+        dir,
+        "b-fine",
+        &format!("echo b-fine >> {log}"),
+        &format!("grep -qx b-fine {log}"),
+        &[],
+    );
+    let config = AppConfigBuilder::default()
+        .environment("test")
+        .package_directory(dir.path())
+        .use_colors(false)
+        .stop_on_error(stop_on_error)
+        .build();
+    create_test_service_with_config(config)
+}
+
+/// Test that a package whose dependencies can't be resolved only fails itself
+#[tokio::test]
+async fn test_service_install_all_unresolvable_package() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let service = unresolvable_and_independent_packages(&temp_dir, false);
+
+    // Act
+    let stream = service.install_all(InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => assert!(msg.contains("a-broken"), "{msg}"),
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert_eq!(read_log(temp_dir.path().join("install.log")), ["b-fine"]);
+    let summary = install_summary(&events);
+    assert_eq!(summary.failed, ["a-broken"]);
+    assert_eq!(summary.installed, ["b-fine"]);
+}
+
+/// Test that an unresolvable package stops everything else when `stop_on_error` is
+/// set
+#[tokio::test]
+async fn test_service_install_all_unresolvable_package_stop_on_error() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let service = unresolvable_and_independent_packages(&temp_dir, true);
+
+    // Act
+    let stream = service.install_all(InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert!(read_log(temp_dir.path().join("install.log")).is_empty());
+    let summary = install_summary(&events);
+    assert_eq!(summary.failed, ["a-broken"]);
+    assert_eq!(summary.skipped, ["b-fine"]);
+}
+
+/// Test that a requested package that can't be loaded only fails itself
+#[tokio::test]
+async fn test_service_install_packages_missing_package() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let service = unresolvable_and_independent_packages(&temp_dir, false);
+
+    // Act
+    let names = ["nowhere", "b-fine"].map(str::to_string);
+    let stream = service
+        .install_packages(&names, InstallOptions::default())
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_failed_operation(&events);
+    assert_eq!(read_log(temp_dir.path().join("install.log")), ["b-fine"]);
+    assert_eq!(install_summary(&events).failed, ["nowhere"]);
+}
+
+/// Test that a dry run lists the packages that can't be resolved
+#[tokio::test]
+async fn test_service_install_all_dry_run_unresolvable_package() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let service = unresolvable_and_independent_packages(&temp_dir, false);
+    let options = InstallOptions {
+        dry_run: true,
+        ..InstallOptions::default()
+    };
+
+    // Act
+    let stream = service.install_all(options).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert!(matches!(
+        get_operation_result(&events),
+        Some(OperationResult::Failure(_))
+    ));
+    let plan = install_plan(&events);
+    assert_eq!(plan.failed, ["a-broken"]);
+    assert_eq!(plan.to_install().count(), 1);
+}
+
+/// Test that a dry run for several packages plans their merged graph
+#[tokio::test]
+async fn test_service_install_packages_dry_run() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_package_with_dependencies(&temp_dir, "app", "install-app", "exit 1", &["lib"]);
+    create_package_with_dependencies(&temp_dir, "tool", "install-tool", "exit 1", &["lib"]);
+    create_package_with_dependencies(&temp_dir, "lib", "install-lib", "exit 1", &[]);
+    create_package_with_dependencies(&temp_dir, "done", "install-done", "true", &[]);
+    let service = create_service_test_service(&temp_dir);
+    let options = InstallOptions {
+        dry_run: true,
+        ..InstallOptions::default()
+    };
+
+    // Act
+    let names = ["app", "tool", "done"].map(str::to_string);
+    let stream = service.install_packages(&names, options).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    let plan = install_plan(&events);
+    let steps: Vec<_> = plan
+        .steps
+        .iter()
+        .map(|step| (step.package_name.as_str(), step.action))
+        .collect();
+    assert_eq!(
+        steps,
+        [
+            ("done", InstallPlanAction::AlreadyInstalled),
+            ("lib", InstallPlanAction::Install),
+            ("app", InstallPlanAction::Install),
+            ("tool", InstallPlanAction::Install),
+        ]
+    );
+    assert_eq!(plan.requested, ["done", "app", "tool"]);
+}
+
+/// Test that already installed dependencies are checked but not reinstalled
+#[tokio::test]
+async fn test_service_install_skips_installed_dependencies() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("install.log");
+    let log = log.display();
+    create_package_with_dependencies(
+        &temp_dir,
+        "app",
+        &format!("echo app >> {log}"),
+        &format!("grep -qx app {log}"),
+        &["lib"],
+    );
+    create_package_with_dependencies(&temp_dir, "lib", &format!("echo lib >> {log}"), "true", &[]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    let installed = std::fs::read_to_string(temp_dir.path().join("install.log")).unwrap();
+    assert_eq!(installed.lines().collect::<Vec<_>>(), ["app"]);
+}
+
+/// Test that an install whose check still fails afterwards is reported as broken
+#[tokio::test]
+async fn test_service_install_not_detected_after_install() {
+    // Arrange: the install command "succeeds" without installing anything
+    let temp_dir = TempDir::new().unwrap();
+    create_package_with_dependencies(&temp_dir, "broken", "true", "exit 1", &[]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("broken", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => assert!(
+            msg.contains("install command succeeded but package not detected"),
+            "{msg}"
+        ),
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert!(events.iter().any(|e| matches!(
+        e,
+        PackageEvent::Error {
+            error: StreamedError::InstallError(InstallError::NotDetected { .. }),
+            ..
+        }
+    )));
+}
+
+/// Test that a dependency that isn't detected after installing fails the install
+#[tokio::test]
+async fn test_service_install_dependency_not_detected_after_install() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let marker = temp_dir.path().join("app-installed");
+    create_package_with_dependencies(
+        &temp_dir,
+        "app",
+        &format!("touch {}", marker.display()),
+        "exit 1",
+        &["lib"],
+    );
+    create_package_with_dependencies(&temp_dir, "lib", "true", "exit 1", &[]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => {
+            assert!(msg.contains("`lib`"), "{msg}");
+            assert!(msg.contains("not detected"), "{msg}");
+        }
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert!(
+        !marker.exists(),
+        "app shouldn't install without its dependency"
+    );
+}
+
+/// Test that a failed dependency stops anything that depends on it from installing
+#[tokio::test]
+async fn test_service_install_failed_dependency_stops_install() {
+    // Arrange: app -> (lib-a, lib-b), lib-b -> lib-a, and lib-a fails
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("install.log");
+    let log = log.display();
+    create_package_with_dependencies(
+        &temp_dir,
+        "app",
+        &format!("echo app >> {log}"),
+        &format!("grep -qx app {log}"),
+        &["lib-a", "lib-b"],
+    );
+    create_package_with_dependencies(&temp_dir, "lib-a", "exit 1", "exit 1", &[]);
+    create_package_with_dependencies(
+        &temp_dir,
+        "lib-b",
+        &format!("echo lib-b >> {log}"),
+        &format!("grep -qx lib-b {log}"),
+        &["lib-a"],
+    );
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    let result = get_operation_result(&events).expect("Should have an operation result");
+    match result {
+        OperationResult::Failure(msg) => assert!(msg.contains("lib-a"), "{msg}"),
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert!(
+        !temp_dir.path().join("install.log").exists(),
+        "Nothing depending on lib-a should be installed"
+    );
+}
+
+/// Test that a missing dependency fails the install before any command runs
+#[tokio::test]
+async fn test_service_install_missing_dependency() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let marker = temp_dir.path().join("ran");
+    create_package_with_dependencies(
+        &temp_dir,
+        "app",
+        "true",
+        &format!("touch {}", marker.display()),
+        &["missing-lib"],
+    );
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_failed_operation(&events);
+    assert!(!marker.exists(), "No command should run for a broken plan");
+    assert!(events.iter().any(|e| matches!(
+        e,
+        PackageEvent::Error {
+            error: StreamedError::DependencyError(_),
+            ..
+        }
+    )));
+}
+
+/// Test that a dependency cycle is refused before any command runs
+#[tokio::test]
+async fn test_service_install_dependency_cycle() {
+    // Arrange: a -> b -> a
+    let temp_dir = TempDir::new().unwrap();
+    let marker = temp_dir.path().join("ran");
+    let touch = format!("touch {}", marker.display());
+    create_package_with_dependencies(&temp_dir, "a", &touch, &touch, &["b"]);
+    create_package_with_dependencies(&temp_dir, "b", &touch, &touch, &["a"]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("a", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_failed_operation(&events);
+    assert!(!marker.exists(), "No command should run for a cyclic plan");
+    let result = get_operation_result(&events);
+    assert!(
+        matches!(result, Some(OperationResult::Failure(msg)) if msg.contains("a -> b -> a")),
+        "Failure should name the cycle: {result:?}"
+    );
+}
+
+/// Create a package whose install, check, and uninstall commands work on a marker file
+fn create_uninstallable_package(dir: &TempDir, name: &str, dependencies: &[&str]) -> PathBuf {
+    let marker = dir.path().join(format!("{name}-installed"));
+    let package = PackageBuilder::default()
+        .name(name)
+        .version("1.0.0")
+        .environment("test", |b| {
+            b.install(format!("touch {}", marker.display()))
+                .check_some(format!("test -f {}", marker.display()))
+                .uninstall_some(format!("rm {}", marker.display()))
+                .dependencies(dependencies.to_vec())
+        })
+        .build();
+    create_package_file(dir, &package);
+    marker
+}
+
+#[tokio::test]
+async fn test_service_uninstall_success() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let marker = create_uninstallable_package(&temp_dir, "test-package", &[]);
+    std::fs::write(&marker, "").unwrap();
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.uninstall("test-package").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    assert!(!marker.exists(), "The uninstall command should have run");
+    let progress_count = events
+        .iter()
+        .filter(|e| matches!(e, PackageEvent::Progress { .. }))
+        .count();
+    assert_eq!(progress_count, 7);
+}
+
+#[tokio::test]
+async fn test_service_uninstall_not_installed() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_uninstallable_package(&temp_dir, "test-package", &[]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.uninstall("test-package").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert!(matches!(
+        get_operation_result(&events),
+        Some(OperationResult::NotInstalled(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_service_uninstall_without_uninstall_command() {
+    // Arrange: installed, but there's no way to uninstall it
+    let temp_dir = TempDir::new().unwrap();
+    create_package_with_dependencies(&temp_dir, "test-package", "true", "true", &[]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.uninstall("test-package").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => {
+            assert!(msg.contains("No uninstall command defined"), "{msg}");
+        }
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+}
+
+/// Test that an uninstall whose check still passes afterwards is reported as broken
+#[tokio::test]
+async fn test_service_uninstall_still_detected_after_uninstall() {
+    // Arrange: the uninstall command "succeeds" without removing anything
+    let temp_dir = TempDir::new().unwrap();
+    let content = r#"name: "test-package"
+version: "1.0.0"
+environments:
+  test:
+    install: "true"
+    check: "true"
+    uninstall: "true"
+"#;
+    std::fs::write(temp_dir.path().join("test-package.yml"), content).unwrap();
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.uninstall("test-package").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_failed_operation(&events);
+    assert!(events.iter().any(|e| matches!(
+        e,
+        PackageEvent::Error {
+            error: StreamedError::UninstallError(UninstallError::StillDetected { .. }),
+            ..
+        }
+    )));
+}
+
+/// Test that uninstalling a dependency of installed packages warns, but still runs
+#[tokio::test]
+async fn test_service_uninstall_warns_about_installed_dependents() {
+    // Arrange: app and tool depend on lib, but only app is installed
+    let temp_dir = TempDir::new().unwrap();
+    let lib_marker = create_uninstallable_package(&temp_dir, "lib", &[]);
+    let app_marker = create_uninstallable_package(&temp_dir, "app", &["lib"]);
+    create_uninstallable_package(&temp_dir, "tool", &["lib"]);
+    std::fs::write(&lib_marker, "").unwrap();
+    std::fs::write(&app_marker, "").unwrap();
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.uninstall("lib").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    assert!(!lib_marker.exists());
+    let warnings: Vec<_> = events
+        .iter()
+        .filter_map(|e| match e {
+            PackageEvent::Warning { message, .. } => Some(message.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        warnings,
+        ["Package 'lib' is a dependency of installed package(s): app"]
+    );
+}
+
+/// Test that a dependent whose `when:` doesn't hold on this machine isn't
+/// reported, even if its check passes
+#[tokio::test]
+async fn test_service_uninstall_skips_dependents_with_unmet_conditions() {
+    // Arrange: gated depends on lib and looks installed, but only applies when an
+    // unset variable is set
+    let temp_dir = TempDir::new().unwrap();
+    let lib_marker = create_uninstallable_package(&temp_dir, "lib", &[]);
+    std::fs::write(&lib_marker, "").unwrap();
+    let content = r#"name: "gated"
+version: "1.0.0"
+environments:
+  test:
+    when: { env_var: SELFIE_TEST_UNSET_VARIABLE }
+    install: "true"
+    check: "true"
+    dependencies: [lib]
+"#;
+    std::fs::write(temp_dir.path().join("gated.yml"), content).unwrap();
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.uninstall("lib").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    assert!(
+        !events
+            .iter()
+            .any(|e| matches!(e, PackageEvent::Warning { .. }))
+    );
+}
+
+/// Create a package that's installed when its marker file exists, and whose update
+/// command (if any) appends its name to `upgrade.log`
+fn create_upgradable_package(
+    dir: &TempDir,
+    name: &str,
+    installed: bool,
+    update: Option<&str>,
+) -> PathBuf {
+    let marker = dir.path().join(format!("{name}-installed"));
+    if installed {
+        std::fs::write(&marker, "").unwrap();
+    }
+    let log = dir.path().join("install.log");
+    let package = PackageBuilder::default()
+        .name(name)
+        .version("1.0.0")
+        .environment("test", |b| {
+            b.install(format!("echo {name} >> {}", log.display()))
+                .check_some(format!("test -f {}", marker.display()))
+                .update(update)
+        })
+        .build();
+    create_package_file(dir, &package);
+    marker
+}
+
+fn upgrade_results(events: &[PackageEvent]) -> Vec<(String, UpgradeResult)> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            PackageEvent::UpgradeResultCompleted { upgrade_result, .. } => Some((
+                upgrade_result.package_name.clone(),
+                upgrade_result.result.clone(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_service_upgrade_success() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("upgrade.log");
+    create_upgradable_package(
+        &temp_dir,
+        "test-package",
+        true,
+        Some(&format!("echo test-package >> {}", log.display())),
+    );
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.upgrade("test-package").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    assert_eq!(
+        read_log(temp_dir.path().join("upgrade.log")),
+        ["test-package"]
+    );
+    assert_eq!(
+        upgrade_results(&events),
+        [("test-package".to_string(), UpgradeResult::Upgraded)]
+    );
+}
+
+/// Test that a package without an update command isn't reinstalled instead
+#[tokio::test]
+async fn test_service_upgrade_not_upgradable() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_upgradable_package(&temp_dir, "test-package", true, None);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.upgrade("test-package").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => assert!(msg.contains("not upgradable"), "{msg}"),
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert_eq!(
+        upgrade_results(&events),
+        [("test-package".to_string(), UpgradeResult::NotUpgradable)]
+    );
+    assert!(read_log(temp_dir.path().join("install.log")).is_empty());
+}
+
+#[tokio::test]
+async fn test_service_upgrade_not_installed() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("upgrade.log");
+    create_upgradable_package(
+        &temp_dir,
+        "test-package",
+        false,
+        Some(&format!("echo test-package >> {}", log.display())),
+    );
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.upgrade("test-package").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert!(matches!(
+        get_operation_result(&events),
+        Some(OperationResult::NotInstalled(_))
+    ));
+    assert!(read_log(temp_dir.path().join("upgrade.log")).is_empty());
+}
+
+fn upgrade_all_packages(dir: &TempDir, stop_on_error: bool) -> impl PackageService {
+    let log = dir.path().join("upgrade.log");
+    let log = log.display();
+    create_upgradable_package(dir, "a-broken", true, Some("exit 1"));
+    create_upgradable_package(dir, "b-fine", true, Some(&format!("echo b-fine >> {log}")));
+    create_upgradable_package(dir, "c-fixed", true, None);
+    create_upgradable_package(dir, "d-missing", false, Some(&format!("echo d >> {log}")));
+    let config = AppConfigBuilder::default()
+        .environment("test")
+        .package_directory(dir.path())
+        .use_colors(false)
+        .stop_on_error(stop_on_error)
+        .build();
+    create_test_service_with_config(config)
+}
+
+#[tokio::test]
+async fn test_service_upgrade_all() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let service = upgrade_all_packages(&temp_dir, false);
+
+    // Act
+    let stream = service.upgrade_all().await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert!(matches!(
+        get_operation_result(&events),
+        Some(OperationResult::Failure(_))
+    ));
+    assert_eq!(read_log(temp_dir.path().join("upgrade.log")), ["b-fine"]);
+    let summary = events
+        .iter()
+        .find_map(|e| match e {
+            PackageEvent::UpgradeSummaryCompleted {
+                upgrade_summary, ..
+            } => Some(upgrade_summary.clone()),
+            _ => None,
+        })
+        .expect("Should send an upgrade summary");
+    let UpgradeSummaryData {
+        upgraded,
+        not_upgradable,
+        not_installed,
+        failed,
+        skipped,
+        ..
+    } = summary;
+    assert_eq!(upgraded, ["b-fine"]);
+    assert_eq!(not_upgradable, ["c-fixed"]);
+    assert_eq!(not_installed, ["d-missing"]);
+    assert_eq!(failed, ["a-broken"]);
+    assert!(skipped.is_empty());
+}
+
+#[tokio::test]
+async fn test_service_upgrade_all_stop_on_error() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let service = upgrade_all_packages(&temp_dir, true);
+
+    // Act
+    let stream = service.upgrade_all().await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => assert!(msg.contains("3 skipped"), "{msg}"),
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert!(read_log(temp_dir.path().join("upgrade.log")).is_empty());
+}
+
+fn state_store(dir: &TempDir) -> JsonStateStore<RealFileSystem> {
+    create_test_state_store(&service_test_config_with_dir(dir.path()))
+}
+
+#[tokio::test]
+async fn test_service_install_records_direct_and_dependency_installs() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_uninstallable_package(&temp_dir, "lib", &[]);
+    let app_marker = create_uninstallable_package(&temp_dir, "app", &["lib"]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    let installs = state_store(&temp_dir).list_installs().unwrap();
+    let summary: Vec<_> = installs
+        .iter()
+        .map(|r| (r.package_name.as_str(), r.environment.as_str(), r.reason))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("app", "test", InstallReason::Direct),
+            ("lib", "test", InstallReason::Dependency),
+        ]
+    );
+    assert_eq!(installs[0].version, "1.0.0");
+    assert_eq!(
+        installs[0].install_command,
+        format!("touch {}", app_marker.display())
+    );
+}
+
+#[tokio::test]
+async fn test_service_install_keeps_direct_reason() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    let lib_marker = create_uninstallable_package(&temp_dir, "lib", &[]);
+    create_uninstallable_package(&temp_dir, "app", &["lib"]);
+    let service = create_service_test_service(&temp_dir);
+
+    let stream = service.install("lib", InstallOptions::default()).await;
+    assert_successful_operation(&collect_events(stream).await);
+    std::fs::remove_file(lib_marker).unwrap();
+
+    // Act: `lib` is now installed again, this time as a dependency
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    let lib = state_store(&temp_dir)
+        .get_install("lib", "test")
+        .unwrap()
+        .unwrap();
+    assert_eq!(lib.reason, InstallReason::Direct);
+}
+
+#[tokio::test]
+async fn test_service_uninstall_removes_install_record() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_uninstallable_package(&temp_dir, "test-package", &[]);
+    let service = create_service_test_service(&temp_dir);
+    let stream = service
+        .install("test-package", InstallOptions::default())
+        .await;
+    assert_successful_operation(&collect_events(stream).await);
+    let store = state_store(&temp_dir);
+    assert!(store.get_install("test-package", "test").unwrap().is_some());
+
+    // Act
+    let stream = service.uninstall("test-package").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    assert!(store.get_install("test-package", "test").unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_service_list_and_info_include_install_record() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_uninstallable_package(&temp_dir, "installed", &[]);
+    create_uninstallable_package(&temp_dir, "not-installed", &[]);
+    let service = create_service_test_service(&temp_dir);
+    let stream = service
+        .install("installed", InstallOptions::default())
+        .await;
+    assert_successful_operation(&collect_events(stream).await);
+
+    // Act
+    let list_events = collect_events(service.list().await.unwrap()).await;
+    let info_events = collect_events(service.info("installed").await.unwrap()).await;
+
+    // Assert
+    let package_list = list_events
+        .iter()
+        .find_map(|event| match event {
+            PackageEvent::PackageListLoaded { package_list, .. } => Some(package_list),
+            _ => None,
+        })
+        .expect("Expected a package list");
+    let records: HashMap<_, _> = package_list
+        .valid_packages
+        .iter()
+        .map(|item| (item.name.as_str(), item.install_record.is_some()))
+        .collect();
+    assert!(records["installed"]);
+    assert!(!records["not-installed"]);
+
+    let environment_status = info_events
+        .iter()
+        .find_map(|event| match event {
+            PackageEvent::EnvironmentStatusChecked {
+                environment_status, ..
+            } => Some(environment_status),
+            _ => None,
+        })
+        .expect("Expected an environment status");
+    let record = environment_status.install_record.as_ref().unwrap();
+    assert_eq!(record.reason, InstallReason::Direct);
+}
+
+/// Write a package whose install command logs to `install.log` and then fails,
+/// with `rollback` commands of its own
+fn create_rollback_package(dir: &TempDir, name: &str, rollback: &[&str], dependencies: &[&str]) {
+    let log = dir.path().join("install.log").display().to_string();
+    let rollback: Vec<String> = rollback
+        .iter()
+        .map(|cmd| cmd.replace("{log}", &log))
+        .collect();
+    let package = PackageBuilder::default()
+        .name(name)
+        .version("1.0.0")
+        .environment("test", |b| {
+            b.install(format!("echo install-{name} >> {log} && exit 1"))
+                .check_some("false")
+                .dependencies(dependencies.to_vec())
+                .rollback(rollback.clone())
+        })
+        .build();
+    create_package_file(dir, &package);
+}
+
+fn rollback_on_failure() -> InstallOptions {
+    InstallOptions {
+        rollback_on_failure: true,
+        ..InstallOptions::default()
+    }
+}
+
+fn rollback_data(events: &[PackageEvent]) -> Option<(&OperationInfo, &RollbackData)> {
+    events.iter().find_map(|event| match event {
+        PackageEvent::RollbackCompleted {
+            operation_info,
+            rollback,
+        } => Some((operation_info, rollback)),
+        _ => None,
+    })
+}
+
+#[tokio::test]
+async fn test_service_install_rolls_back_failed_install() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_rollback_package(
+        &temp_dir,
+        "test-package",
+        &["echo undo-1 >> {log}", "echo undo-2 >> {log}"],
+        &[],
+    );
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("test-package", rollback_on_failure()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => assert!(msg.ends_with("(rolled back)"), "{msg}"),
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert_eq!(
+        read_log(temp_dir.path().join("install.log")),
+        ["install-test-package", "undo-1", "undo-2"]
+    );
+
+    let (operation_info, rollback) = rollback_data(&events).expect("Expected a rollback");
+    assert!(matches!(
+        operation_info.operation_type,
+        OperationType::PackageRollback
+    ));
+    assert_eq!(
+        operation_info.parent_id,
+        Some(started_operation_id(&events))
+    );
+    assert!(rollback.succeeded());
+    assert_eq!(rollback.steps.len(), 2);
+}
+
+#[tokio::test]
+async fn test_service_install_without_rollback_on_failure() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_rollback_package(&temp_dir, "test-package", &["echo undo >> {log}"], &[]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .install("test-package", InstallOptions::default())
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert!(matches!(
+        get_operation_result(&events),
+        Some(OperationResult::Failure(_))
+    ));
+    assert_eq!(
+        read_log(temp_dir.path().join("install.log")),
+        ["install-test-package"]
+    );
+    assert!(rollback_data(&events).is_none());
+}
+
+#[tokio::test]
+async fn test_service_install_rollback_failure() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_rollback_package(
+        &temp_dir,
+        "test-package",
+        &["exit 1", "echo undo-2 >> {log}"],
+        &[],
+    );
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("test-package", rollback_on_failure()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => {
+            assert!(msg.ends_with("(rollback failed)"), "{msg}");
+        }
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert_eq!(
+        read_log(temp_dir.path().join("install.log")),
+        ["install-test-package"]
+    );
+
+    let (_, rollback) = rollback_data(&events).expect("Expected a rollback");
+    assert!(!rollback.succeeded());
+    assert!(matches!(
+        rollback.steps[0].result,
+        RollbackStepResult::Failed(_)
+    ));
+    assert_eq!(rollback.steps[1].result, RollbackStepResult::Skipped);
+
+    let rollback_error = events.iter().find_map(|event| match event {
+        PackageEvent::Error {
+            error: StreamedError::InstallError(err @ InstallError::RollbackFailed { .. }),
+            ..
+        } => Some(err),
+        _ => None,
+    });
+    match rollback_error {
+        Some(InstallError::RollbackFailed { command, .. }) => assert_eq!(command, "exit 1"),
+        other => panic!("Expected a rollback error, got: {other:?}"),
+    }
+}
+
+/// Write a package with a `pre_install` and `post_install` command, logging each
+/// command it runs to `install.log`; the `failing` hook exits unsuccessfully
+fn create_failing_hook_package(dir: &TempDir, name: &str, failing: &str) {
+    let log = dir.path().join("install.log");
+    let hook = |hook: &str| {
+        let exit = if hook == failing { " && exit 1" } else { "" };
+        format!("\"echo {hook} >> {log}{exit}\"", log = log.display())
+    };
+    let content = format!(
+        r#"name: "{name}"
+version: "1.0.0"
+environments:
+  test:
+    pre_install: [{pre}]
+    install: "echo install >> {log}"
+    post_install: [{post}]
+    check: "false"
+    rollback: ["echo undo >> {log}"]
+"#,
+        pre = hook("pre"),
+        post = hook("post"),
+        log = log.display(),
+    );
+    std::fs::write(dir.path().join(format!("{name}.yml")), content).unwrap();
+}
+
+#[tokio::test]
+async fn test_service_install_rolls_back_failed_pre_install() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_failing_hook_package(&temp_dir, "test-package", "pre");
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("test-package", rollback_on_failure()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => assert!(msg.ends_with("(rolled back)"), "{msg}"),
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert_eq!(
+        read_log(temp_dir.path().join("install.log")),
+        ["pre", "undo"]
+    );
+    assert!(rollback_data(&events).is_some());
+}
+
+#[tokio::test]
+async fn test_service_install_rolls_back_failed_post_install() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_failing_hook_package(&temp_dir, "test-package", "post");
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("test-package", rollback_on_failure()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => assert!(msg.ends_with("(rolled back)"), "{msg}"),
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert_eq!(
+        read_log(temp_dir.path().join("install.log")),
+        ["pre", "install", "post", "undo"]
+    );
+}
+
+#[tokio::test]
+async fn test_service_install_rolls_back_failed_dependency() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_rollback_package(&temp_dir, "lib", &["echo undo-lib >> {log}"], &[]);
+    create_package_with_dependencies(&temp_dir, "app", "true", "false", &["lib"]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("app", rollback_on_failure()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => {
+            assert!(
+                msg.contains("'lib'") && msg.ends_with("(rolled back)"),
+                "{msg}"
+            );
+        }
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+    assert_eq!(
+        read_log(temp_dir.path().join("install.log")),
+        ["install-lib", "undo-lib"]
+    );
+    let (_, rollback) = rollback_data(&events).expect("Expected a rollback");
+    assert_eq!(rollback.package_name, "lib");
+}
+
+/// Write a package whose install command logs to `install.log` and only succeeds
+/// once it has been run `succeed_on` times
+fn create_flaky_package(dir: &TempDir, name: &str, succeed_on: u32, retry_settings: &str) {
+    let log = dir.path().join("install.log");
+    let content = format!(
+        r#"name: "{name}"
+version: "1.0.0"
+environments:
+  test:
+    install: "echo attempt >> {log} && [ $(wc -l < {log}) -ge {succeed_on} ]"
+{retry_settings}"#,
+        log = log.display(),
+    );
+    std::fs::write(dir.path().join(format!("{name}.yml")), content).unwrap();
+}
+
+fn warning_messages(events: &[PackageEvent]) -> Vec<&str> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            PackageEvent::Warning { message, .. } => Some(message.as_ref()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_service_install_retries_failed_command() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_flaky_package(&temp_dir, "test-package", 3, "");
+    let config = AppConfigBuilder::default()
+        .environment("test")
+        .package_directory(temp_dir.path())
+        .use_colors(false)
+        .retries(3)
+        .retry_delay(0)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act
+    let stream = service
+        .install("test-package", InstallOptions::default())
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    assert_eq!(read_log(temp_dir.path().join("install.log")).len(), 3);
+
+    let retry_warnings: Vec<_> = warning_messages(&events)
+        .into_iter()
+        .filter(|message| message.starts_with("Attempt "))
+        .collect();
+    assert_eq!(retry_warnings.len(), 2, "{retry_warnings:?}");
+    assert!(retry_warnings[0].starts_with("Attempt 1/4 of `install` command failed"));
+    assert!(retry_warnings[1].starts_with("Attempt 2/4 of `install` command failed"));
+}
+
+#[tokio::test]
+async fn test_service_install_environment_retries_override_global() {
+    // Arrange: the global setting would retry enough times, but the package
+    // environment only allows one retry
+    let temp_dir = TempDir::new().unwrap();
+    create_flaky_package(
+        &temp_dir,
+        "test-package",
+        3,
+        "    retries: 1\n    retry_delay: 0\n",
+    );
+    let config = AppConfigBuilder::default()
+        .environment("test")
+        .package_directory(temp_dir.path())
+        .use_colors(false)
+        .retries(5)
+        .retry_delay(30)
+        .build();
+    let service = create_test_service_with_config(config);
+
+    // Act
+    let stream = service
+        .install("test-package", InstallOptions::default())
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert!(matches!(
+        get_operation_result(&events),
+        Some(OperationResult::Failure(_))
+    ));
+    assert_eq!(read_log(temp_dir.path().join("install.log")).len(), 2);
+    let warnings = warning_messages(&events);
+    assert!(
+        warnings
+            .iter()
+            .any(|message| message.starts_with("Attempt 1/2 of `install` command failed")),
+        "{warnings:?}"
+    );
+    assert!(
+        warnings
+            .iter()
+            .any(|message| message.ends_with("(attempt 2/2)")),
+        "{warnings:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_service_check_packages() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_test_package_file(&temp_dir, "installed", true);
+    create_package_with_dependencies(&temp_dir, "missing", "true", "false", &[]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .check_packages(&["missing".to_string(), "installed".to_string()])
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    let check_summary = events
+        .iter()
+        .find_map(|event| match event {
+            PackageEvent::CheckSummaryCompleted { check_summary, .. } => Some(check_summary),
+            _ => None,
+        })
+        .expect("Expected a check summary");
+    assert_eq!(check_summary.installed, ["installed"]);
+    assert_eq!(check_summary.missing, ["missing"]);
+    assert!(matches!(
+        get_operation_result(&events),
+        Some(OperationResult::Failure(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_service_check_packages_unknown_package() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_test_package_file(&temp_dir, "installed", true);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .check_packages(&["installed".to_string(), "nonexistent".to_string()])
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert: nothing is checked when a package can't be loaded
+    assert_failed_operation(&events);
+    assert!(
+        !events
+            .iter()
+            .any(|event| matches!(event, PackageEvent::CheckResultCompleted { .. }))
+    );
+}
+
+#[tokio::test]
+async fn test_service_install_packages_sends_summary() {
+    // Arrange: app -> lib, tool is already installed
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["lib", "app"] {
+        let marker = temp_dir.path().join(name);
+        let dependencies: &[&str] = if name == "app" { &["lib"] } else { &[] };
+        create_package_with_dependencies(
+            &temp_dir,
+            name,
+            &format!("touch {}", marker.display()),
+            &format!("test -f {}", marker.display()),
+            dependencies,
+        );
+    }
+    create_package_with_dependencies(&temp_dir, "tool", "true", "true", &[]);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .install_packages(
+            &["app".to_string(), "tool".to_string()],
+            InstallOptions::default(),
+        )
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    let install_summary = install_summary(&events);
+    assert_eq!(install_summary.environment, "test");
+    assert_eq!(install_summary.installed, ["lib", "app"]);
+    assert_eq!(install_summary.already_installed, ["tool"]);
+    assert!(install_summary.failed.is_empty());
+    assert!(install_summary.skipped.is_empty());
+}
+
+/// Create a package in the `test` environment whose hooks and install command log
+/// what they do to `install.log`; `fail` names the step that should exit 1
+fn create_hook_package(dir: &TempDir, name: &str, dependencies: &[&str], fail: Option<&str>) {
+    let log = dir.path().join("install.log");
+    let marker = dir.path().join(format!("{name}.installed"));
+    let step = |step: &str| {
+        let exit = if fail == Some(step) { " && exit 1" } else { "" };
+        format!("echo {step}-{name} >> {}{exit}", log.display())
+    };
+    let content = format!(
+        r#"name: "{name}"
+version: "1.0.0"
+environments:
+  test:
+    pre_install:
+      - "{pre}"
+    install: "{install} && touch {marker}"
+    post_install:
+      - "{post}"
+    check: "test -f {marker}"
+    dependencies: [{dependencies}]
+"#,
+        pre = step("pre"),
+        install = step("install"),
+        post = step("post"),
+        marker = marker.display(),
+        dependencies = dependencies.join(", "),
+    );
+    std::fs::write(dir.path().join(format!("{name}.yml")), content).unwrap();
+}
+
+fn hook_error(events: &[PackageEvent]) -> Option<&InstallError> {
+    events.iter().find_map(|event| match event {
+        PackageEvent::Error {
+            error: StreamedError::InstallError(err @ InstallError::HookFailed { .. }),
+            ..
+        } => Some(err),
+        _ => None,
+    })
+}
+
+#[tokio::test]
+async fn test_service_install_runs_hooks_around_install() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_hook_package(&temp_dir, "lib", &[], None);
+    create_hook_package(&temp_dir, "app", &["lib"], None);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.install("app", InstallOptions::default()).await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_successful_operation(&events);
+    assert_eq!(
+        read_log(temp_dir.path().join("install.log")),
+        [
+            "pre-lib",
+            "install-lib",
+            "post-lib",
+            "pre-app",
+            "install-app",
+            "post-app"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_service_install_pre_install_hook_failure() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_hook_package(&temp_dir, "test-package", &[], Some("pre"));
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .install("test-package", InstallOptions::default())
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_failed_operation(&events);
+    assert_eq!(
+        read_log(temp_dir.path().join("install.log")),
+        ["pre-test-package"]
+    );
+    match hook_error(&events) {
+        Some(InstallError::HookFailed { hook, .. }) => assert_eq!(*hook, InstallHook::PreInstall),
+        other => panic!("Expected a hook error, got: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_service_install_post_install_hook_failure() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_hook_package(&temp_dir, "lib", &[], Some("post"));
+    create_hook_package(&temp_dir, "app", &[], None);
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .install_packages(
+            &["lib".to_string(), "app".to_string()],
+            InstallOptions::default(),
+        )
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    assert_failed_operation(&events);
+    assert!(read_log(temp_dir.path().join("install.log")).contains(&"install-lib".to_string()));
+    match hook_error(&events) {
+        Some(InstallError::HookFailed {
+            package_name, hook, ..
+        }) => {
+            assert_eq!(package_name, "lib");
+            assert_eq!(*hook, InstallHook::PostInstall);
+        }
+        other => panic!("Expected a hook error, got: {other:?}"),
+    }
+}
+
+/// Create an installed package in the `test` environment whose version command
+/// prints `version_output` and that requires `required_version`
+fn create_versioned_package(
+    dir: &TempDir,
+    name: &str,
+    version_output: &str,
+    required_version: &str,
+) {
+    let content = format!(
+        r#"name: "{name}"
+version: "1.0.0"
+environments:
+  test:
+    install: "true"
+    check: "true"
+    version_command: "echo '{name} {version_output}'"
+    required_version: "{required_version}"
+"#
+    );
+    std::fs::write(dir.path().join(format!("{name}.yml")), content).unwrap();
+}
+
+#[tokio::test]
+async fn test_service_check_outdated_package() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_versioned_package(&temp_dir, "old-tool", "13.2.1", ">=14.0");
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.check("old-tool").await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    let check_result = events
+        .iter()
+        .find_map(|event| match event {
+            PackageEvent::CheckResultCompleted { check_result, .. } => Some(check_result),
+            _ => None,
+        })
+        .expect("Expected a check result");
+    match &check_result.result {
+        CheckResult::Outdated {
+            installed_version,
+            required_version,
+        } => {
+            assert_eq!(installed_version, "13.2.1");
+            assert_eq!(required_version, ">=14.0");
+        }
+        other => panic!("Expected an outdated result, got: {other:?}"),
+    }
+    match get_operation_result(&events) {
+        Some(OperationResult::Failure(msg)) => assert!(msg.contains("outdated"), "{msg}"),
+        other => panic!("Expected failure, got: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_service_check_packages_sorts_outdated() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_versioned_package(&temp_dir, "new-tool", "v14.1.0", ">=14.0");
+    create_versioned_package(&temp_dir, "old-tool", "13.2.1", ">=14.0");
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service
+        .check_packages(&["new-tool".to_string(), "old-tool".to_string()])
+        .await;
+    let events = collect_events(stream).await;
+
+    // Assert
+    let summary = check_summary(&events);
+    assert_eq!(summary.installed, ["new-tool"]);
+    assert_eq!(summary.outdated, ["old-tool"]);
+    assert!(matches!(
+        get_operation_result(&events),
+        Some(OperationResult::Failure(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_service_info_shows_installed_version() {
+    // Arrange
+    let temp_dir = TempDir::new().unwrap();
+    create_versioned_package(&temp_dir, "tool", "14.1.0", ">=14.0");
+    let service = create_service_test_service(&temp_dir);
+
+    // Act
+    let stream = service.info("tool").await.unwrap();
+    let events = collect_events(stream).await;
+
+    // Assert
+    let environment_status = events
+        .iter()
+        .find_map(|event| match event {
+            PackageEvent::EnvironmentStatusChecked {
+                environment_status, ..
+            } => Some(environment_status),
+            _ => None,
+        })
+        .expect("Expected an environment status");
+    assert_eq!(
+        environment_status.installed_version.as_deref(),
+        Some("14.1.0")
+    );
+    assert_eq!(
+        environment_status.required_version.as_deref(),
+        Some(">=14.0")
+    );
+}
@@ -4,6 +4,10 @@ use crate::constants::{SERVICE_TEST_ENV, TEST_ENV, TEST_PACKAGE_DIR};
 use selfie::config::{AppConfig, AppConfigBuilder};
 use std::path::Path;
 
+/// Directory, inside a test package directory, where test configs keep install state
+/// so tests never touch the real user data directory
+pub const STATE_DIR_NAME: &str = ".selfie";
+
 /// Creates a standard test configuration with colors disabled.
 /// This is the most commonly used config in CLI command tests.
 #[must_use]
@@ -11,6 +15,7 @@ pub fn test_config() -> AppConfig {
     AppConfigBuilder::default()
         .environment(TEST_ENV)
         .package_directory(TEST_PACKAGE_DIR)
+        .state_directory(Path::new(TEST_PACKAGE_DIR).join(STATE_DIR_NAME))
         .use_colors(false)
         .build()
 }
@@ -22,6 +27,7 @@ pub fn test_config_with_colors() -> AppConfig {
     AppConfigBuilder::default()
         .environment(TEST_ENV)
         .package_directory(TEST_PACKAGE_DIR)
+        .state_directory(Path::new(TEST_PACKAGE_DIR).join(STATE_DIR_NAME))
         .use_colors(true)
         .build()
 }
@@ -33,6 +39,7 @@ pub fn test_config_verbose() -> AppConfig {
     AppConfigBuilder::default()
         .environment(TEST_ENV)
         .package_directory(TEST_PACKAGE_DIR)
+        .state_directory(Path::new(TEST_PACKAGE_DIR).join(STATE_DIR_NAME))
         .use_colors(false)
         .verbose(true)
         .build()
@@ -45,6 +52,7 @@ pub fn test_config_for_env(environment: &str) -> AppConfig {
     AppConfigBuilder::default()
         .environment(environment)
         .package_directory(TEST_PACKAGE_DIR)
+        .state_directory(Path::new(TEST_PACKAGE_DIR).join(STATE_DIR_NAME))
         .use_colors(false)
         .build()
 }
@@ -55,6 +63,7 @@ pub fn test_config_with_dir<P: AsRef<Path>>(package_dir: P) -> AppConfig {
     AppConfigBuilder::default()
         .environment(TEST_ENV)
         .package_directory(package_dir.as_ref())
+        .state_directory(package_dir.as_ref().join(STATE_DIR_NAME))
         .use_colors(false)
         .build()
 }
@@ -68,6 +77,7 @@ pub fn test_config_with_dir_and_env<P: AsRef<Path>>(
     AppConfigBuilder::default()
         .environment(environment)
         .package_directory(package_dir.as_ref())
+        .state_directory(package_dir.as_ref().join(STATE_DIR_NAME))
         .use_colors(false)
         .build()
 }
@@ -78,6 +88,7 @@ pub fn service_test_config_with_dir<P: AsRef<Path>>(package_dir: P) -> AppConfig
     AppConfigBuilder::default()
         .environment(SERVICE_TEST_ENV)
         .package_directory(package_dir.as_ref())
+        .state_directory(package_dir.as_ref().join(STATE_DIR_NAME))
         .use_colors(false)
         .build()
 }
@@ -1,7 +1,11 @@
 //! Test package file creation helpers to eliminate duplication in service tests.
 
 use crate::constants::{SERVICE_TEST_ENV, TEST_ENV, TEST_VERSION};
-use std::{fs, path::PathBuf};
+use selfie::package::Package;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 use tempfile::TempDir;
 
 /// Creates a standard test package file with install and check commands.
@@ -17,6 +21,44 @@ pub fn create_test_package_file(dir: &TempDir, name: &str) -> PathBuf {
     create_package_file_with_check(dir, name, true)
 }
 
+/// Writes a package built with `PackageBuilder` to `<name>.yaml` in a directory.
+/// Serializing the package keeps its commands quoted correctly, whatever they contain.
+///
+/// # Example
+/// ```rust
+/// let package = PackageBuilder::default()
+///     .name("my-package")
+///     .version("1.0.0")
+///     .environment("test", |b| b.install("true"))
+///     .build();
+/// let package_path = create_package_file(&temp_dir, &package);
+/// ```
+pub fn create_package_file<P: AsRef<Path>>(dir: P, package: &Package) -> PathBuf {
+    let yaml = serde_yaml::to_string(package).unwrap();
+    fs::create_dir_all(dir.as_ref()).unwrap();
+
+    let file_path = dir.as_ref().join(format!("{}.yaml", package.name()));
+    fs::write(&file_path, yaml).unwrap();
+    file_path
+}
+
+/// Reads the lines test package commands have appended to a log file.
+/// A log that hasn't been written yet reads as empty.
+///
+/// # Example
+/// ```rust
+/// let log = read_log(temp_dir.path().join("install.log"));
+/// assert_eq!(log, ["lib", "app"]);
+/// ```
+#[must_use]
+pub fn read_log<P: AsRef<Path>>(path: P) -> Vec<String> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(ToString::to_string)
+        .collect()
+}
+
 /// Creates a test package file with optional check command.
 /// Gives you control over whether the package has a check command defined.
 ///
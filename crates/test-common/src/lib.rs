@@ -20,9 +20,9 @@ pub use events::{
     assert_failed_operation, assert_successful_operation, collect_events, get_operation_result,
 };
 pub use fixtures::{
-    create_invalid_package_file, create_package_file_with_check,
-    create_service_invalid_package_file, create_service_test_package_file,
-    create_test_package_file,
+    create_custom_package_file, create_invalid_package_file, create_package_file,
+    create_package_file_with_check, create_service_invalid_package_file,
+    create_service_test_package_file, create_test_package_file, read_log,
 };
 pub use package::{multi_env_test_package, simple_test_package, test_package_with_check};
 pub use service::{
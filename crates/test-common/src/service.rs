@@ -7,17 +7,37 @@ use selfie::{
     commands::shell::ShellCommandRunner,
     config::AppConfig,
     fs::real::RealFileSystem,
-    package::{repository::YamlPackageRepository, service::PackageServiceImpl},
+    package::{
+        repository::YamlPackageRepository, service::PackageServiceImpl, state::JsonStateStore,
+    },
 };
 use std::time::Duration;
 use tempfile::TempDir;
 
+/// The service type created by every helper in this module
+pub type TestService = PackageServiceImpl<
+    YamlPackageRepository<RealFileSystem>,
+    ShellCommandRunner,
+    JsonStateStore<RealFileSystem>,
+    RealFileSystem,
+>;
+
+/// Creates the state store for a test configuration, keeping state next to the
+/// test packages unless the config says otherwise
+#[must_use]
+pub fn create_test_state_store(config: &AppConfig) -> JsonStateStore<RealFileSystem> {
+    let state_directory = config.state_directory().cloned().unwrap_or_else(|| {
+        config
+            .package_directory()
+            .join(crate::config::STATE_DIR_NAME)
+    });
+    JsonStateStore::new(RealFileSystem, &state_directory)
+}
+
 /// Creates a test service with real filesystem and default settings.
 /// This is the most commonly used service setup for integration tests.
 #[must_use]
-pub fn create_test_service(
-    temp_dir: &TempDir,
-) -> PackageServiceImpl<YamlPackageRepository<RealFileSystem>, ShellCommandRunner> {
+pub fn create_test_service(temp_dir: &TempDir) -> TestService {
     let config = test_config_with_dir(temp_dir.path());
     create_test_service_with_config(config)
 }
@@ -25,36 +45,30 @@ pub fn create_test_service(
 /// Creates a test service with a specific configuration.
 /// Useful when you need custom config settings like different environments.
 #[must_use]
-pub fn create_test_service_with_config(
-    config: AppConfig,
-) -> PackageServiceImpl<YamlPackageRepository<RealFileSystem>, ShellCommandRunner> {
+pub fn create_test_service_with_config(config: AppConfig) -> TestService {
     let fs = RealFileSystem;
     let repo = YamlPackageRepository::new(fs, config.package_directory().clone());
     let runner = ShellCommandRunner::new("/bin/sh", Duration::from_secs(30));
-    PackageServiceImpl::new(repo, runner, config)
+    let state_store = create_test_state_store(&config);
+    PackageServiceImpl::new(repo, runner, state_store, RealFileSystem, config)
 }
 
 /// Creates a test service with custom command timeout.
 /// Useful for testing timeout scenarios or when you need longer-running commands.
 #[must_use]
-pub fn create_test_service_with_timeout(
-    temp_dir: &TempDir,
-    timeout: Duration,
-) -> PackageServiceImpl<YamlPackageRepository<RealFileSystem>, ShellCommandRunner> {
+pub fn create_test_service_with_timeout(temp_dir: &TempDir, timeout: Duration) -> TestService {
     let config = test_config_with_dir(temp_dir.path());
     let fs = RealFileSystem;
     let repo = YamlPackageRepository::new(fs, config.package_directory().clone());
     let runner = ShellCommandRunner::new("/bin/sh", timeout);
-    PackageServiceImpl::new(repo, runner, config)
+    let state_store = create_test_state_store(&config);
+    PackageServiceImpl::new(repo, runner, state_store, RealFileSystem, config)
 }
 
 /// Creates a test service for a specific environment.
 /// Useful for testing environment-specific behavior.
 #[must_use]
-pub fn create_test_service_for_env(
-    temp_dir: &TempDir,
-    environment: &str,
-) -> PackageServiceImpl<YamlPackageRepository<RealFileSystem>, ShellCommandRunner> {
+pub fn create_test_service_for_env(temp_dir: &TempDir, environment: &str) -> TestService {
     let config = test_config_with_dir_and_env(temp_dir.path(), environment);
     create_test_service_with_config(config)
 }
@@ -62,20 +76,23 @@ pub fn create_test_service_for_env(
 /// Creates the standard CLI service setup used in command handlers.
 /// This matches the exact pattern used in CLI commands for consistency.
 #[must_use]
-pub fn create_cli_service(
-    config: &AppConfig,
-) -> PackageServiceImpl<YamlPackageRepository<RealFileSystem>, ShellCommandRunner> {
+pub fn create_cli_service(config: &AppConfig) -> TestService {
     let repo = YamlPackageRepository::new(RealFileSystem, config.package_directory().clone());
     let command_runner = ShellCommandRunner::new("/bin/sh", config.command_timeout());
-    PackageServiceImpl::new(repo, command_runner, config.clone())
+    let state_store = create_test_state_store(config);
+    PackageServiceImpl::new(
+        repo,
+        command_runner,
+        state_store,
+        RealFileSystem,
+        config.clone(),
+    )
 }
 
 /// Creates a test service specifically for service layer integration tests.
 /// Uses the correct "test" environment expected by service tests.
 #[must_use]
-pub fn create_service_test_service(
-    temp_dir: &TempDir,
-) -> PackageServiceImpl<YamlPackageRepository<RealFileSystem>, ShellCommandRunner> {
+pub fn create_service_test_service(temp_dir: &TempDir) -> TestService {
     let config = service_test_config_with_dir(temp_dir.path());
     create_test_service_with_config(config)
 }